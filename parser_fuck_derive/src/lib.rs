@@ -0,0 +1,298 @@
+//! `#[derive(Parse)]` for [parser_fuck](https://docs.rs/parser_fuck), use it with the `derive` feature
+//! Structs parse their fields in order, enums try their variants in order and take the first that passes
+//! Whitespace before every token and field is skipped
+//! Nodes carry no location unless asked, wrap a type in `Located` to get it, or mark a `LocRange` field with `#[parse(loc)]`
+//! Left recursive types never finish, like `enum E { Add(Box<E>, ...) }` trying `E` first
+//!
+//! Attributes on the type, a variant or a field, tokens come before what they are on:
+//! - `#[parse(keyword = "let")]` a keyword not followed by a letter, digit or `_`
+//! - `#[parse(token = "=")]` any text
+//! - `#[parse(between("(", ")"))]` text around
+//! - `#[parse(suffix = ";")]` text after
+//!
+//! Only on a field:
+//! - `#[parse(sep_by = ",")]` a `Vec` of zero or more items separated by the text
+//! - `#[parse(with = path)]` parse with a parser instead of the field type
+//! - `#[parse(loc)]` a `LocRange` of the node, from after the whitespace to its closing or suffix text, the same as `Located`, nothing is parsed
+//! ## example
+//! ```
+//! use parser_fuck::*;
+//!
+//! #[derive(Parse, Debug, PartialEq)]
+//! enum Expr {
+//!     Num(i64),
+//!     Call {
+//!         name: Ident,
+//!         #[parse(between("(", ")"), sep_by = ",")]
+//!         args: Vec<Expr>,
+//!     },
+//!     Var(Ident),
+//! }
+//!
+//! #[derive(Parse, Debug, PartialEq)]
+//! #[parse(keyword = "let", suffix = ";")]
+//! struct Let {
+//!     name: Ident,
+//!     #[parse(token = "=")]
+//!     value: Expr,
+//!     #[parse(loc)]
+//!     loc: LocRange,
+//! }
+//!
+//! let r = Let::parse("let a = f(1, x);".span()).unwrap();
+//! assert_eq!(r.name, Ident("a".to_string()));
+//! assert_eq!(
+//!     r.value,
+//!     Expr::Call {
+//!         name: Ident("f".to_string()),
+//!         args: vec![Expr::Num(1), Expr::Var(Ident("x".to_string()))],
+//!     }
+//! );
+//! assert_eq!(r.loc.to.offset, 15);
+//! assert_eq!(Let::parse("leta = 1;".span()), None);
+//!
+//! let r = <Located<Expr>>::parse(" x".span()).unwrap();
+//! assert_eq!(*r, Expr::Var(Ident("x".to_string())));
+//! assert_eq!(r.loc.from.offset, 1);
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parenthesized, parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields,
+    GenericParam, LitStr, Path, Result, Token,
+};
+
+/// Derive `parser_fuck::Parse`, see the [crate docs](index.html)
+#[proc_macro_derive(Parse, attributes(parse))]
+pub fn derive_parse(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct Attrs {
+    keyword: Option<LitStr>,
+    token: Option<LitStr>,
+    between: Option<(LitStr, LitStr)>,
+    suffix: Option<LitStr>,
+    sep_by: Option<LitStr>,
+    with: Option<Path>,
+    loc: bool,
+}
+impl Attrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut r = Attrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("parse")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("keyword") {
+                    r.keyword = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("token") {
+                    r.token = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("suffix") {
+                    r.suffix = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("sep_by") {
+                    r.sep_by = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("with") {
+                    r.with = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("loc") {
+                    r.loc = true;
+                } else if meta.path.is_ident("between") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let open = content.parse()?;
+                    content.parse::<Token![,]>()?;
+                    let close = content.parse()?;
+                    r.between = Some((open, close));
+                } else {
+                    return Err(meta.error("unknown parse attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(r)
+    }
+    /// Only allowed on fields
+    fn check_container(&self, attrs: &[Attribute]) -> Result<()> {
+        if self.sep_by.is_some() || self.with.is_some() || self.loc {
+            let attr = attrs.iter().find(|a| a.path().is_ident("parse")).unwrap();
+            return Err(Error::new_spanned(
+                attr,
+                "sep_by, with and loc are only allowed on fields",
+            ));
+        }
+        Ok(())
+    }
+    /// A loc field parses nothing
+    fn check_loc(&self, attrs: &[Attribute]) -> Result<()> {
+        let other = self.keyword.is_some()
+            || self.token.is_some()
+            || self.between.is_some()
+            || self.suffix.is_some()
+            || self.sep_by.is_some()
+            || self.with.is_some();
+        if self.loc && other {
+            let attr = attrs.iter().find(|a| a.path().is_ident("parse")).unwrap();
+            return Err(Error::new_spanned(
+                attr,
+                "loc can not be used with other parse attributes",
+            ));
+        }
+        Ok(())
+    }
+    fn before(&self) -> TokenStream2 {
+        let keyword = self.keyword.iter();
+        let token = self.token.iter();
+        let open = self.between.iter().map(|(open, _)| open);
+        quote! {
+            #(::parser_fuck::__derive::keyword(&__input, #keyword)?;)*
+            #(::parser_fuck::__derive::token(&__input, #token)?;)*
+            #(::parser_fuck::__derive::token(&__input, #open)?;)*
+        }
+    }
+    fn after(&self) -> TokenStream2 {
+        let close = self.between.iter().map(|(_, close)| close);
+        let suffix = self.suffix.iter();
+        quote! {
+            #(::parser_fuck::__derive::token(&__input, #close)?;)*
+            #(::parser_fuck::__derive::token(&__input, #suffix)?;)*
+        }
+    }
+}
+
+/// Statements parsing the fields, statements filling the `LocRange` fields, and the expression building the value
+fn fields(
+    fields: &Fields,
+    ctor: TokenStream2,
+) -> Result<(TokenStream2, TokenStream2, TokenStream2)> {
+    let mut parsed = vec![];
+    let mut locs = vec![];
+    let mut vars = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let var = format_ident!("__f{}", i);
+        let ty = &field.ty;
+        let attrs = Attrs::parse(&field.attrs)?;
+        attrs.check_loc(&field.attrs)?;
+        if attrs.loc {
+            locs.push(quote! { let #var: #ty = __loc; });
+        } else {
+            let value = if let Some(with) = &attrs.with {
+                quote! { ::parser_fuck::__derive::with(&__input, #with)? }
+            } else if let Some(sep) = &attrs.sep_by {
+                quote! { ::parser_fuck::__derive::sep_by(&__input, #sep, ::parser_fuck::__derive::item)? }
+            } else {
+                quote! { ::parser_fuck::__derive::item(&__input)? }
+            };
+            let (before, after) = (attrs.before(), attrs.after());
+            parsed.push(quote! {
+                #before
+                let #var: #ty = #value;
+                #after
+            });
+        }
+        vars.push(var);
+    }
+    let build = match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| f.ident.as_ref().unwrap());
+            quote! { #ctor { #(#names: #vars),* } }
+        }
+        Fields::Unnamed(_) => quote! { #ctor ( #(#vars),* ) },
+        Fields::Unit => ctor,
+    };
+    let locs = quote! {
+        let __loc = ::parser_fuck::__derive::loc(&__input, __start);
+        #(#locs)*
+    };
+    Ok((quote! { #(#parsed)* }, locs, build))
+}
+
+fn derive(mut input: DeriveInput) -> Result<TokenStream2> {
+    let attrs = Attrs::parse(&input.attrs)?;
+    attrs.check_container(&input.attrs)?;
+    let (before, after) = (attrs.before(), attrs.after());
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (parsed, locs, build) = fields(&data.fields, quote!(Self))?;
+            quote! {
+                #before
+                #parsed
+                #after
+                #locs
+                ::std::option::Option::Some(#build)
+            }
+        }
+        Data::Enum(data) => {
+            let mut variants = vec![];
+            for variant in &data.variants {
+                let attrs = Attrs::parse(&variant.attrs)?;
+                attrs.check_container(&variant.attrs)?;
+                let (v_before, v_after) = (attrs.before(), attrs.after());
+                let ident = &variant.ident;
+                let (parsed, locs, build) = fields(&variant.fields, quote!(Self::#ident))?;
+                variants.push(quote! {
+                    let __r = (|| -> ::std::option::Option<Self> {
+                        #v_before
+                        #parsed
+                        #v_after
+                        #after
+                        #locs
+                        ::std::option::Option::Some(#build)
+                    })();
+                    if let ::std::option::Option::Some(__r) = __r {
+                        break 'choice __r;
+                    }
+                    ::parser_fuck::__derive::back(&__input, __choice);
+                });
+            }
+            quote! {
+                #before
+                let __choice = ::parser_fuck::__derive::start(&__input);
+                let __r = 'choice: {
+                    #(#variants)*
+                    return ::std::option::Option::None;
+                };
+                ::std::option::Option::Some(__r)
+            }
+        }
+        Data::Union(data) => {
+            return Err(Error::new_spanned(
+                data.union_token,
+                "Parse can not be derived for unions",
+            ))
+        }
+    };
+
+    let type_params: Vec<_> = input
+        .generics
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            GenericParam::Type(t) => Some(t.ident.clone()),
+            _ => None,
+        })
+        .collect();
+    let where_clause = input.generics.make_where_clause();
+    for t in type_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#t: ::parser_fuck::Parse));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::parser_fuck::Parse for #name #ty_generics #where_clause {
+            #[allow(unused_variables, clippy::redundant_closure_call)]
+            fn parse(__input: ::parser_fuck::CharSpan<'_>) -> ::std::option::Option<Self> {
+                let __start = ::parser_fuck::__derive::start(&__input);
+                #body
+            }
+        }
+    })
+}
@@ -0,0 +1,128 @@
+use parser_fuck::*;
+
+#[derive(Parse, Debug, PartialEq)]
+enum Value {
+    #[parse(keyword = "null")]
+    Null,
+    Bool(bool),
+    Num(f64),
+    #[parse(between("[", "]"))]
+    List(#[parse(sep_by = ",")] Vec<Value>),
+    Str(#[parse(with = string)] String),
+}
+
+fn string(input: CharSpan) -> Option<String> {
+    one('"')
+        .and(satisfy(|c: Char| c != '"').many())
+        .and(one('"'))
+        .map(|((a, _), b)| input.com_string(a.end..b.start).unwrap())
+        .parse(input.ref_clone())
+}
+
+#[derive(Parse, Debug, PartialEq)]
+#[parse(between("(", ")"))]
+struct Pair<A, B>(A, #[parse(token = ",")] B, #[parse(loc)] LocRange);
+
+#[derive(Parse, Debug, PartialEq)]
+struct Program {
+    items: Vec<Item>,
+}
+
+#[derive(Parse, Debug, PartialEq)]
+#[parse(suffix = ";")]
+enum Item {
+    #[parse(keyword = "use")]
+    Use {
+        path: Ident,
+        #[parse(loc)]
+        loc: LocRange,
+    },
+    #[parse(keyword = "const")]
+    Const {
+        name: Ident,
+        #[parse(token = "=")]
+        value: Value,
+    },
+}
+
+#[test]
+fn test_enum() {
+    let r = Value::parse(r#"[1, null, [true, "a b"], nullx]"#.span());
+    println!("{:?}", r);
+    assert_eq!(r, None);
+
+    let r = Value::parse(r#" [1, null, [true, "a b"]]"#.span());
+    assert_eq!(
+        r,
+        Some(Value::List(vec![
+            Value::Num(1.0),
+            Value::Null,
+            Value::List(vec![Value::Bool(true), Value::Str("a b".to_string())]),
+        ]))
+    );
+}
+
+#[test]
+fn test_generic() {
+    let r = <Pair<i32, Ident>>::parse("( -1 , a )".span()).unwrap();
+    println!("{:?}", r);
+    assert_eq!((r.0, &r.1), (-1, &Ident("a".to_string())));
+    assert_eq!((r.2.from.offset, r.2.to.offset), (0, 9));
+
+    let r = <Pair<bool, bool>>::parse("(true false)".span());
+    assert_eq!(r, None);
+}
+
+#[test]
+fn test_loc() {
+    let code = "use a;\n  const b = 1; ";
+    let r = Program::parse(code.span()).unwrap();
+    println!("{:?}", r);
+    assert_eq!(r.items.len(), 2);
+    match &r.items[0] {
+        Item::Use { path, loc } => {
+            assert_eq!(path, &Ident("a".to_string()));
+            assert_eq!((loc.from.offset, loc.to.offset), (0, 5));
+        }
+        _ => panic!(),
+    }
+    assert_eq!(
+        r.items[1],
+        Item::Const {
+            name: Ident("b".to_string()),
+            value: Value::Num(1.0)
+        }
+    );
+}
+
+mod range {
+    /// Not the crate's LocRange, parsed like any other field
+    #[derive(parser_fuck::Parse, Debug, PartialEq)]
+    pub struct LocRange(pub u32, pub u32);
+}
+
+#[derive(Parse, Debug, PartialEq)]
+struct Lines {
+    lines: range::LocRange,
+}
+
+#[test]
+fn test_located() {
+    let r = <Vec<Located<Item>>>::parse("use a;\n  const b = 1; ".span()).unwrap();
+    println!("{:?}", r);
+    assert_eq!((r[1].loc.from.offset, r[1].loc.to.offset), (9, 20));
+    assert!(matches!(*r[1], Item::Const { .. }));
+
+    match &*r[0] {
+        Item::Use { loc, .. } => assert_eq!(*loc, r[0].loc),
+        _ => panic!(),
+    }
+
+    let r = Lines::parse("1 2".span());
+    assert_eq!(
+        r,
+        Some(Lines {
+            lines: range::LocRange(1, 2)
+        })
+    );
+}
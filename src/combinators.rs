@@ -1,28 +1,45 @@
 mod and;
 mod and_then;
+mod count;
+mod cst;
 mod dyns;
+mod indent;
 mod iter;
+mod label;
 mod many;
 mod map;
 mod may;
+mod memo;
 mod not;
 mod one;
 mod or;
 mod or_else;
 mod or_trans;
+mod profile;
 mod satisfy;
+pub(crate) mod session;
 mod sub;
+mod trace;
+mod try_map;
 pub use and::*;
 pub use and_then::*;
+pub use count::*;
+pub use cst::*;
 pub use dyns::*;
+pub use indent::*;
 pub use iter::*;
+pub use label::*;
 pub use many::*;
 pub use map::*;
 pub use may::*;
+pub use memo::*;
 pub use not::*;
 pub use one::*;
 pub use or::*;
 pub use or_else::*;
 pub use or_trans::*;
+pub use profile::*;
 pub use satisfy::*;
 pub use sub::*;
+pub use trace::*;
+pub use try_map::*;
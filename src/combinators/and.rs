@@ -6,7 +6,7 @@ use std::marker::PhantomData;
 pub struct And<A, B, I = ()> {
     a: A,
     b: B,
-    _i: PhantomData<I>,
+    _i: PhantomData<fn(I)>,
 }
 impl<I: TimeTravel, A, B> And<A, B, I>
 where
@@ -35,6 +35,9 @@ where
         let b = self.b.parse(input)?;
         Some((a, b))
     }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::seq(self.a.describe(), self.b.describe())
+    }
 }
 
 #[macro_export]
@@ -1,4 +1,3 @@
-use crate::common::cell::*;
 use crate::*;
 use std::marker::PhantomData;
 
@@ -6,18 +5,18 @@ use std::marker::PhantomData;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct AndThen<B: Parser<I>, I: TimeTravel, F> {
     base: B,
-    f: ExtRefCell<F>,
-    _i: PhantomData<I>,
+    f: F,
+    _i: PhantomData<fn(I)>,
 }
 impl<B: Parser<I>, I: TimeTravel, U, F> AndThen<B, I, F>
 where
     U: Parser<I>,
-    F: FnMut(B::Output) -> U,
+    F: Fn(B::Output) -> U,
 {
     pub fn new(base: B, f: F) -> Self {
         Self {
             base,
-            f: ExtRefCell::new(f),
+            f,
             _i: PhantomData,
         }
     }
@@ -25,17 +24,22 @@ where
 impl<B: Parser<I>, I: TimeTravel, U, F> Parser<I> for AndThen<B, I, F>
 where
     U: Parser<I>,
-    F: FnMut(B::Output) -> U,
+    F: Fn(B::Output) -> U,
 {
     type Output = U::Output;
 
     fn parse(&self, mut input: I) -> Option<Self::Output> {
         let base = self.base.parse(input.ref_clone())?;
-        let f = unsafe { self.f.get_mut() };
-        let then: U = f(base);
+        let then: U = (self.f)(base);
         input.re_ready();
         then.parse(input)
     }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::seq(
+            self.base.describe(),
+            GrammarNode::Opaque("and_then".to_string()),
+        )
+    }
 }
 
 #[cfg(test)]
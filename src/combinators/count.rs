@@ -0,0 +1,324 @@
+use crate::*;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// Items parsed a known number of times
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Counted<T> {
+    pub items: Vec<T>,
+    /// Range of each item
+    pub ranges: Vec<Range<usize>>,
+    /// Range of all items
+    pub range: Range<usize>,
+}
+
+/// Fewer items than the count
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct CountError {
+    pub expected: usize,
+    pub found: usize,
+    /// Index where the next item failed
+    pub at: usize,
+    /// The item failed because the input ended
+    pub eof: bool,
+}
+impl Display for CountError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.eof {
+            write!(
+                f,
+                "expected {} items, found {} before the end of input at {}",
+                self.expected, self.found, self.at
+            )
+        } else {
+            write!(
+                f,
+                "expected {} items, found {} and item {} failed at {}",
+                self.expected,
+                self.found,
+                self.found + 1,
+                self.at
+            )
+        }
+    }
+}
+impl Error for CountError {}
+
+/// Parse exactly n items
+fn count_items<I: Memoizable, P: Parser<I>>(
+    item: &P,
+    n: usize,
+    mut input: I,
+) -> Result<Counted<P::Output>, CountError> {
+    let start = input.save();
+    // n may come from the input, don't trust it for the capacity
+    let mut items = Vec::with_capacity(n.min(64));
+    let mut ranges = Vec::with_capacity(n.min(64));
+    for found in 0..n {
+        input.re_ready();
+        let from = input.save();
+        let peak = input.swap_peak(0);
+        let r = item.parse(input.ref_clone());
+        let read = input.peak();
+        input.swap_peak(peak.max(read));
+        match r {
+            Some(r) => {
+                let end = input.consumed_end();
+                ranges.push(from..end);
+                items.push(r);
+            }
+            None => {
+                let eof = read > 0 && input.get(read - 1).is_none();
+                input.back(from);
+                return Err(CountError {
+                    expected: n,
+                    found,
+                    at: from,
+                    eof,
+                });
+            }
+        }
+    }
+    let end = ranges.last().map(|r| r.end).unwrap_or(start);
+    Ok(Counted {
+        items,
+        ranges,
+        range: start..end,
+    })
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Take n items of any kind, see [take_n_items](fn.take_n_items.html)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TakeN<I> {
+    n: usize,
+    _i: PhantomData<fn(I)>,
+}
+impl<I> TakeN<I> {
+    #[inline]
+    pub fn new(n: usize) -> Self {
+        Self { n, _i: PhantomData }
+    }
+}
+impl<I: Memoizable> Parser<I> for TakeN<I> {
+    type Output = Result<Counted<I::Item>, CountError>;
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        let any = |mut input: I| {
+            let r = input.next()?;
+            input.do_ready();
+            Some(r)
+        };
+        Some(count_items(&any, self.n, input))
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(GrammarNode::Class(".".to_string()), self.n, Some(self.n))
+    }
+}
+
+/// Take the next n items, like n bytes of a binary input  
+/// Err if the input ends early
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let code = "abc".span();
+/// let r = take_n_items(2).parse(code.ref_clone()).unwrap().unwrap();
+/// assert_eq!(r.range, 0..2);
+/// assert_eq!(r.items[1], 'b');
+///
+/// let e = take_n_items(2).parse(code).unwrap().unwrap_err();
+/// assert_eq!(e, CountError { expected: 2, found: 1, at: 3, eof: true });
+/// ```
+#[inline]
+pub fn take_n_items<I>(n: usize) -> TakeN<I> {
+    TakeN::new(n)
+}
+
+/// Read a length, then exactly that many items, see [length_value](fn.length_value.html)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LengthValue<L, P, I> {
+    len: L,
+    item: P,
+    _i: PhantomData<fn(I)>,
+}
+impl<L, P, I> LengthValue<L, P, I> {
+    #[inline]
+    pub fn new(len: L, item: P) -> Self {
+        Self {
+            len,
+            item,
+            _i: PhantomData,
+        }
+    }
+}
+impl<L, P, I> Parser<I> for LengthValue<L, P, I>
+where
+    I: Memoizable,
+    L: Parser<I, Output = usize>,
+    P: Parser<I>,
+{
+    type Output = Result<Counted<P::Output>, CountError>;
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        let n = self.len.parse(input.ref_clone())?;
+        Some(count_items(&self.item, n, input))
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::seq(
+            self.len.describe(),
+            GrammarNode::repeat(self.item.describe(), 0, None),
+        )
+    }
+}
+
+/// Read a length, then exactly that many items  
+/// None if the length fails, Err if an item fails or the input ends early
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// fn number(input: CharSpan) -> Option<usize> {
+///     satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false))
+///         .many1()
+///         .map(|r| input.com_string(range_of_many1(r)).unwrap().parse().unwrap())
+///         .parse(input.ref_clone())
+/// }
+/// // netstring
+/// let len = || number.and(one(':')).map(|(n, _)| n);
+/// let x = length_value(len(), satisfy(|_: Char| true)).and(one(','));
+/// let code = "5:hello,".span();
+/// let (r, _) = x.parse(code.ref_clone()).unwrap();
+/// assert_eq!(code.com_string(r.unwrap().range), Some("hello".to_string()));
+///
+/// let r = length_value(len(), satisfy(|_: Char| true)).parse("5:hi,".span());
+/// assert!(r.unwrap().unwrap_err().eof);
+/// ```
+#[inline]
+pub fn length_value<L, P, I>(len: L, item: P) -> LengthValue<L, P, I> {
+    LengthValue::new(len, item)
+}
+
+/// Parse a header, then the count it gives of items, see [count_from](fn.count_from.html)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CountFrom<H, F, P, I> {
+    header: H,
+    f: F,
+    item: P,
+    _i: PhantomData<fn(I)>,
+}
+impl<H, F, P, I> CountFrom<H, F, P, I> {
+    #[inline]
+    pub fn new(header: H, f: F, item: P) -> Self {
+        Self {
+            header,
+            f,
+            item,
+            _i: PhantomData,
+        }
+    }
+}
+impl<H, F, P, I> Parser<I> for CountFrom<H, F, P, I>
+where
+    I: Memoizable,
+    H: Parser<I>,
+    F: Fn(&H::Output) -> usize,
+    P: Parser<I>,
+{
+    type Output = Result<(H::Output, Counted<P::Output>), CountError>;
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        let header = self.header.parse(input.ref_clone())?;
+        let n = (self.f)(&header);
+        Some(count_items(&self.item, n, input).map(|c| (header, c)))
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::seq(
+            self.header.describe(),
+            GrammarNode::repeat(self.item.describe(), 0, None),
+        )
+    }
+}
+
+/// Parse a header, then repeat the item the count f takes from it  
+/// None if the header fails, Err if an item fails or the input ends early
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let header = satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false))
+///     .map(|r| r.start);
+/// let x = count_from(header, |_: &usize| 2, substr("ab"));
+/// let r = x.parse("7abab".span()).unwrap().unwrap();
+/// assert_eq!(r.1.ranges, vec![1..3, 3..5]);
+///
+/// let e = x.parse("7abx".span()).unwrap().unwrap_err();
+/// assert_eq!(e.to_string(), "expected 2 items, found 1 and item 2 failed at 3");
+/// ```
+#[inline]
+pub fn count_from<H, F, P, I>(header: H, f: F, item: P) -> CountFrom<H, F, P, I> {
+    CountFrom::new(header, f, item)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::vec::IntoIter;
+
+    type Bytes = Span<IntoIter<u8>>;
+
+    fn byte(mut input: Bytes) -> Option<u8> {
+        let b = input.next()?;
+        input.do_ready();
+        Some(b)
+    }
+
+    #[test]
+    fn test_binary() {
+        // count, then (len, bytes) records
+        let record = length_value(byte.map(usize::from), byte);
+        let x = count_from(byte, |n: &u8| *n as usize, record);
+
+        let span = Span::new(vec![2, 2, 10, 20, 1, 30].into_iter());
+        let r = x.parse(span.ref_clone());
+        println!("{:?}", r);
+        let (n, records) = r.unwrap().unwrap();
+        assert_eq!(n, 2);
+        assert_eq!(records.ranges, vec![1..4, 4..6]);
+        let values: Vec<Vec<u8>> = records
+            .items
+            .into_iter()
+            .map(|r| r.unwrap().items)
+            .collect();
+        assert_eq!(values, vec![vec![10, 20], vec![30]]);
+        assert_eq!(span.save(), 6);
+    }
+
+    #[test]
+    fn test_eof() {
+        let x = length_value(byte.map(usize::from), byte);
+        let span = Span::new(vec![3, 1, 2].into_iter());
+        let r = x.parse(span.ref_clone());
+        assert_eq!(
+            r,
+            Some(Err(CountError {
+                expected: 3,
+                found: 2,
+                at: 3,
+                eof: true
+            }))
+        );
+
+        let r = take_n_items(0).parse(Span::new(vec![1u8].into_iter()));
+        assert_eq!(
+            r,
+            Some(Ok(Counted {
+                items: vec![],
+                ranges: vec![],
+                range: 0..0
+            }))
+        );
+        assert_eq!(x.parse(Span::new(vec![].into_iter())), None);
+    }
+}
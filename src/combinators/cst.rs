@@ -0,0 +1,263 @@
+use crate::combinators::session;
+use crate::*;
+use std::any::Any;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+/// A finished token or node, ranges are item indices
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum CstEvent<K> {
+    Token {
+        kind: K,
+        trivia: bool,
+        start: usize,
+        end: usize,
+    },
+    Node {
+        kind: K,
+        start: usize,
+        end: usize,
+        children: Vec<CstEvent<K>>,
+    },
+}
+impl<K> CstEvent<K> {
+    #[inline]
+    pub fn start(&self) -> usize {
+        match self {
+            CstEvent::Token { start, .. } => *start,
+            CstEvent::Node { start, .. } => *start,
+        }
+    }
+    #[inline]
+    pub fn end(&self) -> usize {
+        match self {
+            CstEvent::Token { end, .. } => *end,
+            CstEvent::Node { end, .. } => *end,
+        }
+    }
+}
+
+/// The finished subtrees of the current parse, in order
+#[derive(Debug)]
+pub(crate) struct CstEvents<K> {
+    pub forest: Vec<CstEvent<K>>,
+}
+impl<K: 'static> CstEvents<K> {
+    /// Drop what a failed branch left behind, everything ending after `at` is from one
+    #[inline]
+    fn truncate(&mut self, at: usize) -> usize {
+        while self.forest.last().map(|e| e.end() > at).unwrap_or(false) {
+            self.forest.pop();
+        }
+        self.forest.len()
+    }
+}
+
+/// The events of the running session, boxed since their kind is generic
+#[derive(Default)]
+struct AnyEvents(Option<Box<dyn Any>>);
+
+thread_local! {
+    static CST: RefCell<Option<AnyEvents>> = const { RefCell::new(None) };
+}
+
+/// Collect the tokens and nodes of the parsers run in f
+pub(crate) fn cst_session<K: 'static, R>(f: impl FnOnce() -> R) -> (R, Vec<CstEvent<K>>) {
+    let mut events = AnyEvents(Some(Box::new(CstEvents::<K> { forest: vec![] })));
+    let r = session::run(&CST, &mut events, f);
+    let forest = events
+        .0
+        .and_then(|e| e.downcast::<CstEvents<K>>().ok())
+        .map(|e| e.forest)
+        .unwrap_or_default();
+    (r, forest)
+}
+
+/// Call f with the events of the current session, if there is one for K
+#[inline]
+fn with_events<K: 'static, R>(f: impl FnOnce(&mut CstEvents<K>) -> R) -> Option<R> {
+    CST.with(|c| {
+        let mut c = c.borrow_mut();
+        let events = c.as_mut()?.0.as_mut()?.downcast_mut::<CstEvents<K>>()?;
+        Some(f(events))
+    })
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Mark what the subparser consumed as a token of the [CST](struct.SyntaxNode.html)  
+/// Tokens are leaves, nodes and tokens inside it are ignored
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Token<B: Parser<I>, I: TimeTravel, K> {
+    base: B,
+    kind: K,
+    trivia: bool,
+    _i: PhantomData<fn(I)>,
+}
+impl<B: Parser<I>, I: TimeTravel, K> Token<B, I, K> {
+    #[inline]
+    pub fn new(base: B, kind: K, trivia: bool) -> Self {
+        Self {
+            base,
+            kind,
+            trivia,
+            _i: PhantomData,
+        }
+    }
+}
+impl<B: Parser<I>, I: Memoizable, K: Copy + 'static> Parser<I> for Token<B, I, K> {
+    type Output = B::Output;
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        let start = input.save();
+        let cp = match with_events::<K, _>(|e| e.truncate(start)) {
+            Some(cp) => cp,
+            None => return self.base.parse(input),
+        };
+        let r = self.base.parse(input.ref_clone());
+        let end = input.consumed_end();
+        with_events::<K, _>(|e| {
+            e.forest.truncate(cp);
+            if r.is_some() && end > start {
+                e.forest.push(CstEvent::Token {
+                    kind: self.kind,
+                    trivia: self.trivia,
+                    start,
+                    end,
+                });
+            }
+        });
+        r
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
+}
+
+/// Wrap the tokens and nodes of the subparser in a node of the [CST](struct.SyntaxNode.html)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Node<B: Parser<I>, I: TimeTravel, K> {
+    base: B,
+    kind: K,
+    _i: PhantomData<fn(I)>,
+}
+impl<B: Parser<I>, I: TimeTravel, K> Node<B, I, K> {
+    #[inline]
+    pub fn new(base: B, kind: K) -> Self {
+        Self {
+            base,
+            kind,
+            _i: PhantomData,
+        }
+    }
+}
+impl<B: Parser<I>, I: Memoizable, K: Copy + 'static> Parser<I> for Node<B, I, K> {
+    type Output = B::Output;
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        let start = input.save();
+        let cp = match with_events::<K, _>(|e| e.truncate(start)) {
+            Some(cp) => cp,
+            None => return self.base.parse(input),
+        };
+        let r = self.base.parse(input.ref_clone());
+        let end = input.consumed_end();
+        with_events::<K, _>(|e| {
+            if r.is_some() {
+                let children = e.forest.split_off(cp.min(e.forest.len()));
+                e.forest.push(CstEvent::Node {
+                    kind: self.kind,
+                    start,
+                    end: end.max(start),
+                    children,
+                });
+            } else {
+                e.forest.truncate(cp);
+            }
+        });
+        r
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let code = "ab";
+        let span = code.span();
+        let x = one('a')
+            .token(1)
+            .and(one('x').token(2))
+            .or(one('a').token(3).and(one('b').token(4)))
+            .node(0);
+
+        let (r, events) = cst_session::<i32, _>(|| x.parse(span));
+        println!("{:?}", events);
+        assert_eq!(r, Some((0..1, 1..2)));
+        assert_eq!(
+            events,
+            vec![CstEvent::Node {
+                kind: 0,
+                start: 0,
+                end: 2,
+                children: vec![
+                    CstEvent::Token {
+                        kind: 3,
+                        trivia: false,
+                        start: 0,
+                        end: 1
+                    },
+                    CstEvent::Token {
+                        kind: 4,
+                        trivia: false,
+                        start: 1,
+                        end: 2
+                    },
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_eof() {
+        let x = substr("ab").token(1);
+        let (r, events) = cst_session::<i32, _>(|| x.parse("ab".span()));
+        assert_eq!(r, Some(0..2));
+        assert_eq!(
+            events,
+            vec![CstEvent::Token {
+                kind: 1,
+                trivia: false,
+                start: 0,
+                end: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn test_nested() {
+        let x = one('a').token(1);
+        let ((inner, panicked), outer) = cst_session::<i32, _>(|| {
+            let (_, inner) = cst_session::<i32, _>(|| x.parse("a".span()));
+            let panicked = std::panic::catch_unwind(|| {
+                cst_session::<i32, _>(|| {
+                    x.parse("a".span());
+                    panic!()
+                })
+            })
+            .is_err();
+            x.parse("a".span());
+            (inner, panicked)
+        });
+        assert!(panicked);
+        assert_eq!(inner.len(), 1);
+        assert_eq!(outer.len(), 1);
+    }
+}
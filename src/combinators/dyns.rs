@@ -1,40 +1,212 @@
 use crate::*;
 use std::fmt;
 use std::fmt::Debug;
-use std::sync::Arc;
+use std::marker::PhantomData;
+use std::sync::{Arc, OnceLock, Weak};
 
 /// Wrap to dynamic
 #[derive(Clone)]
-pub struct Dyn<I: TimeTravel, O = ()> {
-    base: Arc<dyn Parser<I, Output = O>>,
+pub struct Dyn<'a, I: TimeTravel, O = ()> {
+    base: Arc<dyn Parser<I, Output = O> + 'a>,
 }
-impl<I: TimeTravel, O> Dyn<I, O> {
+impl<'a, I: TimeTravel, O> Dyn<'a, I, O> {
     #[inline]
-    pub fn new<T: Parser<I, Output = O> + 'static>(base: T) -> Self {
+    pub fn new<T: Parser<I, Output = O> + 'a>(base: T) -> Self {
         Self {
             base: Arc::new(base),
         }
     }
 }
-impl<I: TimeTravel, O> From<Arc<dyn Parser<I, Output = O>>> for Dyn<I, O> {
-    fn from(base: Arc<dyn Parser<I, Output = O>>) -> Self {
+impl<'a, I: TimeTravel, O> From<Arc<dyn Parser<I, Output = O> + 'a>> for Dyn<'a, I, O> {
+    fn from(base: Arc<dyn Parser<I, Output = O> + 'a>) -> Self {
         Self { base }
     }
 }
-impl<I: TimeTravel, O> Parser<I> for Dyn<I, O> {
+impl<'a, I: TimeTravel, O> Parser<I> for Dyn<'a, I, O> {
     type Output = O;
 
     #[inline]
     fn parse(&self, input: I) -> Option<Self::Output> {
         self.base.parse(input)
     }
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        self.base.name()
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
 }
-impl<I: TimeTravel, O> Debug for Dyn<I, O> {
+impl<'a, I: TimeTravel, O> Debug for Dyn<'a, I, O> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Dyn").field("base", &"...").finish()
     }
 }
 
+/// The handle a [recursive](fn.recursive.html) parser gets to itself, only a weak reference so there is no cycle
+struct Recur<P> {
+    this: Weak<P>,
+}
+impl<P: Parser<I>, I: TimeTravel> Parser<I> for Recur<P> {
+    type Output = P::Output;
+
+    #[inline]
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        let this = self
+            .this
+            .upgrade()
+            .expect("recursive parser used after it was dropped");
+        this.parse(input)
+    }
+    /// A reference by name, describing the parser again would never end
+    fn describe(&self) -> GrammarNode {
+        let name = self.this.upgrade().and_then(|p| p.name().map(String::from));
+        GrammarNode::Ref(name.unwrap_or_else(|| "recursive".to_string()))
+    }
+}
+
+/// Build a parser that refers to itself  
+/// f gets a handle to the parser it returns, the handle only holds a weak reference, so dropping the result frees everything  
+/// Parsing with the handle after the result is dropped panics
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// // nested parentheses, the depth is the output
+/// let nest = recursive(|this: Dyn<CharSpan, usize>| {
+///     one('(')
+///         .and(this.may())
+///         .and(one(')'))
+///         .map(|((_, d), _)| d.map(|d| d + 1).unwrap_or(1))
+/// });
+/// assert_eq!(nest.parse("((()))".span()), Some(3));
+/// assert_eq!(nest.parse("(()".span()), None);
+/// ```
+pub fn recursive<'a, I, O, F, P>(f: F) -> Dyn<'a, I, O>
+where
+    I: TimeTravel + 'a,
+    O: 'a,
+    F: FnOnce(Dyn<'a, I, O>) -> P,
+    P: Parser<I, Output = O> + 'a,
+{
+    let base: Arc<P> = Arc::new_cyclic(|this| f(Dyn::new(Recur { this: this.clone() })));
+    Dyn { base }
+}
+
+/// Build the parser on the first parse, see [lazy](fn.lazy.html)
+pub struct Lazy<F, P, I> {
+    f: F,
+    base: OnceLock<P>,
+    _i: PhantomData<fn(I)>,
+}
+impl<F, P, I> Lazy<F, P, I>
+where
+    F: Fn() -> P,
+{
+    #[inline]
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            base: OnceLock::new(),
+            _i: PhantomData,
+        }
+    }
+}
+impl<F, P, I> Parser<I> for Lazy<F, P, I>
+where
+    I: TimeTravel,
+    F: Fn() -> P,
+    P: Parser<I>,
+{
+    type Output = P::Output;
+
+    #[inline]
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        self.base.get_or_init(&self.f).parse(input)
+    }
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        self.base.get_or_init(&self.f).name()
+    }
+    /// A reference by name like [recursive](fn.recursive.html), the parser may be built from itself
+    fn describe(&self) -> GrammarNode {
+        match self.name() {
+            Some(name) => GrammarNode::Ref(name.to_string()),
+            None => GrammarNode::Opaque("lazy".to_string()),
+        }
+    }
+}
+impl<F, P: Debug, I> Debug for Lazy<F, P, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lazy")
+            .field("base", &self.base.get())
+            .finish()
+    }
+}
+
+/// Build the parser with f on the first parse, then reuse it  
+/// A function returning a parser can refer to itself through `lazy`, the recursion only goes as deep as the input
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// fn list<'a>() -> Dyn<'a, CharSpan<'a>, usize> {
+///     one('[')
+///         .and(lazy(list).many())
+///         .and(one(']'))
+///         .map(|((_, v), _)| v.into_iter().sum::<usize>() + 1)
+///         .dyns()
+/// }
+/// assert_eq!(list().parse("[[][[]]]".span()), Some(4));
+/// ```
+#[inline]
+pub fn lazy<F, P, I>(f: F) -> Lazy<F, P, I>
+where
+    F: Fn() -> P,
+{
+    Lazy::new(f)
+}
+
+/// Wrap to dynamic, shareable between threads  
+/// One grammar can be built once and used by many threads at the same time
+#[derive(Clone)]
+pub struct SyncDyn<I: TimeTravel, O = ()> {
+    base: Arc<dyn Parser<I, Output = O> + Send + Sync>,
+}
+impl<I: TimeTravel, O> SyncDyn<I, O> {
+    #[inline]
+    pub fn new<T: Parser<I, Output = O> + Send + Sync + 'static>(base: T) -> Self {
+        Self {
+            base: Arc::new(base),
+        }
+    }
+}
+impl<I: TimeTravel, O> From<Arc<dyn Parser<I, Output = O> + Send + Sync>> for SyncDyn<I, O> {
+    fn from(base: Arc<dyn Parser<I, Output = O> + Send + Sync>) -> Self {
+        Self { base }
+    }
+}
+impl<I: TimeTravel, O> Parser<I> for SyncDyn<I, O> {
+    type Output = O;
+
+    #[inline]
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        self.base.parse(input)
+    }
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        self.base.name()
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
+}
+impl<I: TimeTravel, O> Debug for SyncDyn<I, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncDyn").field("base", &"...").finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::*;
@@ -51,4 +223,63 @@ mod tests {
         println!("{:?}", r);
         assert_eq!(r, Some(0..3));
     }
+
+    #[test]
+    fn test_sync() {
+        use std::thread;
+
+        let d = substr("asd").and(one('1').many1()).sync_dyns();
+
+        let handles: Vec<_> = ["asd1", "asd11", "asd111", "asd1111"]
+            .iter()
+            .map(|code| {
+                let d = d.clone();
+                thread::spawn(move || d.parse(code.span()).map(|(_, v)| v.len()))
+            })
+            .collect();
+        let r: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        println!("{:?}", r);
+        assert_eq!(r, vec![Some(1), Some(2), Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn test_recursive() {
+        use std::rc::Rc;
+
+        // grammar from configuration, borrowed and not 'static
+        let config = ["(".to_string(), ")".to_string()];
+        let marker = Rc::new(());
+        let x = {
+            let (open, close) = (&*config[0], &*config[1]);
+            let marker = marker.clone();
+            recursive(move |this: Dyn<CharSpan, usize>| {
+                substr(open)
+                    .and(this.many())
+                    .and(substr(close))
+                    .map(move |((_, v), _)| {
+                        let _ = &marker;
+                        v.into_iter().sum::<usize>() + 1
+                    })
+            })
+        };
+        let r = x.parse("(()(()))".span());
+        println!("{:?}", r);
+        assert_eq!(r, Some(4));
+        assert_eq!(Rc::strong_count(&marker), 2);
+        drop(x);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn test_lazy() {
+        let built = std::cell::Cell::new(0);
+        let x = lazy(|| {
+            built.set(built.get() + 1);
+            substr("asd")
+        });
+        assert_eq!(built.get(), 0);
+        assert_eq!(x.parse("asd".span()), Some(0..3));
+        assert_eq!(x.parse("asd".span()), Some(0..3));
+        assert_eq!(built.get(), 1);
+    }
 }
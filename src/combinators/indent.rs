@@ -0,0 +1,500 @@
+use crate::*;
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+/// Indentation of a line
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub struct Indent {
+    /// `Loc::col` of the first non-whitespace item, see [tab_width](struct.CharCharsOptions.html#method.tab_width)
+    pub col: usize,
+    /// The leading spaces and tabs
+    pub text: String,
+}
+impl Indent {
+    #[inline]
+    pub fn new(col: usize, text: impl Into<String>) -> Self {
+        Self {
+            col,
+            text: text.into(),
+        }
+    }
+}
+
+/// How tabs and spaces are compared
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum IndentRule {
+    /// Compare the columns, tabs count as the tab width
+    Columns,
+    /// Like Columns, but a line indented with both tabs and spaces is an error
+    NoMix,
+    /// Compare the text, deeper must extend the enclosing indentation, like Python
+    Prefix,
+}
+impl Default for IndentRule {
+    #[inline]
+    fn default() -> Self {
+        IndentRule::Columns
+    }
+}
+impl IndentRule {
+    /// Compare an indentation with the enclosing one  
+    /// None if they are inconsistent
+    pub fn compare(&self, a: &Indent, enclosing: &Indent) -> Option<Ordering> {
+        match self {
+            IndentRule::Columns => Some(a.col.cmp(&enclosing.col)),
+            IndentRule::NoMix => {
+                if a.text.contains(' ') && a.text.contains('\t') {
+                    None
+                } else {
+                    Some(a.col.cmp(&enclosing.col))
+                }
+            }
+            IndentRule::Prefix => {
+                if a.text == enclosing.text {
+                    Some(Ordering::Equal)
+                } else if a.text.starts_with(&enclosing.text) {
+                    Some(Ordering::Greater)
+                } else if enclosing.text.starts_with(&a.text) {
+                    Some(Ordering::Less)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Stack of the enclosing indentations  
+/// Use as the state of a [StatefulSpan](struct.StatefulSpan.html) so it is restored on backtracking,
+/// or implement [IndentState](trait.IndentState.html) for your own state
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub struct IndentStack {
+    stack: Vec<Indent>,
+    rule: IndentRule,
+}
+impl IndentStack {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    #[inline]
+    pub fn with_rule(rule: IndentRule) -> Self {
+        Self {
+            stack: vec![],
+            rule,
+        }
+    }
+    #[inline]
+    pub fn rule(&self) -> IndentRule {
+        self.rule
+    }
+    /// The innermost indentation, column 0 if empty
+    #[inline]
+    pub fn current(&self) -> Indent {
+        self.stack.last().cloned().unwrap_or_default()
+    }
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+    #[inline]
+    pub fn push(&mut self, indent: Indent) {
+        self.stack.push(indent)
+    }
+    #[inline]
+    pub fn pop(&mut self) -> Option<Indent> {
+        self.stack.pop()
+    }
+    /// Compare an indentation with the innermost one
+    #[inline]
+    pub fn compare(&self, indent: &Indent) -> Option<Ordering> {
+        self.rule.compare(indent, &self.current())
+    }
+}
+
+/// A state that has an indentation stack
+pub trait IndentState {
+    fn indents(&self) -> &IndentStack;
+    fn indents_mut(&mut self) -> &mut IndentStack;
+}
+impl IndentState for IndentStack {
+    #[inline]
+    fn indents(&self) -> &IndentStack {
+        self
+    }
+    #[inline]
+    fn indents_mut(&mut self) -> &mut IndentStack {
+        self
+    }
+}
+
+/// Skip blank lines and the leading whitespace of the next line  
+/// Fails if no line starts here or at the end
+fn line_indent<I: TimeTravel>(input: &mut I) -> Option<Indent>
+where
+    I::Item: GetChar + GetLoc,
+{
+    let from = input.save();
+    let mut new_line = from == 0
+        || input
+            .get(from - 1)
+            .map(|c| c.get_char() == '\n')
+            .unwrap_or(false);
+    let mut text = String::new();
+    loop {
+        let c = input.get(input.save())?;
+        match c.get_char() {
+            ' ' | '\t' => text.push(c.get_char()),
+            '\n' => {
+                new_line = true;
+                text.clear();
+            }
+            _ if new_line => return Some(Indent::new(c.loc().col, text)),
+            _ => return None,
+        }
+        input.next();
+        input.do_ready();
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Check the indentation of the next line against the innermost one, see [same_indent](fn.same_indent.html)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CheckIndent<I, S> {
+    ord: Ordering,
+    _i: PhantomData<fn(I, S)>,
+}
+impl<I, S> CheckIndent<I, S> {
+    #[inline]
+    pub fn new(ord: Ordering) -> Self {
+        Self {
+            ord,
+            _i: PhantomData,
+        }
+    }
+}
+impl<I, S> Parser<StatefulSpan<I, S>> for CheckIndent<I, S>
+where
+    I: Memoizable,
+    I::Item: GetChar + GetLoc,
+    S: IndentState + Clone,
+{
+    type Output = Indent;
+
+    fn parse(&self, mut input: StatefulSpan<I, S>) -> Option<Self::Output> {
+        let indent = line_indent(&mut input)?;
+        let ord = input.with_state(|s| s.indents().compare(&indent))?;
+        if ord == self.ord {
+            Some(indent)
+        } else {
+            None
+        }
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::Opaque(
+            match self.ord {
+                Ordering::Equal => "same_indent",
+                _ => "indent_gt",
+            }
+            .to_string(),
+        )
+    }
+}
+
+/// Skip to the next line, pass if it is as indented as the innermost block  
+/// At the top level the block is column 0
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let code = StatefulSpan::new("a\n\n  b\nc".span(), IndentStack::new());
+/// let x = one('a').and(same_indent()).and(one('b'));
+/// assert_eq!(x.parse(code.clone()), None);
+///
+/// let x = one('a').and(indent_gt()).and(one('b')).and(same_indent()).and(one('c'));
+/// assert!(x.parse(code.ref_clone()).is_some());
+/// ```
+#[inline]
+pub fn same_indent<I, S>() -> CheckIndent<I, S> {
+    CheckIndent::new(Ordering::Equal)
+}
+
+/// Skip to the next line, pass if it is more indented than the innermost block  
+/// See [same_indent](fn.same_indent.html)
+#[inline]
+pub fn indent_gt<I, S>() -> CheckIndent<I, S> {
+    CheckIndent::new(Ordering::Greater)
+}
+
+/// Lines of items more indented than the enclosing block, see [indented_block](fn.indented_block.html)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IndentedBlock<P, I, S> {
+    item: P,
+    _i: PhantomData<fn(I, S)>,
+}
+impl<P, I, S> IndentedBlock<P, I, S> {
+    #[inline]
+    pub fn new(item: P) -> Self {
+        Self {
+            item,
+            _i: PhantomData,
+        }
+    }
+}
+impl<P, I, S> IndentedBlock<P, I, S>
+where
+    P: Parser<StatefulSpan<I, S>>,
+    I: Memoizable,
+    I::Item: GetChar + GetLoc,
+    S: IndentState + Clone,
+{
+    fn block(&self, mut input: StatefulSpan<I, S>) -> Option<Vec<P::Output>> {
+        let indent = indent_gt().parse(input.ref_clone())?;
+        input.update(|s| s.indents_mut().push(indent));
+        let mut items = vec![self.item.parse(input.ref_clone())?];
+        loop {
+            input.re_ready();
+            let from = input.save();
+            let item = same_indent()
+                .parse(input.ref_clone())
+                .and_then(|_| self.item.parse(input.ref_clone()));
+            match item {
+                Some(item) => items.push(item),
+                None => {
+                    input.back(from);
+                    break;
+                }
+            }
+        }
+        input.update(|s| s.indents_mut().pop());
+        Some(items)
+    }
+}
+impl<P, I, S> Parser<StatefulSpan<I, S>> for IndentedBlock<P, I, S>
+where
+    P: Parser<StatefulSpan<I, S>>,
+    I: Memoizable,
+    I::Item: GetChar + GetLoc,
+    S: IndentState + Clone,
+{
+    type Output = Vec<P::Output>;
+
+    fn parse(&self, mut input: StatefulSpan<I, S>) -> Option<Self::Output> {
+        let from = input.save();
+        let r = self.block(input.ref_clone());
+        if r.is_none() {
+            input.back(from);
+        }
+        r
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(self.item.describe(), 1, None)
+    }
+}
+
+/// Skip to the next line and parse a block of items, one per line  
+/// The first line sets the indentation, it must be deeper than the enclosing block and the other lines must match it  
+/// Items may contain nested blocks
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// type In<'a> = StatefulSpan<CharSpan<'a>, IndentStack>;
+/// fn item(input: In) -> Option<usize> {
+///     one('-')
+///         .and(indented_block(item).may())
+///         .map(|(_, b)| b.map(|b| b.iter().sum::<usize>()).unwrap_or(0) + 1)
+///         .parse(input)
+/// }
+/// let code = StatefulSpan::new("list\n  -\n    -\n    -\n  -\n-".span(), IndentStack::new());
+/// let x = substr("list").and(indented_block(item));
+/// let r = x.parse(code.ref_clone());
+/// assert_eq!(r.map(|(_, b)| b), Some(vec![3, 1]));
+/// assert_eq!(code.save(), 24);
+/// assert_eq!(code.state().depth(), 0);
+/// ```
+#[inline]
+pub fn indented_block<P, I, S>(item: P) -> IndentedBlock<P, I, S> {
+    IndentedBlock::new(item)
+}
+
+/// Items that may continue on more indented lines, see [line_fold](fn.line_fold.html)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LineFold<P, I, S> {
+    item: P,
+    _i: PhantomData<fn(I, S)>,
+}
+impl<P, I, S> LineFold<P, I, S> {
+    #[inline]
+    pub fn new(item: P) -> Self {
+        Self {
+            item,
+            _i: PhantomData,
+        }
+    }
+}
+impl<P, I, S> Parser<StatefulSpan<I, S>> for LineFold<P, I, S>
+where
+    P: Parser<StatefulSpan<I, S>>,
+    I: Memoizable,
+    I::Item: GetChar + GetLoc,
+    S: IndentState + Clone,
+{
+    type Output = Vec<P::Output>;
+
+    fn parse(&self, mut input: StatefulSpan<I, S>) -> Option<Self::Output> {
+        let mut items = vec![self.item.parse(input.ref_clone())?];
+        loop {
+            input.re_ready();
+            let from = input.save();
+            let mut spaces = 0;
+            while let Some(c) = input.get(input.save()) {
+                match c.get_char() {
+                    ' ' | '\t' => {
+                        input.next();
+                        input.do_ready();
+                        spaces += 1;
+                    }
+                    '\n' => {
+                        if indent_gt().parse(input.ref_clone()).is_none() {
+                            input.back(from);
+                            return Some(items);
+                        }
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+            if spaces == 0 && input.save() == from {
+                return Some(items);
+            }
+            match self.item.parse(input.ref_clone()) {
+                Some(item) => items.push(item),
+                None => {
+                    input.back(from);
+                    return Some(items);
+                }
+            }
+        }
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(self.item.describe(), 1, None)
+    }
+}
+
+/// Items separated by whitespace, that may continue on lines more indented than the innermost block  
+/// Like a YAML plain scalar or a Haskell line fold
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let code = StatefulSpan::new("a b\n c\n d\ne".span(), IndentStack::new());
+/// let word = satisfy(|c: Char| c.char(|c, _| c.is_alphabetic()).unwrap_or(false));
+/// let r = line_fold(word).parse(code.ref_clone());
+/// assert_eq!(r, Some(vec![0..1, 2..3, 5..6, 8..9]));
+/// assert_eq!(code.save(), 9);
+/// ```
+#[inline]
+pub fn line_fold<P, I, S>(item: P) -> LineFold<P, I, S> {
+    LineFold::new(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    type In<'a> = StatefulSpan<CharSpan<'a>, IndentStack>;
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Yaml {
+        Str(String),
+        Map(Vec<(String, Yaml)>),
+    }
+
+    fn word(input: In) -> Option<String> {
+        satisfy(|c: Char| c.char(|c, _| c.is_alphanumeric()).unwrap_or(false))
+            .many1()
+            .map(|r| input.com_string(range_of_many1(r)).unwrap())
+            .parse(input.ref_clone())
+    }
+    fn entry(input: In) -> Option<(String, Yaml)> {
+        word.and(one(':'))
+            .and(
+                one(' ')
+                    .and(line_fold(word))
+                    .map(|(_, w)| Yaml::Str(w.join(" ")))
+                    .or(indented_block(entry).map(Yaml::Map)),
+            )
+            .map(|((k, _), v)| (k, v))
+            .parse(input)
+    }
+    fn doc(input: In) -> Option<Yaml> {
+        same_indent()
+            .and(entry)
+            .map(|(_, e)| e)
+            .many1()
+            .map(Yaml::Map)
+            .parse(input)
+    }
+    fn map(v: Vec<(&str, Yaml)>) -> Yaml {
+        Yaml::Map(v.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+    fn str(s: &str) -> Yaml {
+        Yaml::Str(s.to_string())
+    }
+
+    #[test]
+    fn test_yaml() {
+        let code = "a:\n  b: 1\n  c:\n\n    d: x\n     y\n  e: 3\nf: 4";
+        let span = StatefulSpan::new(code.span(), IndentStack::new());
+        let r = doc(span.ref_clone());
+        println!("{:?}", r);
+        assert_eq!(
+            r,
+            Some(map(vec![
+                (
+                    "a",
+                    map(vec![
+                        ("b", str("1")),
+                        ("c", map(vec![("d", str("x y"))])),
+                        ("e", str("3")),
+                    ])
+                ),
+                ("f", str("4")),
+            ]))
+        );
+        assert_eq!(span.save(), code.chars().count());
+        assert_eq!(span.state().depth(), 0);
+    }
+
+    #[test]
+    fn test_bad_dedent() {
+        let code = "a:\n    b: 1\n  c: 2";
+        let span = StatefulSpan::new(code.span(), IndentStack::new());
+        let r = doc(span.ref_clone());
+        assert_eq!(r, Some(map(vec![("a", map(vec![("b", str("1"))]))])));
+        assert_eq!(span.save(), 11);
+    }
+
+    #[test]
+    fn test_tabs() {
+        let code = "a:\n\tb: 1\n    c: 2";
+        let chars = CharChars::with_options(code.chars(), CharCharsOptions::new().tab_width(4));
+
+        let span = StatefulSpan::new(chars.clone().span(), IndentStack::new());
+        let r = doc(span.ref_clone());
+        assert_eq!(
+            r,
+            Some(map(vec![(
+                "a",
+                map(vec![("b", str("1")), ("c", str("2"))])
+            )]))
+        );
+
+        let span = StatefulSpan::new(chars.span(), IndentStack::with_rule(IndentRule::Prefix));
+        let r = doc(span.ref_clone());
+        assert_eq!(r, Some(map(vec![("a", map(vec![("b", str("1"))]))])));
+
+        let code = "a:\n \tb: 1";
+        let span = StatefulSpan::new(code.span(), IndentStack::with_rule(IndentRule::NoMix));
+        assert_eq!(doc(span.ref_clone()), None);
+    }
+}
@@ -5,7 +5,7 @@ use std::marker::PhantomData;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Iter<A, I = ()> {
     a: A,
-    _i: PhantomData<I>,
+    _i: PhantomData<fn(I)>,
 }
 impl<I: TimeTravel, A> Iter<A, I>
 where
@@ -27,18 +27,44 @@ where
         Some(ParserIter {
             a: self.a.clone(),
             input,
+            auto_commit: false,
         })
     }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(self.a.describe(), 0, None)
+    }
 }
+/// Iterator of outputs, ends at the first failure and the input is left before it
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ParserIter<A, I> {
     a: A,
     input: I,
+    auto_commit: bool,
 }
 impl<A, I> ParserIter<A, I> {
     pub fn input(&self) -> &I {
         &self.input
     }
+    /// Commit the input after every successful item  
+    /// Lets the iterator run over endless input in constant memory,
+    /// but ranges of previous items can no longer be read from the input
+    pub fn auto_commit(mut self) -> Self {
+        self.auto_commit = true;
+        self
+    }
+    /// Collect the outputs up to the first failure into any Default + Extend, like String or HashMap  
+    /// Borrows so the input can still be checked afterwards, it is left ready after the last output
+    pub fn collect_to<C>(&mut self) -> C
+    where
+        Self: Iterator,
+        I: TimeTravel,
+        C: Default + Extend<<Self as Iterator>::Item>,
+    {
+        let mut c = C::default();
+        c.extend(&mut *self);
+        self.input.re_ready();
+        c
+    }
 }
 impl<A, I: TimeTravel> Iterator for ParserIter<A, I>
 where
@@ -49,8 +75,10 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         let from = self.input.save();
         let a = self.a.parse(self.input.ref_clone());
-        if let None = a {
+        if a.is_none() {
             self.input.back(from);
+        } else if self.auto_commit {
+            self.input.commit();
         }
         a
     }
@@ -120,4 +148,19 @@ mod tests {
         println!("{:?}", v);
         assert_eq!(v, vec![2..3]);
     }
+
+    #[test]
+    fn test_collect_into() {
+        let code = "abc1";
+        let span = code.span();
+        let x = satisfy(|c: Char| c.char(|c, _| c.is_alphabetic()).unwrap_or(false))
+            .map(|r| span.com_string(r).unwrap())
+            .iter();
+
+        let mut r = x.parse(span.ref_clone()).unwrap();
+        let s: String = r.collect_to();
+        assert_eq!(s, "abc");
+        assert_eq!(r.next(), None);
+        assert_eq!(r.input().save(), 3);
+    }
 }
@@ -0,0 +1,55 @@
+use crate::*;
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+/// Give a parser a name for diagnostics, see [label](trait.Parser.html#method.label)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Label<B: Parser<I>, I: TimeTravel> {
+    pub(crate) base: B,
+    pub(crate) name: Cow<'static, str>,
+    _i: PhantomData<fn(I)>,
+}
+impl<B: Parser<I>, I: TimeTravel> Label<B, I> {
+    #[inline]
+    pub fn new(base: B, name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            base,
+            name: name.into(),
+            _i: PhantomData,
+        }
+    }
+}
+impl<B: Parser<I>, I: TimeTravel> Parser<I> for Label<B, I> {
+    type Output = B::Output;
+
+    #[inline]
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        self.base.parse(input)
+    }
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::Label {
+            name: self.name.to_string(),
+            node: Box::new(self.base.describe()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test() {
+        let x = substr("asd").label("asd");
+        assert_eq!(x.name(), Some("asd"));
+        assert_eq!(x.parse("asd".span()), Some(0..3));
+
+        let d = x.label(format!("{}!", "asd")).dyns();
+        assert_eq!(d.name(), Some("asd!"));
+        assert_eq!(Parser::<CharSpan>::name(&substr("asd")), None);
+    }
+}
@@ -1,13 +1,48 @@
 use crate::*;
 use std::marker::PhantomData;
 
+#[inline]
+fn check_min_max(min: usize, max: Option<usize>) {
+    if let Some(max) = max {
+        if max < min {
+            panic!("max must be >= min")
+        }
+    }
+}
+
+/// Parse repeatedly and pass each output to f  
+/// Fails if there are fewer than min or more than max, shared by Many and its variants
+pub(crate) fn many_with<I: TimeTravel, A: Parser<I>>(
+    a: &A,
+    min: usize,
+    max: Option<usize>,
+    mut input: I,
+    mut f: impl FnMut(A::Output),
+) -> Option<usize> {
+    let mut count = 0;
+    loop {
+        let from = input.save();
+        if let Some(v) = a.parse(input.ref_clone()) {
+            f(v);
+            count += 1;
+            if max.map(|max| count > max).unwrap_or(false) {
+                return None;
+            }
+        } else {
+            input.back(from);
+            return if count >= min { Some(count) } else { None };
+        }
+        input.re_ready()
+    }
+}
+
 /// Multiple parsing
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Many<A, I = ()> {
     a: A,
     min: usize,
     max: Option<usize>,
-    _i: PhantomData<I>,
+    _i: PhantomData<fn(I)>,
 }
 impl<I: TimeTravel, A> Many<A, I>
 where
@@ -15,11 +50,7 @@ where
 {
     #[inline]
     pub fn new(a: A, min: usize, max: Option<usize>) -> Self {
-        if let Some(max) = max {
-            if max < min {
-                panic!("max must be >= min")
-            }
-        }
+        check_min_max(min, max);
         Self {
             a,
             min,
@@ -34,48 +65,185 @@ where
 {
     type Output = Vec<A::Output>;
 
-    fn parse(&self, mut input: I) -> Option<Self::Output> {
+    fn parse(&self, input: I) -> Option<Self::Output> {
         let mut vec: Vec<A::Output> = vec![];
-        if let Some(max) = self.max {
-            loop {
-                let from = input.save();
-                let a = self.a.parse(input.ref_clone());
-                if let Some(a) = a {
-                    vec.push(a);
-                    if vec.len() > max {
-                        return None;
-                    }
-                } else {
-                    input.back(from);
-                    if vec.len() >= self.min {
-                        return Some(vec);
-                    } else {
-                        return None;
-                    }
-                }
-                input.re_ready()
-            }
-        } else {
-            loop {
-                let from = input.save();
-                let a = self.a.parse(input.ref_clone());
-                if let Some(a) = a {
-                    vec.push(a);
-                } else {
-                    input.back(from);
-                    if vec.len() >= self.min {
-                        return Some(vec);
-                    } else {
-                        return None;
-                    }
-                }
-                input.re_ready()
-            }
+        many_with(&self.a, self.min, self.max, input, |v| vec.push(v))?;
+        Some(vec)
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(self.a.describe(), self.min, self.max)
+    }
+}
+
+/// Multiple parsing, collect into any Default + Extend, like String or HashMap
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ManyInto<A, C, I = ()> {
+    a: A,
+    min: usize,
+    max: Option<usize>,
+    _i: PhantomData<fn(I) -> C>,
+}
+impl<I: TimeTravel, A, C> ManyInto<A, C, I>
+where
+    A: Parser<I>,
+    C: Default + Extend<A::Output>,
+{
+    #[inline]
+    pub fn new(a: A, min: usize, max: Option<usize>) -> Self {
+        check_min_max(min, max);
+        Self {
+            a,
+            min,
+            max,
+            _i: PhantomData,
         }
     }
 }
+impl<I: TimeTravel, A, C> Parser<I> for ManyInto<A, C, I>
+where
+    A: Parser<I>,
+    C: Default + Extend<A::Output>,
+{
+    type Output = C;
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        let mut c = C::default();
+        many_with(&self.a, self.min, self.max, input, |v| {
+            c.extend(std::iter::once(v))
+        })?;
+        Some(c)
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(self.a.describe(), self.min, self.max)
+    }
+}
+
+/// Multiple parsing, fold the outputs into an accumulator instead of collecting them
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FoldMany<A, T, F, I = ()> {
+    a: A,
+    init: T,
+    f: F,
+    min: usize,
+    max: Option<usize>,
+    _i: PhantomData<fn(I)>,
+}
+impl<I: TimeTravel, A, T, F> FoldMany<A, T, F, I>
+where
+    A: Parser<I>,
+    T: Clone,
+    F: Fn(T, A::Output) -> T,
+{
+    #[inline]
+    pub fn new(a: A, init: T, f: F, min: usize, max: Option<usize>) -> Self {
+        check_min_max(min, max);
+        Self {
+            a,
+            init,
+            f,
+            min,
+            max,
+            _i: PhantomData,
+        }
+    }
+}
+impl<I: TimeTravel, A, T, F> Parser<I> for FoldMany<A, T, F, I>
+where
+    A: Parser<I>,
+    T: Clone,
+    F: Fn(T, A::Output) -> T,
+{
+    type Output = T;
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        let mut acc = Some(self.init.clone());
+        many_with(&self.a, self.min, self.max, input, |v| {
+            acc = acc.take().map(|acc| (self.f)(acc, v))
+        })?;
+        acc
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(self.a.describe(), self.min, self.max)
+    }
+}
+
+/// Multiple parsing, drop the outputs
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SkipMany<A, I = ()> {
+    a: A,
+    min: usize,
+    max: Option<usize>,
+    _i: PhantomData<fn(I)>,
+}
+impl<I: TimeTravel, A> SkipMany<A, I>
+where
+    A: Parser<I>,
+{
+    #[inline]
+    pub fn new(a: A, min: usize, max: Option<usize>) -> Self {
+        check_min_max(min, max);
+        Self {
+            a,
+            min,
+            max,
+            _i: PhantomData,
+        }
+    }
+}
+impl<I: TimeTravel, A> Parser<I> for SkipMany<A, I>
+where
+    A: Parser<I>,
+{
+    type Output = ();
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        many_with(&self.a, self.min, self.max, input, drop)?;
+        Some(())
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(self.a.describe(), self.min, self.max)
+    }
+}
+
+/// Multiple parsing, count the outputs
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CountMany<A, I = ()> {
+    a: A,
+    min: usize,
+    max: Option<usize>,
+    _i: PhantomData<fn(I)>,
+}
+impl<I: TimeTravel, A> CountMany<A, I>
+where
+    A: Parser<I>,
+{
+    #[inline]
+    pub fn new(a: A, min: usize, max: Option<usize>) -> Self {
+        check_min_max(min, max);
+        Self {
+            a,
+            min,
+            max,
+            _i: PhantomData,
+        }
+    }
+}
+impl<I: TimeTravel, A> Parser<I> for CountMany<A, I>
+where
+    A: Parser<I>,
+{
+    type Output = usize;
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        many_with(&self.a, self.min, self.max, input, drop)
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(self.a.describe(), self.min, self.max)
+    }
+}
 
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod tests {
     use crate::*;
 
@@ -302,4 +470,71 @@ mod tests {
         let r = x.parse(span);
         println!("{:?}", r);
     }
+
+    #[test]
+    fn test_fold() {
+        let code = "123a";
+        let span = code.span();
+        let digit = satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false))
+            .map(|r| span.com_string(r).unwrap().parse::<u32>().unwrap());
+        let x = digit.fold_many(0, |a, d| a * 10 + d);
+
+        let r = x.parse(span.ref_clone());
+        println!("{:?}", r);
+        assert_eq!(r, Some(123));
+        assert_eq!(span.save(), 3);
+    }
+
+    #[test]
+    fn test_skip_count() {
+        let code = "asdasdqwe";
+        let span = code.span();
+        let r = substr("asd").skip_many1().parse(span.ref_clone());
+        assert_eq!(r, Some(()));
+        assert_eq!(substr("qwe").parse(span), Some(6..9));
+
+        let span = code.span();
+        assert_eq!(substr("qwe").skip_many1().parse(span.ref_clone()), None);
+        assert_eq!(substr("asd").count_many().parse(span), Some(2));
+    }
+
+    #[test]
+    fn test_min_max() {
+        let code = "asdasdasd";
+        let x = CountMany::new(substr("asd"), 2, Some(3));
+        assert_eq!(x.parse(code.span()), Some(3));
+        let x = SkipMany::new(substr("asd"), 0, Some(2));
+        assert_eq!(x.parse(code.span()), None);
+        let x = FoldMany::new(substr("asd"), 0, |a, _| a + 1, 4, None);
+        assert_eq!(x.parse(code.span()), None);
+    }
+
+    #[test]
+    fn test_into() {
+        use std::collections::{BTreeSet, HashMap};
+
+        let code = "b=1,a=2,b=3";
+        let span = code.span();
+        let word = satisfy(|c: Char| c.char(|c, _| c.is_alphanumeric()).unwrap_or(false))
+            .map(|r| span.com_string(r).unwrap());
+        let char = satisfy(|c: Char| c.is_char()).map(|r| span.com_string(r).unwrap());
+
+        let x = char.many_into::<String>();
+        assert_eq!(x.parse(span.clone()), Some(code.to_string()));
+
+        let kv = word
+            .clone()
+            .and(one('='))
+            .and(word.clone())
+            .map(|((k, _), v)| (k, v));
+        let x = kv.and(one(',').may()).map(|(kv, _)| kv);
+        let r = x.clone().many1_into::<HashMap<_, _>>().parse(span.clone());
+        assert_eq!(r.map(|m| m["b"].clone()), Some("3".to_string()));
+
+        let keys = x.map(|(k, _)| k).some_into::<BTreeSet<_>>(3).parse(span.clone());
+        assert_eq!(
+            keys,
+            Some(vec!["a".to_string(), "b".to_string()].into_iter().collect())
+        );
+    }
 }
@@ -1,4 +1,3 @@
-use crate::common::cell::*;
 use crate::*;
 use std::marker::PhantomData;
 
@@ -6,31 +5,34 @@ use std::marker::PhantomData;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Map<B: Parser<I>, I: TimeTravel, F> {
     base: B,
-    f: ExtRefCell<F>,
-    _i: PhantomData<I>,
+    f: F,
+    _i: PhantomData<fn(I)>,
 }
 impl<B: Parser<I>, I: TimeTravel, U, F> Map<B, I, F>
 where
-    F: FnMut(B::Output) -> U,
+    F: Fn(B::Output) -> U,
 {
     pub fn new(base: B, f: F) -> Self {
         Self {
             base,
-            f: ExtRefCell::new(f),
+            f,
             _i: PhantomData,
         }
     }
 }
 impl<B: Parser<I>, I: TimeTravel, U, F> Parser<I> for Map<B, I, F>
 where
-    F: FnMut(B::Output) -> U,
+    F: Fn(B::Output) -> U,
 {
     type Output = U;
 
     fn parse(&self, input: I) -> Option<Self::Output> {
         let base = self.base.parse(input)?;
-        let f = unsafe { self.f.get_mut() };
-        Some(f(base))
+        Some((self.f)(base))
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
     }
 }
 
@@ -5,7 +5,7 @@ use std::marker::PhantomData;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct May<A, I = ()> {
     a: A,
-    _i: PhantomData<I>,
+    _i: PhantomData<fn(I)>,
 }
 impl<I: TimeTravel, A> May<A, I>
 where
@@ -32,6 +32,9 @@ where
         }
         Some(a)
     }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::repeat(self.a.describe(), 0, Some(1))
+    }
 }
 
 #[cfg(test)]
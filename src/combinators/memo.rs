@@ -0,0 +1,475 @@
+use crate::combinators::session;
+use crate::*;
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// Where the positions after an edit moved to, see [Relocate](trait.Relocate.html)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Shift {
+    /// change of item index
+    pub index: isize,
+    /// change of `Loc::offset`
+    pub offset: isize,
+    /// change of `Loc::line`
+    pub line: isize,
+}
+impl Shift {
+    #[inline]
+    pub const fn new(index: isize, offset: isize, line: isize) -> Self {
+        Self {
+            index,
+            offset,
+            line,
+        }
+    }
+    /// Shift an item index
+    #[inline]
+    pub fn index(&self, index: usize) -> usize {
+        (index as isize + self.index) as usize
+    }
+    /// Shift a Loc  
+    /// `char` and `col` stay, since only whole lines after an edit are moved
+    #[inline]
+    pub fn loc(&self, loc: Loc) -> Loc {
+        Loc {
+            offset: (loc.offset as isize + self.offset) as usize,
+            line: (loc.line as isize + self.line) as usize,
+            ..loc
+        }
+    }
+}
+
+/// Move the positions in a parse result after the text before them changed  
+/// Item ranges `Range<usize>` and `Loc`s are moved, other values are kept  
+/// Implement it for your own results to cache them with [memo](trait.Parser.html#method.memo)
+pub trait Relocate {
+    fn relocate(&mut self, shift: &Shift);
+}
+macro_rules! relocate_nothing {
+    { $($t:ty),* } => { $(
+        impl Relocate for $t {
+            #[inline]
+            fn relocate(&mut self, _: &Shift) {}
+        }
+    )* };
+}
+relocate_nothing! { (), bool, char, u8, u16, u32, u64, i8, i16, i32, i64, isize, f32, f64, String }
+impl Relocate for Range<usize> {
+    #[inline]
+    fn relocate(&mut self, shift: &Shift) {
+        *self = shift.index(self.start)..shift.index(self.end);
+    }
+}
+impl Relocate for Loc {
+    #[inline]
+    fn relocate(&mut self, shift: &Shift) {
+        *self = shift.loc(*self);
+    }
+}
+impl Relocate for LocRange {
+    #[inline]
+    fn relocate(&mut self, shift: &Shift) {
+        self.from.relocate(shift);
+        self.to.relocate(shift);
+    }
+}
+impl Relocate for Char {
+    #[inline]
+    fn relocate(&mut self, shift: &Shift) {
+        match self {
+            Char::Char(_, l) => l.relocate(shift),
+            Char::Wrap(l) => l.relocate(shift),
+        }
+    }
+}
+impl<T: Relocate> Relocate for Option<T> {
+    #[inline]
+    fn relocate(&mut self, shift: &Shift) {
+        if let Some(v) = self {
+            v.relocate(shift)
+        }
+    }
+}
+impl<T: Relocate, E: Relocate> Relocate for Result<T, E> {
+    #[inline]
+    fn relocate(&mut self, shift: &Shift) {
+        match self {
+            Ok(v) => v.relocate(shift),
+            Err(e) => e.relocate(shift),
+        }
+    }
+}
+impl<T: Relocate> Relocate for Box<T> {
+    #[inline]
+    fn relocate(&mut self, shift: &Shift) {
+        (**self).relocate(shift)
+    }
+}
+impl<T: Relocate> Relocate for Vec<T> {
+    #[inline]
+    fn relocate(&mut self, shift: &Shift) {
+        for v in self {
+            v.relocate(shift)
+        }
+    }
+}
+impl<K, V: Relocate, S> Relocate for HashMap<K, V, S> {
+    #[inline]
+    fn relocate(&mut self, shift: &Shift) {
+        for v in self.values_mut() {
+            v.relocate(shift)
+        }
+    }
+}
+macro_rules! relocate_tuple {
+    { $($n:tt $t:ident),* } => {
+        impl<$($t: Relocate),*> Relocate for ($($t,)*) {
+            #[inline]
+            fn relocate(&mut self, shift: &Shift) {
+                $(self.$n.relocate(shift);)*
+            }
+        }
+    };
+}
+relocate_tuple! { 0 A }
+relocate_tuple! { 0 A, 1 B }
+relocate_tuple! { 0 A, 1 B, 2 C }
+relocate_tuple! { 0 A, 1 B, 2 C, 3 D }
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+trait MemoValue {
+    fn relocate_dyn(&mut self, shift: &Shift);
+    fn as_any(&self) -> &dyn Any;
+}
+impl<T: Relocate + 'static> MemoValue for T {
+    #[inline]
+    fn relocate_dyn(&mut self, shift: &Shift) {
+        self.relocate(shift)
+    }
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+struct MemoKey {
+    /// index of the rule name in `MemoTable::rules`
+    rule: usize,
+    output: TypeId,
+    start: usize,
+    ready: bool,
+}
+
+struct MemoEntry {
+    value: Option<Box<dyn MemoValue>>,
+    end: usize,
+    end_ready: bool,
+    peak: usize,
+}
+
+/// What to do with a cached result, see [MemoTable::rebase](struct.MemoTable.html#method.rebase)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Rebase {
+    Keep,
+    Shift(Shift),
+    Drop,
+}
+
+/// Results cached by [memo](trait.Parser.html#method.memo)  
+/// Only used while inside [run](#method.run), keep it to reuse the results in the next run  
+/// The results belong to the first input memo parsers read, other inputs are parsed without the table until [clear](#method.clear)
+#[derive(Default)]
+pub struct MemoTable {
+    entries: HashMap<MemoKey, MemoEntry>,
+    rules: HashMap<String, usize>,
+    /// [input_id](trait.Memoizable.html#tymethod.input_id) of the input the results are from
+    input: Option<u128>,
+    hits: usize,
+}
+thread_local! {
+    static MEMO: RefCell<Option<MemoTable>> = const { RefCell::new(None) };
+}
+impl MemoTable {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Number of cached results
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Number of times a cached result was used
+    #[inline]
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+    #[inline]
+    pub fn reset_hits(&mut self) {
+        self.hits = 0;
+    }
+    #[inline]
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.input = None;
+        self.hits = 0;
+    }
+    /// Index of a rule name
+    fn rule(&mut self, name: &str) -> usize {
+        match self.rules.get(name) {
+            Some(&rule) => rule,
+            None => {
+                let rule = self.rules.len();
+                self.rules.insert(name.to_string(), rule);
+                rule
+            }
+        }
+    }
+    /// Make memo parsers on this thread use this table while f runs
+    #[inline]
+    pub fn run<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        session::run(&MEMO, self, f)
+    }
+    /// Decide for every result by its start and peak item index  
+    /// The kept results are for the next input
+    pub(crate) fn rebase(&mut self, f: impl Fn(usize, usize) -> Rebase) {
+        self.input = None;
+        let entries = std::mem::take(&mut self.entries);
+        for (mut key, mut entry) in entries {
+            match f(key.start, entry.peak) {
+                Rebase::Keep => {}
+                Rebase::Shift(shift) => {
+                    key.start = shift.index(key.start);
+                    entry.end = shift.index(entry.end);
+                    entry.peak = shift.index(entry.peak);
+                    if let Some(v) = &mut entry.value {
+                        v.relocate_dyn(&shift);
+                    }
+                }
+                Rebase::Drop => continue,
+            }
+            self.entries.insert(key, entry);
+        }
+    }
+}
+impl Debug for MemoTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoTable")
+            .field("len", &self.entries.len())
+            .field("hits", &self.hits)
+            .finish()
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Cache the result of the subparser at each position  
+/// The rule name identifies the subparser, so rebuilding the parser keeps the cache  
+/// Memo parsers with the same name and output type share results, give different subparsers different names  
+/// Caches nothing outside of [MemoTable::run](struct.MemoTable.html#method.run)
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// fn item(input: CharSpan) -> Option<std::ops::Range<usize>> {
+///     substr("ab").memo("item").parse(input)
+/// }
+/// let code = "abab";
+/// let x = item.and(one('!')).or(item.and(item).map(|(a, b)| (a, b.start..b.start)));
+///
+/// let mut table = MemoTable::new();
+/// let r = table.run(|| x.parse(code.span()));
+/// assert_eq!(r, Some((0..2, 2..2)));
+/// assert_eq!(table.hits(), 1);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Memo<B: Parser<I>, I: TimeTravel> {
+    base: B,
+    rule: Cow<'static, str>,
+    _i: PhantomData<fn(I)>,
+}
+impl<B: Parser<I>, I: TimeTravel> Memo<B, I> {
+    #[inline]
+    pub fn new(base: B, rule: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            base,
+            rule: rule.into(),
+            _i: PhantomData,
+        }
+    }
+}
+impl<B: Parser<I>, I: Memoizable> Parser<I> for Memo<B, I>
+where
+    B::Output: Clone + Relocate + 'static,
+{
+    type Output = B::Output;
+
+    fn parse(&self, mut input: I) -> Option<Self::Output> {
+        let (start, ready, id) = (input.save(), input.is_ready(), input.input_id());
+        let hit = MEMO.with(|m| {
+            let mut m = m.borrow_mut();
+            // None if there is no table or it is for another input, the hit is None if not cached
+            let table = m.as_mut()?;
+            if *table.input.get_or_insert(id) != id {
+                return None;
+            }
+            let key = MemoKey {
+                rule: table.rule(&self.rule),
+                output: TypeId::of::<B::Output>(),
+                start,
+                ready,
+            };
+            let entry = match table.entries.get(&key) {
+                Some(entry) => entry,
+                None => return Some((key, None)),
+            };
+            let value = match &entry.value {
+                Some(v) => Some(v.as_any().downcast_ref::<B::Output>()?.clone()),
+                None => None,
+            };
+            let r = (value, entry.end, entry.end_ready, entry.peak);
+            table.hits += 1;
+            Some((key, Some(r)))
+        });
+        let (key, hit) = match hit {
+            None => return self.base.parse(input),
+            Some(hit) => hit,
+        };
+        if let Some((value, end, end_ready, peak)) = hit {
+            if end_ready {
+                input.back(end);
+            } else {
+                input.back(end - 1);
+                input.next();
+            }
+            let outer = input.peak();
+            input.swap_peak(outer.max(peak));
+            return value;
+        }
+        let outer = input.swap_peak(key.start);
+        let value = self.base.parse(input.ref_clone());
+        let peak = input.peak();
+        input.swap_peak(outer.max(peak));
+        let entry = MemoEntry {
+            value: value.clone().map(|v| Box::new(v) as Box<dyn MemoValue>),
+            end: input.save(),
+            end_ready: input.is_ready(),
+            peak,
+        };
+        MEMO.with(|m| {
+            if let Some(table) = m.borrow_mut().as_mut() {
+                table.entries.insert(key, entry);
+            }
+        });
+        value
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::cell::Cell;
+    use std::ops::Range;
+
+    #[test]
+    fn test() {
+        let count = Cell::new(0);
+        let code = "asd123";
+        let span = code.span();
+        let x = (|i: CharSpan| {
+            count.set(count.get() + 1);
+            substr("asd").parse(i)
+        })
+        .memo("asd");
+        let p = x
+            .clone()
+            .and(one('!'))
+            .or(x.and(one('1')).map(|(a, _)| (a, 0..0)));
+
+        let r = p.parse(span.ref_clone());
+        println!("{:?}", r);
+        assert_eq!(r, Some((0..3, 0..0)));
+        assert_eq!(count.get(), 2);
+
+        count.set(0);
+        let mut table = MemoTable::new();
+        let r = table.run(|| p.parse(span.clone()));
+        assert_eq!(r, Some((0..3, 0..0)));
+        assert_eq!(count.get(), 1);
+        assert_eq!(table.hits(), 1);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_fail() {
+        let count = Cell::new(0);
+        let code = "qwe";
+        let x = (|i: CharSpan| {
+            count.set(count.get() + 1);
+            substr("asd").parse(i)
+        })
+        .memo("asd");
+        let p = x
+            .clone()
+            .map(|r: Range<usize>| r.len())
+            .or(x.map(|_| 0))
+            .or(one('q').map(|_| 1));
+
+        let mut table = MemoTable::new();
+        let r = table.run(|| p.parse(code.span()));
+        assert_eq!(r, Some(1));
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn test_rules() {
+        fn kw(s: &'static str) -> impl Parser<CharSpan<'static>, Output = Range<usize>> {
+            substr(s).memo(s)
+        }
+        let x = kw("let").and(one('!')).or(kw("if").and(one(' ')));
+
+        let mut table = MemoTable::new();
+        let r = table.run(|| x.parse("if x".span()));
+        assert_eq!(r, Some((0..2, 2..3)));
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.hits(), 0);
+
+        // the results are for the first input only
+        let r = table.run(|| kw("if").parse("let".span()));
+        assert_eq!(r, None);
+        assert_eq!(table.len(), 2);
+        table.clear();
+        let r = table.run(|| kw("if").parse("let".span()));
+        assert_eq!(r, None);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_relocate() {
+        let shift = Shift::new(2, 3, 1);
+        let mut v = (
+            vec![1..2, 4..5],
+            Some(Loc::new_at(5, 1, 2)),
+            "a".to_string(),
+        );
+        v.relocate(&shift);
+        assert_eq!(v.0, vec![3..4, 6..7]);
+        assert_eq!(v.1, Some(Loc::new_at(8, 2, 2)));
+        assert_eq!(v.2, "a");
+    }
+}
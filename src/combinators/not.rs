@@ -1,11 +1,12 @@
 use crate::*;
 use std::marker::PhantomData;
 
-/// Pass if the subparser fail
+/// Pass if the subparser fail  
+/// Never consumes, the input is put back either way
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Not<A, I = ()> {
     a: A,
-    _i: PhantomData<I>,
+    _i: PhantomData<fn(I)>,
 }
 impl<I: TimeTravel, A> Not<A, I>
 where
@@ -22,14 +23,19 @@ where
 {
     type Output = ();
 
-    fn parse(&self, input: I) -> Option<Self::Output> {
+    fn parse(&self, mut input: I) -> Option<Self::Output> {
+        let from = input.save();
         let a = self.a.parse(input.ref_clone());
-        if let Some(_) = a {
+        input.back(from);
+        if a.is_some() {
             None
         } else {
             Some(())
         }
     }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::Not(Box::new(self.a.describe()))
+    }
 }
 
 #[cfg(test)]
@@ -47,4 +53,16 @@ mod tests {
         println!("{:?}", r);
         assert_eq!(r, Some(()))
     }
+
+    #[test]
+    fn test_zero_width() {
+        let x = substr("as").not().and(substr("asd"));
+        assert_eq!(x.parse("asd".span()), None);
+
+        let x = substr("as").not().not().and(substr("asd"));
+        assert_eq!(x.parse("asd".span()), Some(((), 0..3)));
+
+        let x = substr("ax").not().and(substr("asd"));
+        assert_eq!(x.parse("asd".span()), Some(((), 0..3)));
+    }
 }
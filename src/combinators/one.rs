@@ -1,4 +1,5 @@
 use crate::*;
+use std::fmt::Debug;
 use std::ops::Range;
 
 /// Match an item
@@ -13,11 +14,20 @@ use std::ops::Range;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct One<T> {
     val: T,
+    lit: Option<String>,
 }
 impl<T> One<T> {
     #[inline]
     pub const fn new(val: T) -> Self {
-        Self { val }
+        Self { val, lit: None }
+    }
+}
+impl<T: Debug> One<T> {
+    /// [Describe](trait.Parser.html#method.describe) as a literal instead of opaque
+    #[inline]
+    pub fn literal(mut self) -> Self {
+        self.lit = Some(GrammarNode::literal_text(std::slice::from_ref(&self.val)));
+        self
     }
 }
 impl<I: TimeTravel, T> Parser<I> for One<T>
@@ -36,6 +46,13 @@ where
             None
         }
     }
+    /// Opaque, the item may not be `Debug`, see [literal](#method.literal)
+    fn describe(&self) -> GrammarNode {
+        match &self.lit {
+            Some(lit) => GrammarNode::Literal(lit.clone()),
+            None => GrammarNode::Opaque("one".to_string()),
+        }
+    }
 }
 
 /// Match an item
@@ -103,4 +120,20 @@ mod tests {
         println!("{:?}", r);
         assert_eq!(r, Some(1..2));
     }
+
+    #[test]
+    fn test_not_debug() {
+        #[derive(Clone, PartialEq)]
+        struct Tok(u8);
+
+        let a = one(Tok(1));
+        let r = a.parse(Span::new(vec![Tok(1), Tok(2)].into_iter()));
+        assert_eq!(r, Some(0..1));
+        assert_eq!(
+            Parser::<Span<std::vec::IntoIter<Tok>>>::describe(&a),
+            GrammarNode::Opaque("one".to_string())
+        );
+        let r = sub(vec![Tok(1), Tok(2)]).parse(Span::new(vec![Tok(1), Tok(2)].into_iter()));
+        assert_eq!(r, Some(0..2));
+    }
 }
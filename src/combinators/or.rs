@@ -6,7 +6,7 @@ use std::marker::PhantomData;
 pub struct Or<A, B, I = ()> {
     a: A,
     b: B,
-    _i: PhantomData<I>,
+    _i: PhantomData<fn(I)>,
 }
 impl<I: TimeTravel, A, B> Or<A, B, I>
 where
@@ -34,10 +34,12 @@ where
         let a = self.a.parse(input.ref_clone());
         a.or_else(|| {
             input.back(from);
-            let b = self.b.parse(input);
-            b
+            self.b.parse(input)
         })
     }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::choice(self.a.describe(), self.b.describe())
+    }
 }
 
 #[macro_export]
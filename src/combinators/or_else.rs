@@ -1,4 +1,3 @@
-use crate::common::cell::*;
 use crate::*;
 use std::marker::PhantomData;
 
@@ -6,18 +5,18 @@ use std::marker::PhantomData;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct OrElse<B: Parser<I>, I: TimeTravel, F> {
     base: B,
-    f: ExtRefCell<F>,
-    _i: PhantomData<I>,
+    f: F,
+    _i: PhantomData<fn(I)>,
 }
 impl<B: Parser<I>, I: TimeTravel, U, F> OrElse<B, I, F>
 where
     U: Parser<I, Output = B::Output>,
-    F: FnMut() -> U,
+    F: Fn() -> U,
 {
     pub fn new(base: B, f: F) -> Self {
         Self {
             base,
-            f: ExtRefCell::new(f),
+            f,
             _i: PhantomData,
         }
     }
@@ -25,22 +24,24 @@ where
 impl<B: Parser<I>, I: TimeTravel, U, F> Parser<I> for OrElse<B, I, F>
 where
     U: Parser<I, Output = B::Output>,
-    F: FnMut() -> U,
+    F: Fn() -> U,
 {
     type Output = B::Output;
 
     fn parse(&self, mut input: I) -> Option<Self::Output> {
         let from = input.save();
         let base = self.base.parse(input.ref_clone());
-        if let None = base {
-            let f = unsafe { self.f.get_mut() };
-            let then: U = f();
+        if base.is_none() {
+            let then: U = (self.f)();
             input.back(from);
             then.parse(input)
         } else {
             base
         }
     }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::choice(self.base.describe(), (self.f)().describe())
+    }
 }
 
 #[cfg(test)]
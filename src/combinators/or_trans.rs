@@ -1,4 +1,3 @@
-use crate::common::cell::*;
 use crate::*;
 use std::marker::PhantomData;
 use std::ops::Range;
@@ -11,45 +10,44 @@ pub struct OrTrans<B: Parser<I>, I: TimeTravel, F> {
     base: B,
     no_eof: bool,
     no_retry: bool,
-    f: ExtRefCell<F>,
-    _i: PhantomData<I>,
+    f: F,
+    _i: PhantomData<fn(I)>,
 }
 impl<B: Parser<I>, I: TimeTravel, F> OrTrans<B, I, F>
 where
-    F: FnMut(I, Range<usize>) -> B::Output,
+    F: Fn(I, Range<usize>) -> B::Output,
 {
     pub fn new(base: B, no_eof: bool, no_retry: bool, f: F) -> Self {
         Self {
             base,
             no_eof,
             no_retry,
-            f: ExtRefCell::new(f),
+            f,
             _i: PhantomData,
         }
     }
 }
 impl<B: Parser<I>, I: TimeTravel, F> Parser<I> for OrTrans<B, I, F>
 where
-    F: FnMut(I, Range<usize>) -> B::Output,
+    F: Fn(I, Range<usize>) -> B::Output,
 {
     type Output = B::Output;
 
     fn parse(&self, mut input: I) -> Option<Self::Output> {
         let from = input.save();
         let base = self.base.parse(input.ref_clone());
-        if let None = base {
+        if base.is_none() {
             if self.no_retry {
                 if input.is_complete() && self.no_eof {
                     return None;
                 }
-                let f = unsafe { self.f.get_mut() };
                 let now = input.save();
                 let r = if input.is_complete() && from == now && now != 0 {
                     from - 1
                 } else {
                     from
                 }..now;
-                return Some(f(input.ref_clone(), r));
+                return Some((self.f)(input.ref_clone(), r));
             }
             loop {
                 if input.is_complete() {
@@ -57,25 +55,21 @@ where
                         return None;
                     }
                     let now = input.save();
-                    let f = unsafe { self.f.get_mut() };
-
                     let r = if input.is_complete() && from == now && now != 0 {
                         from - 1
                     } else {
                         from
                     }..now;
 
-                    let r = Some(f(input.clone(), r));
+                    let r = Some((self.f)(input.clone(), r));
                     return r;
                 }
                 let save = input.save();
                 let base = self.base.parse(input.ref_clone());
                 if base.is_some() {
-                    let f = unsafe { self.f.get_mut() };
-
                     let r = from..save;
 
-                    let r = Some(f(input.ref_clone(), r));
+                    let r = Some((self.f)(input.ref_clone(), r));
                     input.back(save);
                     return r;
                 }
@@ -84,6 +78,10 @@ where
             base
         }
     }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,259 @@
+use crate::combinators::session;
+use crate::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Numbers of one [profiled](trait.Parser.html#method.profile) parser
+#[derive(Debug, PartialEq, Eq, Clone, Default, Hash, Serialize, Deserialize)]
+pub struct RuleStats {
+    pub name: String,
+    pub calls: usize,
+    pub passed: usize,
+    pub failed: usize,
+    /// Items read again that a profiled parser had already read in this run, because something backtracked
+    pub rescanned: usize,
+    /// Time inside the parser, including the parsers inside it
+    pub time: Duration,
+}
+
+/// What to sort a [ProfileReport](struct.ProfileReport.html) by, numbers are sorted largest first
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ProfileKey {
+    Name,
+    Calls,
+    Passed,
+    Failed,
+    Rescanned,
+    Time,
+}
+
+/// The numbers of every profiled parser, sorted by name  
+/// It is `Serialize`, export it as JSON with serde_json
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileReport {
+    pub rules: Vec<RuleStats>,
+}
+impl ProfileReport {
+    pub fn sort_by(mut self, key: ProfileKey) -> Self {
+        match key {
+            ProfileKey::Name => self.rules.sort_by(|a, b| a.name.cmp(&b.name)),
+            ProfileKey::Calls => self.rules.sort_by_key(|r| Reverse(r.calls)),
+            ProfileKey::Passed => self.rules.sort_by_key(|r| Reverse(r.passed)),
+            ProfileKey::Failed => self.rules.sort_by_key(|r| Reverse(r.failed)),
+            ProfileKey::Rescanned => self.rules.sort_by_key(|r| Reverse(r.rescanned)),
+            ProfileKey::Time => self.rules.sort_by_key(|r| Reverse(r.time)),
+        }
+        self
+    }
+    /// A header line then a line per parser, the time is in nanoseconds
+    pub fn to_csv(&self) -> String {
+        let mut s = String::from("name,calls,passed,failed,rescanned,time_ns\n");
+        for r in &self.rules {
+            let name = if r.name.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", r.name.replace('"', "\"\""))
+            } else {
+                r.name.clone()
+            };
+            writeln!(
+                s,
+                "{},{},{},{},{},{}",
+                name,
+                r.calls,
+                r.passed,
+                r.failed,
+                r.rescanned,
+                r.time.as_nanos()
+            )
+            .unwrap();
+        }
+        s
+    }
+}
+
+/// Collect the numbers of the [profiled](trait.Parser.html#method.profile) parsers run inside [run](#method.run)
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// // both branches read the `a`s, the second one again
+/// let a = || one('a').many1().profile("as");
+/// let x = a().and(one('b')).or(a().and(one('c'))).profile("x");
+///
+/// let mut profiler = Profiler::new();
+/// profiler.run(|| x.parse("aaac".span()));
+/// let report = profiler.report().sort_by(ProfileKey::Rescanned);
+/// assert_eq!(report.rules[0].name, "as");
+/// assert_eq!((report.rules[0].calls, report.rules[0].rescanned), (2, 4));
+/// assert!(report.to_csv().starts_with("name,calls,passed,failed,rescanned,time_ns\nas,2,2,0,4,"));
+/// ```
+#[derive(Debug, Default)]
+pub struct Profiler {
+    stats: HashMap<String, RuleStats>,
+    /// One past the furthest item a profiled parser read
+    scanned: usize,
+}
+thread_local! {
+    static PROFILE: RefCell<Option<Profiler>> = const { RefCell::new(None) };
+}
+impl Profiler {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Make profiled parsers on this thread count into this profiler while f runs  
+    /// Only use one input in a run, rescans are counted by the item index
+    pub fn run<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        self.scanned = 0;
+        session::run(&PROFILE, self, f)
+    }
+    /// The numbers of a parser
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&RuleStats> {
+        self.stats.get(name)
+    }
+    pub fn report(&self) -> ProfileReport {
+        ProfileReport {
+            rules: self.stats.values().cloned().collect(),
+        }
+        .sort_by(ProfileKey::Name)
+    }
+    #[inline]
+    pub fn clear(&mut self) {
+        self.stats.clear();
+    }
+}
+
+/// Count calls, results, rescans and time of the subparser, see [profile](trait.Parser.html#method.profile)  
+/// A [Label](struct.Label.html) that also counts
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Profile<B: Parser<I>, I: TimeTravel> {
+    label: Label<B, I>,
+}
+impl<B: Parser<I>, I: TimeTravel> Profile<B, I> {
+    #[inline]
+    pub fn new(base: B, name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            label: Label::new(base, name),
+        }
+    }
+}
+impl<B: Parser<I>, I: Memoizable> Parser<I> for Profile<B, I> {
+    type Output = B::Output;
+
+    fn parse(&self, mut input: I) -> Option<Self::Output> {
+        let scanned = match PROFILE.with(|p| p.borrow().as_ref().map(|p| p.scanned)) {
+            Some(scanned) => scanned,
+            None => return self.label.parse(input),
+        };
+        let at = input.consumed_end();
+        let outer = input.swap_peak(at);
+        let start = Instant::now();
+        let r = self.label.parse(input.ref_clone());
+        let time = start.elapsed();
+        let peak = input.peak();
+        input.swap_peak(outer.max(peak));
+        PROFILE.with(|p| {
+            if let Some(p) = p.borrow_mut().as_mut() {
+                p.scanned = p.scanned.max(peak);
+                let name = &self.label.name;
+                let stats = p
+                    .stats
+                    .entry(name.to_string())
+                    .or_insert_with(|| RuleStats {
+                        name: name.to_string(),
+                        ..RuleStats::default()
+                    });
+                stats.calls += 1;
+                if r.is_some() {
+                    stats.passed += 1;
+                } else {
+                    stats.failed += 1;
+                }
+                stats.rescanned += peak.min(scanned).saturating_sub(at);
+                stats.time += time;
+            }
+        });
+        r
+    }
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        self.label.name()
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.label.describe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_profile() {
+        let word = || {
+            satisfy(|c: Char| c.char(|c, _| c.is_alphabetic()).unwrap_or(false))
+                .many1()
+                .profile("word")
+        };
+        let call = word().and(one('(')).map(|_| ()).profile("call");
+        let x = call
+            .or(word().map(|_| ()))
+            .and(one(';'))
+            .profile("stmt")
+            .many();
+
+        let mut profiler = Profiler::new();
+        let span = "abc;f(;".span();
+        let r = profiler.run(|| x.parse(span.ref_clone()));
+        assert_eq!(r.map(|v| v.len()), Some(2));
+        let report = profiler.report();
+        println!("{:?}", report);
+        let counts: Vec<_> = report
+            .rules
+            .iter()
+            .map(|r| (r.name.as_str(), r.calls, r.passed, r.failed, r.rescanned))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("call", 3, 1, 2, 0),
+                ("stmt", 3, 2, 1, 0),
+                // the first call reads `abc;` and fails, the word reads it again
+                // at the end both try to read past it
+                ("word", 5, 3, 2, 5),
+            ]
+        );
+        assert!(profiler.get("stmt").unwrap().time >= profiler.get("call").unwrap().time);
+
+        let report = report.sort_by(ProfileKey::Calls);
+        assert_eq!(report.rules[0].name, "word");
+        let report = report.sort_by(ProfileKey::Rescanned);
+        assert_eq!(report.rules[0].rescanned, 5);
+        let json = serde_json::to_string(&report).unwrap();
+        let back: ProfileReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, report);
+
+        // counts add up over runs, nothing is counted outside of them
+        profiler.run(|| x.parse("a;".span()));
+        x.parse("a;".span());
+        assert_eq!(profiler.get("stmt").unwrap().calls, 5);
+        profiler.clear();
+        assert!(profiler.report().rules.is_empty());
+
+        let report = ProfileReport {
+            rules: vec![RuleStats {
+                name: "a,\"b\"".to_string(),
+                ..RuleStats::default()
+            }],
+        };
+        assert_eq!(
+            report.to_csv().lines().nth(1),
+            Some("\"a,\"\"b\"\"\",0,0,0,0,0")
+        );
+    }
+}
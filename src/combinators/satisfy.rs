@@ -1,4 +1,3 @@
-use crate::common::cell::*;
 use crate::*;
 use std::marker::PhantomData;
 use std::ops::Range;
@@ -14,18 +13,18 @@ use std::ops::Range;
 /// ```
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Satisfy<F, I = ()> {
-    f: ExtRefCell<F>,
-    _i: PhantomData<I>,
+    f: F,
+    _i: PhantomData<fn(I)>,
 }
 impl<I, F> Satisfy<F, I>
 where
     I: TimeTravel,
-    F: FnMut(I::Item) -> bool,
+    F: Fn(I::Item) -> bool,
 {
     #[inline]
     pub fn new(f: F) -> Self {
         Self {
-            f: ExtRefCell::new(f),
+            f,
             _i: PhantomData,
         }
     }
@@ -33,7 +32,7 @@ where
 impl<I, F> Parser<I> for Satisfy<F, I>
 where
     I: TimeTravel,
-    F: FnMut(I::Item) -> bool,
+    F: Fn(I::Item) -> bool,
 {
     type Output = Range<usize>;
 
@@ -41,14 +40,17 @@ where
         let from = input.save();
         let n: I::Item = input.next()?;
         input.do_ready();
-        let f = unsafe { self.f.get_mut() };
-        let r: bool = f(n);
+        let r: bool = (self.f)(n);
         if r {
             Some(input.make_range(from))
         } else {
             None
         }
     }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::Opaque("satisfy".to_string())
+    }
 }
 
 /// Check whether it is satisfied
@@ -64,7 +66,7 @@ where
 pub fn satisfy<I, F>(f: F) -> Satisfy<F, I>
 where
     I: TimeTravel,
-    F: FnMut(I::Item) -> bool,
+    F: Fn(I::Item) -> bool,
 {
     Satisfy::new(f)
 }
@@ -0,0 +1,30 @@
+//! Thread local state that parsers use while a run of [MemoTable](../struct.MemoTable.html), [TraceLog](../struct.TraceLog.html), [Profiler](../struct.Profiler.html) or a [CST](../struct.SyntaxNode.html) build is going
+
+use std::cell::RefCell;
+use std::thread::LocalKey;
+
+/// The slot of a session, None outside of a run
+pub(crate) type Slot<T> = LocalKey<RefCell<Option<T>>>;
+
+/// Move `value` into the slot while f runs, then move it back and restore what was there before, also on panic
+pub(crate) fn run<T: Default + 'static, R>(
+    slot: &'static Slot<T>,
+    value: &mut T,
+    f: impl FnOnce() -> R,
+) -> R {
+    struct Guard<'a, T: Default + 'static> {
+        slot: &'static Slot<T>,
+        value: &'a mut T,
+        prev: Option<T>,
+    }
+    impl<T: Default + 'static> Drop for Guard<'_, T> {
+        fn drop(&mut self) {
+            let now = self.slot.with(|s| s.replace(self.prev.take()));
+            *self.value = now.unwrap_or_default();
+        }
+    }
+    let now = std::mem::take(value);
+    let prev = slot.with(|s| s.replace(Some(now)));
+    let _guard = Guard { slot, value, prev };
+    f()
+}
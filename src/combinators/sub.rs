@@ -1,6 +1,7 @@
 use libsugar::*;
 
 use crate::*;
+use std::fmt::Debug;
 use std::ops::Range;
 
 /// Match subpart
@@ -15,11 +16,20 @@ use std::ops::Range;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Sub<T> {
     sub: Vec<T>,
+    lit: Option<String>,
 }
 impl<T> Sub<T> {
     #[inline]
     pub const fn new(sub: Vec<T>) -> Self {
-        Self { sub }
+        Self { sub, lit: None }
+    }
+}
+impl<T: Debug> Sub<T> {
+    /// [Describe](trait.Parser.html#method.describe) as a literal instead of opaque
+    #[inline]
+    pub fn literal(mut self) -> Self {
+        self.lit = Some(GrammarNode::literal_text(&self.sub));
+        self
     }
 }
 impl<T, I: IntoIterator<Item = T>> From<I> for Sub<T> {
@@ -58,7 +68,7 @@ where
         //     .ref_clone()
         //     .zip(self.sub.iter())
         //     .position(|(a, b)| PartialEq::<T>::ne(&a, b));
-        if let None = pos {
+        if pos.is_none() {
             let pos = input.save() - from;
             if self.sub.len() + 1 == pos {
                 return Some(from..input.save() - 1);
@@ -75,6 +85,13 @@ where
         }
         None
     }
+    /// Opaque, the item may not be `Debug`, see [literal](#method.literal)
+    fn describe(&self) -> GrammarNode {
+        match &self.lit {
+            Some(lit) => GrammarNode::Literal(lit.clone()),
+            None => GrammarNode::Opaque("sub".to_string()),
+        }
+    }
 }
 
 /// Match subpart
@@ -91,7 +108,7 @@ pub fn sub<T, I: IntoIterator<Item = T>>(c: I) -> Sub<T> {
     Sub::from(c)
 }
 
-/// Match substring
+/// Match substring, described as a literal
 /// ## example
 /// ```
 /// # use parser_fuck::*;
@@ -102,7 +119,7 @@ pub fn sub<T, I: IntoIterator<Item = T>>(c: I) -> Sub<T> {
 /// ```
 #[inline]
 pub fn substr(c: &str) -> Sub<char> {
-    Sub::from(c.chars())
+    Sub::from(c.chars()).literal()
 }
 
 #[cfg(test)]
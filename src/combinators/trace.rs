@@ -0,0 +1,351 @@
+use crate::combinators::session;
+use crate::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// What happened, see [TraceEvent](struct.TraceEvent.html)
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+pub enum TraceKind {
+    Enter,
+    /// Passed, with the consumed range and its location
+    Pass {
+        range: Range<usize>,
+        loc: Option<LocRange>,
+    },
+    Fail,
+}
+
+/// A traced parser was entered or left
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+pub struct TraceEvent {
+    pub name: String,
+    /// Number of traced parsers this one is inside
+    pub depth: usize,
+    /// Where the parser started, a `save()` offset
+    pub at: usize,
+    /// Location of `at`, None at the end of the input
+    pub loc: Option<Loc>,
+    pub kind: TraceKind,
+}
+impl Display for TraceEvent {
+    /// One line of the text log, indented by depth
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:1$}", "", self.depth * 2)?;
+        match &self.kind {
+            TraceKind::Enter => write!(f, "> {}", self.name)?,
+            TraceKind::Pass { range, .. } => {
+                write!(f, "< {} passed {}..{}", self.name, range.start, range.end)?
+            }
+            TraceKind::Fail => write!(f, "< {} failed", self.name)?,
+        }
+        match (&self.kind, self.loc) {
+            (TraceKind::Pass { loc: Some(loc), .. }, _) => write!(
+                f,
+                " at {}:{}-{}:{}",
+                loc.from.line + 1,
+                loc.from.col + 1,
+                loc.to.line + 1,
+                loc.to.col + 1
+            ),
+            (_, Some(loc)) => write!(f, " at {}:{}", loc.line + 1, loc.col + 1),
+            (_, None) => write!(f, " at end"),
+        }
+    }
+}
+
+/// Events of the [traced](trait.Parser.html#method.trace) parsers run inside [run](#method.run)  
+/// It is `Serialize`, export it as JSON with serde_json
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let x = one('a').trace("a").many().and(one('b')).trace("ab");
+/// let mut log = TraceLog::new();
+/// let r = log.run(|| x.parse("aab".span()));
+/// assert!(r.is_some());
+/// assert_eq!(log.to_string(), "\
+/// > ab at 1:1
+///   > a at 1:1
+///   < a passed 0..1 at 1:1-1:1
+///   > a at 1:2
+///   < a passed 1..2 at 1:2-1:2
+///   > a at 1:3
+///   < a failed at 1:3
+/// < ab passed 0..3 at 1:1-1:3
+/// ");
+/// let json = serde_json::to_string(&log).unwrap();
+/// assert!(json.starts_with(r#"{"events":[{"name":"ab","depth":0,"at":0,"#));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct TraceLog {
+    pub events: Vec<TraceEvent>,
+}
+/// Where events go outside of a run, see [set_sink](struct.TraceLog.html#method.set_sink)
+type Sink = Arc<dyn Fn(&TraceEvent) + Send + Sync>;
+
+thread_local! {
+    static TRACE: RefCell<Option<TraceLog>> = const { RefCell::new(None) };
+    /// Number of traced parsers entered, per run
+    static DEPTH: RefCell<Option<usize>> = const { RefCell::new(None) };
+}
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+static HAS_SINK: AtomicBool = AtomicBool::new(false);
+impl TraceLog {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Record the traced parsers on this thread while f runs, appending to the events
+    pub fn run<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        session::run(&TRACE, self, || session::run(&DEPTH, &mut 0, f))
+    }
+    /// Pass the events of traced parsers to a function as they happen, on all threads, when not inside [run](#method.run)
+    /// ## example
+    /// ```
+    /// # use parser_fuck::*;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let lines = Arc::new(Mutex::new(vec![]));
+    /// let sink = lines.clone();
+    /// TraceLog::set_sink(move |e| sink.lock().unwrap().push(e.to_string()));
+    /// one('a').trace("a").parse("a".span());
+    /// TraceLog::clear_sink();
+    /// assert!(lines.lock().unwrap().contains(&"< a passed 0..1 at 1:1-1:1".to_string()));
+    /// ```
+    #[inline]
+    pub fn set_sink(sink: impl Fn(&TraceEvent) + Send + Sync + 'static) {
+        replace_sink(Some(Arc::new(sink)));
+    }
+    /// Stop passing events set by [set_sink](#method.set_sink) or [set_stderr](#method.set_stderr)
+    #[inline]
+    pub fn clear_sink() {
+        replace_sink(None);
+    }
+    /// Print the events of traced parsers to stderr, see [set_sink](#method.set_sink)
+    pub fn set_stderr(on: bool) {
+        if on {
+            Self::set_sink(|e| eprintln!("{}", e));
+        } else {
+            Self::clear_sink();
+        }
+    }
+    #[inline]
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+impl Display for TraceLog {
+    /// The indented text log, one event per line
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+fn replace_sink(sink: Option<Sink>) {
+    HAS_SINK.store(sink.is_some(), Ordering::Relaxed);
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = sink;
+}
+#[inline]
+fn tracing() -> bool {
+    HAS_SINK.load(Ordering::Relaxed) || TRACE.with(|t| t.borrow().is_some())
+}
+fn record(event: TraceEvent) {
+    let outside = TRACE.with(|t| match t.borrow_mut().as_mut() {
+        Some(log) => {
+            log.events.push(event);
+            None
+        }
+        None => Some(event),
+    });
+    if let Some(event) = outside {
+        // clone it out, the sink may trace too
+        let sink = SINK.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(sink) = sink {
+            sink(&event)
+        }
+    }
+}
+
+/// Record entering and leaving the subparser, see [trace](trait.Parser.html#method.trace)  
+/// A [Label](struct.Label.html) that also records
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Trace<B: Parser<I>, I: TimeTravel> {
+    label: Label<B, I>,
+}
+impl<B: Parser<I>, I: TimeTravel> Trace<B, I> {
+    #[inline]
+    pub fn new(base: B, name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            label: Label::new(base, name),
+        }
+    }
+}
+impl<B: Parser<I>, I> Parser<I> for Trace<B, I>
+where
+    I: Memoizable + ComLoc<ComLocData = usize> + ComLocRange<ComLocRangeData = Range<usize>>,
+{
+    type Output = B::Output;
+
+    fn parse(&self, input: I) -> Option<Self::Output> {
+        if !tracing() {
+            return self.label.parse(input);
+        }
+        let at = input.consumed_end();
+        let loc = input.loc(at);
+        let depth = DEPTH.with(|d| {
+            let mut d = d.borrow_mut();
+            let depth = d.get_or_insert(0);
+            *depth += 1;
+            *depth - 1
+        });
+        let event = |kind| TraceEvent {
+            name: self.label.name.to_string(),
+            depth,
+            at,
+            loc,
+            kind,
+        };
+        record(event(TraceKind::Enter));
+        let r = self.label.parse(input.ref_clone());
+        DEPTH.with(|d| *d.borrow_mut() = Some(depth));
+        record(event(match r {
+            Some(_) => {
+                let range = at..input.consumed_end();
+                let loc = if range.is_empty() {
+                    None
+                } else {
+                    input.loc_range(range.clone())
+                };
+                TraceKind::Pass { range, loc }
+            }
+            None => TraceKind::Fail,
+        }));
+        r
+    }
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        self.label.name()
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.label.describe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_trace() {
+        let item = substr("ab").trace("ab").or(substr("a").trace("a"));
+        let x = item.many1().trace("items");
+
+        let mut log = TraceLog::new();
+        let span = "aba\nx".span();
+        let r = log.run(|| x.parse(span.ref_clone()));
+        println!("{}", log);
+        assert_eq!(r, Some(vec![0..2, 2..3]));
+        let events: Vec<_> = log
+            .events
+            .iter()
+            .map(|e| (e.name.as_str(), e.depth, e.at))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                ("items", 0, 0),
+                ("ab", 1, 0),
+                ("ab", 1, 0),
+                ("ab", 1, 2),
+                ("ab", 1, 2),
+                ("a", 1, 2),
+                ("a", 1, 2),
+                ("ab", 1, 3),
+                ("ab", 1, 3),
+                ("a", 1, 3),
+                ("a", 1, 3),
+                ("items", 0, 0),
+            ]
+        );
+        assert_eq!(
+            log.events[11].kind,
+            TraceKind::Pass {
+                range: 0..3,
+                loc: Some(loc_range_of(loc_of(0, 0, 0), loc_of(2, 0, 2)))
+            }
+        );
+        assert_eq!(log.events[10].kind, TraceKind::Fail);
+        assert_eq!(log.events[10].to_string(), "  < a failed at 1:4");
+
+        let tab = TraceEvent {
+            name: "x".to_string(),
+            depth: 0,
+            at: 1,
+            loc: Some(Loc::new_at_col(1, 0, 1, 4)),
+            kind: TraceKind::Enter,
+        };
+        assert_eq!(tab.to_string(), "> x at 1:5");
+
+        let mut inner = TraceLog::new();
+        let r = log.run(|| {
+            substr("a").trace("outer").parse("a".span());
+            inner.run(|| substr("b").trace("inner").parse("b".span()))
+        });
+        assert_eq!(r, Some(0..1));
+        assert_eq!(log.events.len(), 14);
+        assert_eq!(inner.events.len(), 2);
+        assert_eq!(
+            inner.events[1].to_string(),
+            "< inner passed 0..1 at 1:1-1:1"
+        );
+
+        let end = substr("a").trace("a").and(substr("b").trace("b").may());
+        log.clear();
+        log.run(|| end.parse("a".span()));
+        assert_eq!(log.events[3].to_string(), "< b failed at end");
+
+        let json = serde_json::to_string(&log).unwrap();
+        let back: TraceLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, log);
+    }
+
+    #[test]
+    fn test_sink() {
+        use std::sync::{Arc, Mutex};
+
+        // other tests may trace at the same time, keep the events of this one
+        let lines = Arc::new(Mutex::new(vec![]));
+        let sink = lines.clone();
+        TraceLog::set_sink(move |e| {
+            if e.name.starts_with("sink_") {
+                sink.lock().unwrap().push(e.to_string())
+            }
+        });
+        let x = substr("a").trace("sink_a").trace("sink_x");
+        x.parse("a".span());
+        let mut log = TraceLog::new();
+        log.run(|| x.parse("a".span()));
+        TraceLog::clear_sink();
+        x.parse("a".span());
+
+        assert_eq!(log.events.len(), 4);
+        assert_eq!(
+            *lines.lock().unwrap(),
+            vec![
+                "> sink_x at 1:1",
+                "  > sink_a at 1:1",
+                "  < sink_a passed 0..1 at 1:1-1:1",
+                "< sink_x passed 0..1 at 1:1-1:1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::*;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// A failed [try_map](trait.Parser.html#method.try_map), with the range the subparser consumed
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct MapError<E> {
+    pub error: E,
+    pub range: Range<usize>,
+}
+impl<E: Display> Display for MapError<E> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.error, self.range.start, self.range.end
+        )
+    }
+}
+impl<E: Error + 'static> Error for MapError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Map the output with a function that can fail, see [try_map](trait.Parser.html#method.try_map)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TryMap<B: Parser<I>, I: TimeTravel, F> {
+    base: B,
+    f: F,
+    _i: PhantomData<fn(I)>,
+}
+impl<B: Parser<I>, I: TimeTravel, U, E, F> TryMap<B, I, F>
+where
+    F: Fn(B::Output) -> Result<U, E>,
+{
+    pub fn new(base: B, f: F) -> Self {
+        Self {
+            base,
+            f,
+            _i: PhantomData,
+        }
+    }
+}
+impl<B: Parser<I>, I: Memoizable, U, E, F> Parser<I> for TryMap<B, I, F>
+where
+    F: Fn(B::Output) -> Result<U, E>,
+{
+    type Output = Result<U, MapError<E>>;
+
+    fn parse(&self, mut input: I) -> Option<Self::Output> {
+        input.re_ready();
+        let from = input.save();
+        let base = self.base.parse(input.ref_clone())?;
+        Some((self.f)(base).map_err(|error| MapError {
+            error,
+            range: from..input.consumed_end(),
+        }))
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
+}
+
+/// Map the output, fail without consuming if f returns None, see [map_opt](trait.Parser.html#method.map_opt)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MapOpt<B: Parser<I>, I: TimeTravel, F> {
+    base: B,
+    f: F,
+    _i: PhantomData<fn(I)>,
+}
+impl<B: Parser<I>, I: TimeTravel, U, F> MapOpt<B, I, F>
+where
+    F: Fn(B::Output) -> Option<U>,
+{
+    pub fn new(base: B, f: F) -> Self {
+        Self {
+            base,
+            f,
+            _i: PhantomData,
+        }
+    }
+}
+impl<B: Parser<I>, I: TimeTravel, U, F> Parser<I> for MapOpt<B, I, F>
+where
+    F: Fn(B::Output) -> Option<U>,
+{
+    type Output = U;
+
+    fn parse(&self, mut input: I) -> Option<Self::Output> {
+        let from = input.save();
+        let base = self.base.parse(input.ref_clone())?;
+        let r = (self.f)(base);
+        if r.is_none() {
+            input.back(from);
+        }
+        r
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
+}
+
+/// Fail without consuming if the predicate rejects the output, see [filter](trait.Parser.html#method.filter)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Filter<B: Parser<I>, I: TimeTravel, F> {
+    base: B,
+    f: F,
+    _i: PhantomData<fn(I)>,
+}
+impl<B: Parser<I>, I: TimeTravel, F> Filter<B, I, F>
+where
+    F: Fn(&B::Output) -> bool,
+{
+    pub fn new(base: B, f: F) -> Self {
+        Self {
+            base,
+            f,
+            _i: PhantomData,
+        }
+    }
+}
+impl<B: Parser<I>, I: TimeTravel, F> Parser<I> for Filter<B, I, F>
+where
+    F: Fn(&B::Output) -> bool,
+{
+    type Output = B::Output;
+
+    fn parse(&self, mut input: I) -> Option<Self::Output> {
+        let from = input.save();
+        let base = self.base.parse(input.ref_clone())?;
+        if (self.f)(&base) {
+            Some(base)
+        } else {
+            input.back(from);
+            None
+        }
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
+}
+
+/// Fail without consuming if the predicate rejects the input and output, see [verify](trait.Parser.html#method.verify)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Verify<B: Parser<I>, I: TimeTravel, F> {
+    base: B,
+    f: F,
+    _i: PhantomData<fn(I)>,
+}
+impl<B: Parser<I>, I: TimeTravel, F> Verify<B, I, F>
+where
+    F: Fn(&I, &B::Output) -> bool,
+{
+    pub fn new(base: B, f: F) -> Self {
+        Self {
+            base,
+            f,
+            _i: PhantomData,
+        }
+    }
+}
+impl<B: Parser<I>, I: TimeTravel, F> Parser<I> for Verify<B, I, F>
+where
+    F: Fn(&I, &B::Output) -> bool,
+{
+    type Output = B::Output;
+
+    fn parse(&self, mut input: I) -> Option<Self::Output> {
+        let from = input.save();
+        let base = self.base.parse(input.ref_clone())?;
+        if (self.f)(&input, &base) {
+            Some(base)
+        } else {
+            input.back(from);
+            None
+        }
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.base.describe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn digits(input: CharSpan) -> Option<String> {
+        satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false))
+            .many1()
+            .map(|r| input.com_string(range_of_many1(r)).unwrap())
+            .parse(input.ref_clone())
+    }
+
+    #[test]
+    fn test_try_map() {
+        let x = substr("x=").and(digits.try_map(|s| s.parse::<u8>()));
+
+        let span = "x=42".span();
+        let r = x.parse(span.ref_clone());
+        println!("{:?}", r);
+        assert_eq!(r.map(|(_, v)| v), Some(Ok(42)));
+
+        let span = "x=300;".span();
+        let (_, r) = x.parse(span.ref_clone()).unwrap();
+        let e = r.unwrap_err();
+        println!("{}", e);
+        assert_eq!(e.range, 2..5);
+        assert_eq!(span.save(), 5);
+    }
+
+    #[test]
+    fn test_reject() {
+        let byte = || digits.map_opt(|s| s.parse::<u8>().ok());
+        let x = byte().or(digits.map(|_| 0u8));
+        let span = "300".span();
+        assert_eq!(x.parse(span.ref_clone()), Some(0));
+        let span = "300".span();
+        assert_eq!(byte().parse(span.ref_clone()), None);
+        assert_eq!(span.save(), 0);
+
+        let even = digits.filter(|s| s.len() % 2 == 0);
+        let span = "123".span();
+        assert_eq!(even.parse(span.ref_clone()), None);
+        assert_eq!(span.save(), 0);
+
+        fn word(input: CharSpan) -> Option<std::ops::Range<usize>> {
+            satisfy(|c: Char| c.char(|c, _| c.is_alphabetic()).unwrap_or(false))
+                .many1()
+                .map(range_of_many1)
+                .parse(input)
+        }
+        let ident = word.verify(|i: &CharSpan, r| i.com_string(r.clone()).unwrap() != "let");
+        let span = "let".span();
+        assert_eq!(ident.parse(span.ref_clone()), None);
+        assert_eq!(span.save(), 0);
+        assert_eq!(ident.parse("lets".span()), Some(0..4));
+    }
+}
@@ -1,14 +1,38 @@
-pub mod cell;
 mod char_span;
 mod chars;
+pub(crate) mod cursor;
+mod describe;
+mod feed;
+mod grapheme;
+mod incremental;
+mod line_index;
 mod loc;
+#[cfg(feature = "derive")]
+mod parse;
+mod source_map;
 mod span;
+mod splice;
+mod stateful;
+mod syntax;
+mod sync_span;
 mod time_travel;
 mod timeline;
 
 pub use char_span::*;
 pub use chars::*;
+pub use describe::*;
+pub use feed::*;
+pub use grapheme::*;
+pub use incremental::*;
+pub use line_index::*;
 pub use loc::*;
+#[cfg(feature = "derive")]
+pub use parse::*;
+pub use source_map::*;
 pub use span::*;
+pub use splice::*;
+pub use stateful::*;
+pub use syntax::*;
+pub use sync_span::*;
 pub use time_travel::*;
 pub use timeline::*;
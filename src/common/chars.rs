@@ -1,5 +1,4 @@
 use super::*;
-use libsugar::Also;
 use serde::{Deserialize, Serialize};
 use std::convert::From;
 use std::fmt;
@@ -100,10 +99,10 @@ impl PartialEq<char> for Char {
         }
     }
 }
-impl Into<char> for Char {
+impl From<Char> for char {
     #[inline]
-    fn into(self) -> char {
-        self.c()
+    fn from(c: Char) -> char {
+        c.c()
     }
 }
 impl GetString for Char {
@@ -121,25 +120,168 @@ impl GetChar for Char {
 
 //\/////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Options of [CharChars](struct.CharChars.html)  
+/// `'\n'`, `'\r'` and `"\r\n"` are newlines by default, and a tab is 1 column wide
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let options = CharCharsOptions::new().tab_width(4).unicode_newlines(true);
+/// let chars: Vec<Char> = CharChars::with_options("\ta\u{2028}b".chars(), options).collect();
+/// assert_eq!(chars[1], Char::Char('a', Loc::new_at_col(1, 0, 1, 4)));
+/// assert!(chars[2].is_wrap());
+/// assert_eq!(chars[3].loc().line, 1);
+/// ```
+#[derive(Debug, Clone, Copy, Hash, PartialOrd, Ord, PartialEq, Eq)]
+pub struct CharCharsOptions {
+    cr: bool,
+    split_crlf: bool,
+    unicode: bool,
+    vt_ff: bool,
+    tab_width: usize,
+}
+impl CharCharsOptions {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            cr: true,
+            split_crlf: false,
+            unicode: false,
+            vt_ff: false,
+            tab_width: 1,
+        }
+    }
+    /// Treat a lone `'\r'` as a newline, default true  
+    /// `"\r\n"` is always a single line break
+    #[inline]
+    pub const fn cr(mut self, v: bool) -> Self {
+        self.cr = v;
+        self
+    }
+    /// Keep `"\r\n"` as two `Wrap` items instead of one, default false  
+    /// The line still only advances once, after the `'\n'`
+    #[inline]
+    pub const fn split_crlf(mut self, v: bool) -> Self {
+        self.split_crlf = v;
+        self
+    }
+    /// Treat NEL `U+0085`, LS `U+2028` and PS `U+2029` as newlines, default false
+    #[inline]
+    pub const fn unicode_newlines(mut self, v: bool) -> Self {
+        self.unicode = v;
+        self
+    }
+    /// Treat VT `U+000B` and FF `U+000C` as newlines, default false
+    #[inline]
+    pub const fn vt_ff(mut self, v: bool) -> Self {
+        self.vt_ff = v;
+        self
+    }
+    /// Width of a tab stop used for `Loc::col`, default 1
+    #[inline]
+    pub const fn tab_width(mut self, width: usize) -> Self {
+        self.tab_width = if width == 0 { 1 } else { width };
+        self
+    }
+    /// Get the tab width
+    #[inline]
+    pub const fn get_tab_width(&self) -> usize {
+        self.tab_width
+    }
+    /// Check if a char is a newline, not including `'\r'` which depends on what follows
+    #[inline]
+    pub fn is_newline(&self, c: char) -> bool {
+        match c {
+            '\n' => true,
+            '\u{85}' | '\u{2028}' | '\u{2029}' => self.unicode,
+            '\u{B}' | '\u{C}' => self.vt_ff,
+            _ => false,
+        }
+    }
+    /// Check if `'\r'` is a newline
+    #[inline]
+    pub fn is_cr_newline(&self) -> bool {
+        self.cr
+    }
+    /// Check if `"\r\n"` is kept as two items
+    #[inline]
+    pub fn is_split_crlf(&self) -> bool {
+        self.split_crlf
+    }
+    /// Visual column after `c` when it is at `col`
+    #[inline]
+    pub fn next_col(&self, c: char, col: usize) -> usize {
+        if c == '\t' {
+            (col / self.tab_width + 1) * self.tab_width
+        } else {
+            col + 1
+        }
+    }
+}
+impl Default for CharCharsOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
 /// An iterator that produces [`Char`](enum.Char.html)
 #[derive(Debug, Clone, Hash, PartialOrd, Ord, PartialEq, Eq, Default)]
 pub struct CharChars<I: Iterator<Item = char>> {
     iter: I,
     loc: Loc,
-    isr: Option<Loc>,
+    options: CharCharsOptions,
     end: bool,
-    nextret: Option<Char>,
+    peeked: Option<char>,
+    file: Option<FileId>,
 }
 impl<I: Iterator<Item = char>> CharChars<I> {
     pub fn new(iter: I) -> Self {
+        Self::with_options(iter, CharCharsOptions::new())
+    }
+    pub fn with_options(iter: I, options: CharCharsOptions) -> Self {
         Self {
             iter,
             loc: Loc::new(),
-            isr: None,
+            options,
             end: false,
-            nextret: None,
+            peeked: None,
+            file: None,
         }
     }
+    /// Mark the chars as coming from a file of a [SourceMap](struct.SourceMap.html)
+    #[inline]
+    pub fn with_file(mut self, file: FileId) -> Self {
+        self.file = Some(file);
+        self
+    }
+    /// Get the options
+    #[inline]
+    pub fn options(&self) -> &CharCharsOptions {
+        &self.options
+    }
+    #[inline]
+    fn next_raw(&mut self) -> Option<char> {
+        if let Some(c) = self.peeked.take() {
+            return Some(c);
+        }
+        if self.end {
+            return None;
+        }
+        let c = self.iter.next();
+        if c.is_none() {
+            self.end = true;
+        }
+        c
+    }
+    #[inline]
+    fn new_line(&mut self, len: usize) {
+        self.loc.offset += len;
+        self.loc.line += 1;
+        self.loc.char = 0;
+        self.loc.col = 0;
+    }
 }
 impl<I: Iterator<Item = char>> From<I> for CharChars<I> {
     #[inline]
@@ -147,76 +289,50 @@ impl<I: Iterator<Item = char>> From<I> for CharChars<I> {
         Self::new(iter)
     }
 }
+impl<I: Iterator<Item = char>> GetFileId for CharChars<I> {
+    #[inline]
+    fn file_id(&self) -> Option<FileId> {
+        self.file
+    }
+}
 impl<I: Iterator<Item = char>> Iterator for CharChars<I> {
     type Item = Char;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(r) = self.nextret {
-            self.nextret = None;
-            return Some(r);
-        }
-        if self.end {
-            return None;
-        }
-        loop {
-            let n = self.iter.next();
-            if let Some(c) = n {
-                if let Some(l) = self.isr {
-                    if c == '\n' {
-                        self.isr = None;
-                        return Some(Char::Wrap(l)).also(|_| {
-                            self.loc.offset += 1;
-                        });
-                    } else {
-                        if c == '\r' {
-                            self.isr = Some(self.loc);
-                            self.loc.offset += 1;
-                            self.loc.char = 0;
-                            self.loc.line += 1;
-                            return Some(Char::Wrap(l));
-                        } else {
-                            self.isr = None;
-                            self.nextret = Some(Char::Char(c, self.loc));
-                            self.loc.offset += 1;
-                            self.loc.char += 1;
-                            return Some(Char::Wrap(l));
-                        }
-                    }
+        let c = self.next_raw()?;
+        let loc = self.loc;
+        if c == '\r' && self.options.cr {
+            let n = self.next_raw();
+            if n == Some('\n') {
+                if self.options.split_crlf {
+                    self.peeked = n;
+                    self.loc.offset += 1;
+                    self.loc.char += 1;
+                    self.loc.col += 1;
                 } else {
-                    if c == '\r' {
-                        self.isr = Some(self.loc);
-                        self.loc.offset += 1;
-                        self.loc.char = 0;
-                        self.loc.line += 1;
-                    } else if c == '\n' {
-                        return Some(Char::Wrap(self.loc)).also(|_| {
-                            self.loc.offset += 1;
-                            self.loc.char = 0;
-                            self.loc.line += 1;
-                        });
-                    } else {
-                        return Some(Char::Char(c, self.loc)).also(|_| {
-                            self.loc.offset += 1;
-                            self.loc.char += 1;
-                        });
-                    }
+                    self.new_line(2);
                 }
             } else {
-                if let Some(l) = self.isr {
-                    self.end = true;
-                    return Some(Char::Wrap(l));
-                } else {
-                    self.end = true;
-                    return None;
-                }
+                self.peeked = n;
+                self.new_line(1);
             }
+            return Some(Char::Wrap(loc));
         }
+        if self.options.is_newline(c) {
+            self.new_line(1);
+            return Some(Char::Wrap(loc));
+        }
+        self.loc.offset += 1;
+        self.loc.char += 1;
+        self.loc.col = self.options.next_col(c, self.loc.col);
+        Some(Char::Char(c, loc))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Char, CharChars, Loc};
+    use super::{Char, CharChars, CharCharsOptions, Loc};
+    use crate::GetLoc;
 
     #[test]
     fn test_char_chars() {
@@ -232,4 +348,72 @@ mod tests {
         assert_eq!(cc.next(), Some(Char::Char('d', Loc::new_at(7, 3, 0))));
         assert_eq!(cc.next(), None);
     }
+
+    #[test]
+    fn test_cr_end() {
+        let code = "a\r";
+        let mut cc = CharChars::new(code.chars());
+
+        assert_eq!(cc.next(), Some(Char::Char('a', Loc::new_at(0, 0, 0))));
+        assert_eq!(cc.next(), Some(Char::Wrap(Loc::new_at(1, 0, 1))));
+        assert_eq!(cc.next(), None);
+        assert_eq!(cc.next(), None);
+    }
+
+    #[test]
+    fn test_split_crlf() {
+        let code = "a\r\nb";
+        let options = CharCharsOptions::new().split_crlf(true);
+        let mut cc = CharChars::with_options(code.chars(), options);
+
+        assert_eq!(cc.next(), Some(Char::Char('a', Loc::new_at(0, 0, 0))));
+        assert_eq!(cc.next(), Some(Char::Wrap(Loc::new_at(1, 0, 1))));
+        assert_eq!(cc.next(), Some(Char::Wrap(Loc::new_at(2, 0, 2))));
+        assert_eq!(cc.next(), Some(Char::Char('b', Loc::new_at(3, 1, 0))));
+        assert_eq!(cc.next(), None);
+    }
+
+    #[test]
+    fn test_no_cr() {
+        let code = "a\rb\r\nc";
+        let options = CharCharsOptions::new().cr(false);
+        let mut cc = CharChars::with_options(code.chars(), options);
+
+        assert_eq!(cc.next(), Some(Char::Char('a', Loc::new_at(0, 0, 0))));
+        assert_eq!(cc.next(), Some(Char::Char('\r', Loc::new_at(1, 0, 1))));
+        assert_eq!(cc.next(), Some(Char::Char('b', Loc::new_at(2, 0, 2))));
+        assert_eq!(cc.next(), Some(Char::Char('\r', Loc::new_at(3, 0, 3))));
+        assert_eq!(cc.next(), Some(Char::Wrap(Loc::new_at(4, 0, 4))));
+        assert_eq!(cc.next(), Some(Char::Char('c', Loc::new_at(5, 1, 0))));
+    }
+
+    #[test]
+    fn test_unicode_newlines() {
+        let code = "a\u{2028}b\u{85}c\u{C}d";
+        let mut cc = CharChars::new(code.chars());
+        assert_eq!(cc.filter(|c| c.is_wrap()).count(), 0);
+
+        let options = CharCharsOptions::new().unicode_newlines(true);
+        cc = CharChars::with_options(code.chars(), options);
+        let wraps: Vec<usize> = cc.filter_map(|c| c.wrap(|l| l.line)).collect();
+        assert_eq!(wraps, vec![0, 1]);
+
+        let options = options.vt_ff(true);
+        cc = CharChars::with_options(code.chars(), options);
+        assert_eq!(cc.last(), Some(Char::Char('d', Loc::new_at(6, 3, 0))));
+    }
+
+    #[test]
+    fn test_tab_width() {
+        let code = "\ta\tbc\t\td";
+        let options = CharCharsOptions::new().tab_width(4);
+        let cols: Vec<usize> = CharChars::with_options(code.chars(), options)
+            .map(|c| c.loc().col)
+            .collect();
+
+        assert_eq!(cols, vec![0, 4, 5, 8, 9, 10, 12, 16]);
+
+        let cols: Vec<usize> = CharChars::new(code.chars()).map(|c| c.loc().col).collect();
+        assert_eq!(cols, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    }
 }
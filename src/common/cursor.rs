@@ -0,0 +1,26 @@
+//! Positions of a shared CharSpan, for parsers that work on `&CharSpan` instead of moving it
+
+use crate::*;
+
+/// The position after what was read
+pub(crate) fn now(input: &CharSpan) -> usize {
+    let mut input = input.ref_clone();
+    input.re_ready();
+    input.save()
+}
+
+/// Go back to a position from `now`
+pub(crate) fn back(input: &CharSpan, index: usize) {
+    input.ref_clone().back(index)
+}
+
+/// Location of start to the last consumed item, empty at start if nothing was consumed
+pub(crate) fn loc(input: &CharSpan, start: usize) -> LocRange {
+    let end = input.consumed_end();
+    if end > start {
+        input.loc_range(start..end)
+    } else {
+        input.loc(start).map(|l| LocRange::new(l, l))
+    }
+    .unwrap_or_default()
+}
@@ -0,0 +1,211 @@
+use std::any::type_name;
+use std::fmt::Debug;
+
+/// What a parser accepts, see [describe](trait.Parser.html#method.describe)
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let x = substr("a").or(one('b').literal()).many1().label("ab");
+/// let node = Parser::<CharSpan>::describe(&x);
+/// assert_eq!(
+///     node,
+///     GrammarNode::Label {
+///         name: "ab".to_string(),
+///         node: Box::new(GrammarNode::Repeat {
+///             node: Box::new(GrammarNode::Choice(vec![
+///                 GrammarNode::Literal("a".to_string()),
+///                 GrammarNode::Literal("b".to_string()),
+///             ])),
+///             min: 1,
+///             max: None,
+///         }),
+///     }
+/// );
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum GrammarNode {
+    /// All in order
+    Seq(Vec<GrammarNode>),
+    /// The first that passes
+    Choice(Vec<GrammarNode>),
+    /// min to max times, no max is unbounded
+    Repeat {
+        node: Box<GrammarNode>,
+        min: usize,
+        max: Option<usize>,
+    },
+    /// Pass if node fails
+    Not(Box<GrammarNode>),
+    /// Items matched exactly, chars are written escaped without quotes, other items with `{:?}` separated by spaces
+    Literal(String),
+    /// One item out of a set, like `[a-z]`, `.` is any item
+    Class(String),
+    /// A named parser and its definition
+    Label {
+        name: String,
+        node: Box<GrammarNode>,
+    },
+    /// A named parser defined elsewhere, recursion stops here
+    Ref(String),
+    /// A parser that can not be looked into, like a closure or [satisfy](fn.satisfy.html)
+    Opaque(String),
+}
+
+impl GrammarNode {
+    /// Nodes directly inside
+    pub fn children(&self) -> &[GrammarNode] {
+        match self {
+            Self::Seq(v) | Self::Choice(v) => v,
+            Self::Repeat { node, .. } | Self::Not(node) | Self::Label { node, .. } => {
+                std::slice::from_ref(node)
+            }
+            Self::Literal(_) | Self::Class(_) | Self::Ref(_) | Self::Opaque(_) => &[],
+        }
+    }
+    /// Visit this node and everything inside, parents first
+    pub fn walk(&self, f: &mut impl FnMut(&GrammarNode)) {
+        f(self);
+        for c in self.children() {
+            c.walk(f);
+        }
+    }
+
+    /// Seq of a and b, flattening nested seqs
+    pub(crate) fn seq(a: Self, b: Self) -> Self {
+        let mut v = match a {
+            Self::Seq(v) => v,
+            a => vec![a],
+        };
+        match b {
+            Self::Seq(b) => v.extend(b),
+            b => v.push(b),
+        }
+        Self::Seq(v)
+    }
+    /// Choice of a and b, flattening nested choices
+    pub(crate) fn choice(a: Self, b: Self) -> Self {
+        let mut v = match a {
+            Self::Choice(v) => v,
+            a => vec![a],
+        };
+        match b {
+            Self::Choice(b) => v.extend(b),
+            b => v.push(b),
+        }
+        Self::Choice(v)
+    }
+    pub(crate) fn repeat(node: Self, min: usize, max: Option<usize>) -> Self {
+        Self::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        }
+    }
+    /// The text of a [Literal](#variant.Literal)
+    pub(crate) fn literal_text<T: Debug>(items: &[T]) -> String {
+        let items: Vec<String> = items.iter().map(|i| format!("{:?}", i)).collect();
+        let chars = items
+            .iter()
+            .all(|i| i.len() > 2 && i.starts_with('\'') && i.ends_with('\''));
+        if chars {
+            items.iter().map(|i| &i[1..i.len() - 1]).collect()
+        } else {
+            items.join(" ")
+        }
+    }
+    /// The name, or the type name without its path
+    pub(crate) fn opaque<T: ?Sized>(name: Option<&str>) -> Self {
+        Self::Opaque(match name {
+            Some(name) => name.to_string(),
+            None => {
+                // drop generics and impl blocks, they can contain `::` too
+                let mut depth = 0;
+                let ty: String = type_name::<T>()
+                    .chars()
+                    .filter(|&c| {
+                        match c {
+                            '<' => depth += 1,
+                            '>' => depth -= 1,
+                            _ => return depth == 0,
+                        }
+                        false
+                    })
+                    .collect();
+                match ty.rsplit("::").next().unwrap_or(&ty) {
+                    "{{closure}}" => "closure".to_string(),
+                    ty => ty.to_string(),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn digit(input: CharSpan) -> Option<std::ops::Range<usize>> {
+        satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false)).parse(input)
+    }
+
+    #[test]
+    fn test_describe() {
+        let x = substr("let")
+            .and(one(' ').literal().many1())
+            .and(digit.or(|_: CharSpan| Some(0..0)).not())
+            .and(substr("=").may())
+            .map(|_| ());
+        let r = Parser::<CharSpan>::describe(&x);
+        println!("{:?}", r);
+        assert_eq!(
+            r,
+            GrammarNode::Seq(vec![
+                GrammarNode::Literal("let".to_string()),
+                GrammarNode::repeat(GrammarNode::Literal(" ".to_string()), 1, None),
+                GrammarNode::Not(Box::new(GrammarNode::Choice(vec![
+                    GrammarNode::Opaque("digit".to_string()),
+                    GrammarNode::Opaque("closure".to_string()),
+                ]))),
+                GrammarNode::repeat(GrammarNode::Literal("=".to_string()), 0, Some(1)),
+            ])
+        );
+        assert_eq!(
+            Parser::<Span<std::vec::IntoIter<i32>>>::describe(&sub(vec![1, 2]).literal()),
+            GrammarNode::Literal("1 2".to_string())
+        );
+        #[cfg(feature = "derive")]
+        assert_eq!(
+            Parser::<CharSpan>::describe(&Ident::parse),
+            GrammarNode::Opaque("parse".to_string())
+        );
+        assert_eq!(
+            Parser::<CharSpan>::describe(&one('\n').literal()),
+            GrammarNode::Literal("\\n".to_string())
+        );
+        assert_eq!(
+            Parser::<CharSpan>::describe(&one('\n')),
+            GrammarNode::Opaque("one".to_string())
+        );
+    }
+
+    #[test]
+    fn test_recursive() {
+        let nest = recursive(|this: Dyn<CharSpan, ()>| {
+            one('(')
+                .and(this.may())
+                .and(one(')'))
+                .map(|_| ())
+                .label("nest")
+        });
+        let r = nest.describe();
+        println!("{:?}", r);
+        let mut refs = vec![];
+        r.walk(&mut |n| {
+            if let GrammarNode::Ref(name) = n {
+                refs.push(name.clone())
+            }
+        });
+        assert_eq!(refs, vec!["nest".to_string()]);
+        assert_eq!(r.children().len(), 1);
+    }
+}
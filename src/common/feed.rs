@@ -0,0 +1,396 @@
+use crate::*;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Result of feeding a [FeedParser](struct.FeedParser.html)
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Feed<O> {
+    /// The parser ran into the end of the buffered input, feed more or close
+    NeedMore,
+    /// Parsed one output, the consumed bytes are removed from the buffer  
+    /// At least one byte is consumed, a parser that passes on nothing gets NeedMore or [Empty](enum.FeedError.html#variant.Empty)
+    Done(O, usize),
+    /// The input can not be parsed, no matter what comes next
+    Error(FeedError),
+}
+impl<O> Feed<O> {
+    #[inline]
+    pub fn is_need_more(&self) -> bool {
+        matches!(self, Feed::NeedMore)
+    }
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        matches!(self, Feed::Done(..))
+    }
+    #[inline]
+    pub fn is_error(&self) -> bool {
+        matches!(self, Feed::Error(_))
+    }
+}
+
+/// Why a [FeedParser](struct.FeedParser.html) failed, offsets are bytes in the buffer
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum FeedError {
+    /// Invalid utf-8 at the offset
+    Utf8(usize),
+    /// The parser failed, the offset is the furthest char it read
+    Failed(usize),
+    /// The input is closed and the parser passed without consuming anything, it would give the same output forever
+    Empty,
+}
+impl Display for FeedError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FeedError::Utf8(at) => write!(f, "invalid utf-8 at byte {}", at),
+            FeedError::Failed(at) => write!(f, "parse failed at byte {}", at),
+            FeedError::Empty => write!(f, "parse consumed nothing"),
+        }
+    }
+}
+impl Error for FeedError {}
+
+/// Parse input that arrives in chunks, like a line protocol off a socket  
+/// Each call parses the buffered text from the start, if the parser reads past the end of it the result is [NeedMore](enum.Feed.html#variant.NeedMore) instead of a failure  
+/// A `'\r'` at the end is held back until the next chunk, so a split `"\r\n"` is still one Wrap  
+/// Nothing is kept between calls, an output that arrives in n chunks is parsed n times, so a long output fed in small chunks costs O(n²)  
+/// Only what was consumed is dropped, use it for many short outputs like lines or frames, not for one large document
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// fn line(input: CharSpan) -> Option<String> {
+///     satisfy(|c: Char| c.is_char())
+///         .many()
+///         .and(one('\n'))
+///         .map(|(r, _)| r.into_iter().map(|r| input.com_string(r).unwrap()).collect())
+///         .parse(input.ref_clone())
+/// }
+/// let mut parser = FeedParser::new(line);
+/// assert_eq!(parser.feed("PI"), Feed::NeedMore);
+/// assert_eq!(parser.feed("NG\nQU"), Feed::Done("PING".to_string(), 5));
+/// assert_eq!(parser.buffered(), "QU");
+/// assert!(parser.close().is_error());
+/// ```
+#[derive(Debug)]
+pub struct FeedParser<P> {
+    parser: P,
+    buf: String,
+    /// bytes not yet decoded, an incomplete utf-8 sequence at the end of a chunk
+    pending: Vec<u8>,
+    closed: bool,
+    consumed: usize,
+}
+impl<P> FeedParser<P> {
+    #[inline]
+    pub fn new(parser: P) -> Self {
+        Self {
+            parser,
+            buf: String::new(),
+            pending: vec![],
+            closed: false,
+            consumed: 0,
+        }
+    }
+    /// The text received but not yet consumed
+    #[inline]
+    pub fn buffered(&self) -> &str {
+        &self.buf
+    }
+    /// Nothing is buffered
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty() && self.pending.is_empty()
+    }
+    /// The input has ended
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+    /// Total bytes consumed by all outputs
+    #[inline]
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+    /// Append a chunk and parse
+    pub fn feed<O>(&mut self, chunk: impl AsRef<[u8]>) -> Feed<O>
+    where
+        P: for<'a> Parser<CharSpan<'a>, Output = O>,
+    {
+        assert!(!self.closed, "feed after close");
+        self.pending.extend_from_slice(chunk.as_ref());
+        match std::str::from_utf8(&self.pending) {
+            Ok(s) => {
+                self.buf.push_str(s);
+                self.pending.clear();
+            }
+            Err(e) => {
+                let valid = e.valid_up_to();
+                self.buf
+                    .push_str(std::str::from_utf8(&self.pending[..valid]).unwrap());
+                self.pending.drain(..valid);
+                if e.error_len().is_some() {
+                    return Feed::Error(FeedError::Utf8(self.buf.len()));
+                }
+            }
+        }
+        self.poll()
+    }
+    /// Mark the end of input and parse what is left, never returns NeedMore
+    pub fn close<O>(&mut self) -> Feed<O>
+    where
+        P: for<'a> Parser<CharSpan<'a>, Output = O>,
+    {
+        self.closed = true;
+        if !self.pending.is_empty() {
+            return Feed::Error(FeedError::Utf8(self.buf.len()));
+        }
+        self.poll()
+    }
+    /// Parse the buffered text again without new input  
+    /// Use after Done to get the next output already in the buffer
+    pub fn poll<O>(&mut self) -> Feed<O>
+    where
+        P: for<'a> Parser<CharSpan<'a>, Output = O>,
+    {
+        let hold = !self.closed && self.buf.ends_with('\r');
+        let src = if hold {
+            &self.buf[..self.buf.len() - 1]
+        } else {
+            &self.buf[..]
+        };
+        // byte offset of every item, a `"\r\n"` is one item
+        let chars: Vec<usize> = src.char_indices().map(|(b, _)| b).collect();
+        let items: Vec<usize> = CharChars::new(src.chars())
+            .map(|c| chars[c.loc().offset])
+            .collect();
+        let byte_of = |item: usize| items.get(item).copied().unwrap_or(src.len());
+
+        let span = src.span();
+        let r = self.parser.parse(span.ref_clone());
+        if !self.closed && span.peak() > items.len() {
+            return Feed::NeedMore;
+        }
+        match r {
+            None => Feed::Error(FeedError::Failed(byte_of(span.peak().max(1) - 1))),
+            Some(o) => {
+                let end = span.consumed_end();
+                let bytes = byte_of(end);
+                if bytes == 0 {
+                    return if self.closed {
+                        Feed::Error(FeedError::Empty)
+                    } else {
+                        Feed::NeedMore
+                    };
+                }
+                self.buf.drain(..bytes);
+                self.consumed += bytes;
+                Feed::Done(o, bytes)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use self::reader::*;
+
+#[cfg(feature = "async")]
+mod reader {
+    use super::*;
+    use futures::io::{AsyncRead, AsyncReadExt};
+    use std::io;
+
+    /// Read outputs of a [FeedParser](struct.FeedParser.html) from an `AsyncRead`  
+    /// Parse errors are `io::ErrorKind::InvalidData` wrapping a [FeedError](enum.FeedError.html)
+    #[derive(Debug)]
+    pub struct FeedReader<R, P> {
+        reader: R,
+        parser: FeedParser<P>,
+        chunk: Vec<u8>,
+    }
+    impl<R: AsyncRead + Unpin, P> FeedReader<R, P> {
+        #[inline]
+        pub fn new(reader: R, parser: P) -> Self {
+            Self::with_capacity(reader, parser, 4096)
+        }
+        /// Read at most `capacity` bytes at a time
+        #[inline]
+        pub fn with_capacity(reader: R, parser: P, capacity: usize) -> Self {
+            Self {
+                reader,
+                parser: FeedParser::new(parser),
+                chunk: vec![0; capacity.max(1)],
+            }
+        }
+        #[inline]
+        pub fn parser(&self) -> &FeedParser<P> {
+            &self.parser
+        }
+        #[inline]
+        pub fn into_inner(self) -> R {
+            self.reader
+        }
+        /// The next output, None if the input ended with nothing left
+        pub async fn next<O>(&mut self) -> io::Result<Option<O>>
+        where
+            P: for<'a> Parser<CharSpan<'a>, Output = O>,
+        {
+            if self.parser.is_closed() && self.parser.is_empty() {
+                return Ok(None);
+            }
+            let mut r = self.parser.poll();
+            loop {
+                match r {
+                    Feed::Done(o, _) => return Ok(Some(o)),
+                    Feed::Error(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                    Feed::NeedMore => {}
+                }
+                let n = self.reader.read(&mut self.chunk).await?;
+                r = if n != 0 {
+                    self.parser.feed(&self.chunk[..n])
+                } else if self.parser.is_empty() {
+                    self.parser.closed = true;
+                    return Ok(None);
+                } else {
+                    self.parser.close()
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn line(input: CharSpan) -> Option<String> {
+        satisfy(|c: Char| c.is_char())
+            .many()
+            .and(satisfy(|c: Char| c.is_wrap()))
+            .map(|(r, _)| {
+                r.into_iter()
+                    .map(|r| input.com_string(r).unwrap())
+                    .collect()
+            })
+            .parse(input.ref_clone())
+    }
+    fn command(input: CharSpan) -> Option<&'static str> {
+        substr("GET")
+            .map(|_| "get")
+            .or(substr("SET").map(|_| "set"))
+            .and(one(' '))
+            .map(|(c, _)| c)
+            .parse(input)
+    }
+
+    #[test]
+    fn test_chunks() {
+        let mut parser = FeedParser::new(line);
+        let r: Vec<Feed<String>> = vec!["a", "b\r", "\nc\n", ""]
+            .into_iter()
+            .map(|c| parser.feed(c))
+            .collect();
+        println!("{:?}", r);
+        assert_eq!(
+            r,
+            vec![
+                Feed::NeedMore,
+                Feed::NeedMore,
+                Feed::Done("ab".to_string(), 4),
+                Feed::Done("c".to_string(), 2),
+            ]
+        );
+        assert_eq!(parser.consumed(), 6);
+        assert_eq!(parser.close::<String>(), Feed::Error(FeedError::Failed(0)));
+    }
+
+    #[test]
+    fn test_incomplete_or_failed() {
+        let mut parser = FeedParser::new(command);
+        assert_eq!(parser.feed("SE"), Feed::NeedMore);
+        assert_eq!(parser.feed("T GE"), Feed::Done("set", 4));
+        assert_eq!(parser.poll::<&str>(), Feed::NeedMore);
+        assert_eq!(parser.feed("X "), Feed::Error(FeedError::Failed(2)));
+    }
+
+    #[test]
+    fn test_empty() {
+        fn a(input: CharSpan) -> Option<usize> {
+            one('a').many().map(|v| v.len()).parse(input)
+        }
+        let mut parser = FeedParser::new(a);
+        assert_eq!(parser.feed("aab"), Feed::Done(2, 2));
+        assert_eq!(parser.poll::<usize>(), Feed::NeedMore);
+        assert_eq!(parser.feed("c"), Feed::NeedMore);
+        assert_eq!(parser.buffered(), "bc");
+        assert_eq!(parser.close::<usize>(), Feed::Error(FeedError::Empty));
+    }
+
+    #[test]
+    fn test_utf8() {
+        let mut parser = FeedParser::new(line);
+        let bytes = "中\n".as_bytes();
+        assert_eq!(parser.feed(&bytes[..1]), Feed::NeedMore);
+        assert_eq!(parser.feed(&bytes[1..]), Feed::Done("中".to_string(), 4));
+        assert_eq!(
+            parser.feed::<String>([b'a', 0xff]),
+            Feed::Error(FeedError::Utf8(1))
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_reader() {
+        use futures::channel::mpsc;
+        use futures::{executor, SinkExt, TryStreamExt};
+        use std::io;
+
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut reader = FeedReader::with_capacity(rx.into_async_read(), line, 3);
+        executor::block_on(async {
+            let send = async {
+                for chunk in &["PI", "NG\r", "\nPO", "NG\nQUIT\n"] {
+                    tx.send(Ok(chunk.as_bytes().to_vec())).await.unwrap();
+                }
+                tx.close().await.unwrap();
+            };
+            let recv = async {
+                let mut r = vec![];
+                while let Some(line) = reader.next().await.unwrap() {
+                    r.push(line);
+                }
+                r
+            };
+            let (_, r) = futures::join!(send, recv);
+            println!("{:?}", r);
+            assert_eq!(r, vec!["PING", "PONG", "QUIT"]);
+        });
+        assert_eq!(reader.parser().consumed(), 16);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_reader_error() {
+        let mut reader = FeedReader::new(&b"GET SET POST"[..], command);
+        futures::executor::block_on(async {
+            assert_eq!(reader.next().await.unwrap(), Some("get"));
+            assert_eq!(reader.next().await.unwrap(), Some("set"));
+            let e = reader.next().await.unwrap_err();
+            assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+            assert_eq!(e.to_string(), "parse failed at byte 0");
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_reader_empty() {
+        fn a(input: CharSpan) -> Option<usize> {
+            one('a').many().map(|v| v.len()).parse(input)
+        }
+        let mut reader = FeedReader::new(&b"aab"[..], a);
+        futures::executor::block_on(async {
+            assert_eq!(reader.next().await.unwrap(), Some(2));
+            let e = reader.next().await.unwrap_err();
+            assert_eq!(e.to_string(), "parse consumed nothing");
+        });
+    }
+}
@@ -0,0 +1,253 @@
+use super::*;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use unicode_segmentation::{GraphemeIndices, UnicodeSegmentation};
+
+/// Extended grapheme clusters and newlines with location  
+/// `Loc::offset` counts chars, so [LineIndex](struct.LineIndex.html) can convert it  
+/// but its range methods assume 1 char per item  
+/// `Loc::char` and `Loc::col` count clusters, so they match what users see
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash, PartialOrd, Ord)]
+pub enum Grapheme<'a> {
+    /// cluster and Loc
+    Grapheme(&'a str, Loc),
+    /// wrap, see [CharCharsOptions](struct.CharCharsOptions.html), and Loc
+    Wrap(Loc),
+}
+impl<'a> Grapheme<'a> {
+    /// Get the cluster  
+    /// when it is Wrap return `"\n"`
+    #[inline]
+    pub fn str(&self) -> &'a str {
+        match self {
+            Grapheme::Grapheme(s, _) => s,
+            Grapheme::Wrap(_) => "\n",
+        }
+    }
+    /// is `Grapheme`
+    #[inline]
+    pub fn is_grapheme(&self) -> bool {
+        matches!(self, Grapheme::Grapheme(_, _))
+    }
+    /// is `Wrap`
+    #[inline]
+    pub fn is_wrap(&self) -> bool {
+        matches!(self, Grapheme::Wrap(_))
+    }
+    /// just equivalent to eq, but ignore Loc
+    #[inline]
+    pub fn str_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Grapheme::Grapheme(a, _), Grapheme::Grapheme(b, _)) => a == b,
+            (Grapheme::Wrap(_), Grapheme::Wrap(_)) => true,
+            _ => false,
+        }
+    }
+}
+impl GetLoc for Grapheme<'_> {
+    #[inline]
+    fn loc(&self) -> Loc {
+        match self {
+            Grapheme::Grapheme(_, l) => *l,
+            Grapheme::Wrap(l) => *l,
+        }
+    }
+}
+impl Display for Grapheme<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Grapheme::Grapheme(s, _) => write!(f, "Grapheme({})", s),
+            Grapheme::Wrap(_) => write!(f, "Wrap"),
+        }
+    }
+}
+impl PartialEq<&str> for Grapheme<'_> {
+    /// Checks if a cluster is equal to it  
+    /// when it is Wrap check eq to `"\n"`
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.str() == *other
+    }
+}
+impl PartialEq<str> for Grapheme<'_> {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.str() == other
+    }
+}
+impl PartialEq<char> for Grapheme<'_> {
+    /// Checks if the cluster is exactly this char  
+    /// when it is Wrap check eq to `'\n'`
+    fn eq(&self, other: &char) -> bool {
+        let mut chars = self.str().chars();
+        chars.next() == Some(*other) && chars.next().is_none()
+    }
+}
+impl GetString for Grapheme<'_> {
+    #[inline]
+    fn get_string(&self) -> String {
+        self.str().to_string()
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// An iterator that produces [`Grapheme`](enum.Grapheme.html)  
+/// Newlines follow the same [CharCharsOptions](struct.CharCharsOptions.html) as [CharChars](struct.CharChars.html)  
+/// Every cluster is 1 column wide except tabs
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let code = "e\u{301}!";
+/// let r = one("e\u{301}").and(one('!')).parse(GraphemeChars::new(code).span());
+/// assert_eq!(r, Some((0..1, 1..2)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct GraphemeChars<'a> {
+    iter: GraphemeIndices<'a>,
+    loc: Loc,
+    options: CharCharsOptions,
+    /// the `'\n'` half of a split `"\r\n"`
+    pending: Option<Loc>,
+}
+impl<'a> GraphemeChars<'a> {
+    #[inline]
+    pub fn new(src: &'a str) -> Self {
+        Self::with_options(src, CharCharsOptions::new())
+    }
+    #[inline]
+    pub fn with_options(src: &'a str, options: CharCharsOptions) -> Self {
+        Self {
+            iter: src.grapheme_indices(true),
+            loc: Loc::new(),
+            options,
+            pending: None,
+        }
+    }
+    /// Get the options
+    #[inline]
+    pub fn options(&self) -> &CharCharsOptions {
+        &self.options
+    }
+    #[inline]
+    fn new_line(&mut self, len: usize) {
+        self.loc.offset += len;
+        self.loc.line += 1;
+        self.loc.char = 0;
+        self.loc.col = 0;
+    }
+    #[inline]
+    fn is_newline(&self, s: &str) -> bool {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some('\r'), None) => self.options.is_cr_newline(),
+            (Some(c), None) => self.options.is_newline(c),
+            _ => false,
+        }
+    }
+}
+impl<'a> Iterator for GraphemeChars<'a> {
+    type Item = Grapheme<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(loc) = self.pending.take() {
+            self.new_line(1);
+            return Some(Grapheme::Wrap(loc));
+        }
+        let (_, s) = self.iter.next()?;
+        let loc = self.loc;
+        if s == "\r\n" {
+            if self.options.is_cr_newline() && !self.options.is_split_crlf() {
+                self.new_line(2);
+                return Some(Grapheme::Wrap(loc));
+            }
+            // split in two, the line only advances after the '\n'
+            self.loc.offset += 1;
+            self.loc.char += 1;
+            self.loc.col += 1;
+            self.pending = Some(self.loc);
+            return Some(if self.options.is_cr_newline() {
+                Grapheme::Wrap(loc)
+            } else {
+                Grapheme::Grapheme(&s[..1], loc)
+            });
+        }
+        if self.is_newline(s) {
+            self.new_line(1);
+            return Some(Grapheme::Wrap(loc));
+        }
+        self.loc.offset += s.chars().count();
+        self.loc.char += 1;
+        self.loc.col = if s == "\t" {
+            self.options.next_col('\t', self.loc.col)
+        } else {
+            self.loc.col + 1
+        };
+        Some(Grapheme::Grapheme(s, loc))
+    }
+}
+
+/// A of kind Span, wrap [Grapheme](enum.Grapheme.html)
+pub type GraphemeSpan<'a> = Span<GraphemeChars<'a>>;
+
+impl<'a> SpanOf for GraphemeChars<'a> {
+    type SpanOfTarget = GraphemeSpan<'a>;
+
+    #[inline]
+    fn span(self) -> Self::SpanOfTarget {
+        GraphemeSpan::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_grapheme_chars() {
+        let code = "ae\u{301}\r\n👨‍👩‍👧b\n";
+        let mut gc = GraphemeChars::new(code);
+
+        assert_eq!(gc.next(), Some(Grapheme::Grapheme("a", Loc::new_at(0, 0, 0))));
+        assert_eq!(gc.next(), Some(Grapheme::Grapheme("e\u{301}", Loc::new_at(1, 0, 1))));
+        assert_eq!(gc.next(), Some(Grapheme::Wrap(Loc::new_at(3, 0, 2))));
+        assert_eq!(gc.next(), Some(Grapheme::Grapheme("👨‍👩‍👧", Loc::new_at(5, 1, 0))));
+        assert_eq!(gc.next(), Some(Grapheme::Grapheme("b", Loc::new_at(10, 1, 1))));
+        assert_eq!(gc.next(), Some(Grapheme::Wrap(Loc::new_at(11, 1, 2))));
+        assert_eq!(gc.next(), None);
+    }
+
+    #[test]
+    fn test_split_crlf() {
+        let code = "a\r\nb";
+        let options = CharCharsOptions::new().split_crlf(true);
+        let gc = GraphemeChars::with_options(code, options);
+        let items: Vec<Grapheme> = gc.collect();
+
+        assert_eq!(items[1], Grapheme::Wrap(Loc::new_at(1, 0, 1)));
+        assert_eq!(items[2], Grapheme::Wrap(Loc::new_at(2, 0, 2)));
+        assert_eq!(items[3], Grapheme::Grapheme("b", Loc::new_at(3, 1, 0)));
+
+        let options = CharCharsOptions::new().cr(false);
+        let items: Vec<Grapheme> = GraphemeChars::with_options(code, options).collect();
+        assert_eq!(items[1], Grapheme::Grapheme("\r", Loc::new_at(1, 0, 1)));
+        assert_eq!(items[2], Grapheme::Wrap(Loc::new_at(2, 0, 2)));
+    }
+
+    #[test]
+    fn test_span() {
+        let code = "👍🏽 ok";
+        let span = GraphemeChars::new(code).span();
+        let x = one("👍🏽").and(one(' ')).and(sub(vec!["o", "k"]));
+
+        let r = x.parse(span.ref_clone());
+        println!("{:?}", r);
+        assert_eq!(r, Some(((0..1, 1..2), 2..4)));
+        assert_eq!(span.com_string(0..4), Some(code.to_string()));
+        let range = span.loc_range(2..4).unwrap();
+        assert_eq!(range, (3, 0, 2, 4, 0, 3).into());
+
+        let index = LineIndex::new(code);
+        assert_eq!(index.to_utf16(range.from), Some(LineCol::new(0, 5)));
+    }
+}
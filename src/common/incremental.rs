@@ -0,0 +1,217 @@
+use crate::*;
+use std::ops::Range;
+
+/// Replace a range of the text  
+/// The range counts chars like `Loc::offset`, use [LineIndex](struct.LineIndex.html) to convert from editor positions
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub new_text: String,
+}
+impl TextEdit {
+    #[inline]
+    pub fn new(range: Range<usize>, new_text: impl Into<String>) -> Self {
+        Self {
+            range,
+            new_text: new_text.into(),
+        }
+    }
+    /// Insert text at a char offset
+    #[inline]
+    pub fn insert(at: usize, text: impl Into<String>) -> Self {
+        Self::new(at..at, text)
+    }
+    /// Delete a range
+    #[inline]
+    pub fn delete(range: Range<usize>) -> Self {
+        Self::new(range, "")
+    }
+}
+
+/// Reparse a document after edits, reusing the results of [memo](trait.Parser.html#method.memo) parsers  
+/// A cached result is reused if everything it read is before the edit,
+/// or it starts on a line after the edit, then its positions are moved by [Relocate](trait.Relocate.html)
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// fn word(input: CharSpan) -> Option<LocRange> {
+///     satisfy(|c: Char| c.is_char())
+///         .many1()
+///         .map(|r| input.loc_range(range_of_many1(r)).unwrap())
+///         .memo("word")
+///         .parse(input.ref_clone())
+/// }
+/// fn doc(input: CharSpan) -> Option<Vec<LocRange>> {
+///     word.and(one('\n').may()).map(|(w, _)| w).many().parse(input)
+/// }
+/// let mut parser = IncrementalParser::new(doc);
+/// parser.parse("a\nbb\nccc");
+///
+/// let r = parser.edit(TextEdit::insert(0, "x")).unwrap();
+/// assert_eq!(parser.text(), "xa\nbb\nccc");
+/// assert_eq!(r[2], (6, 2, 0, 8, 2, 2).into());
+/// assert_eq!(parser.memo().hits(), 2);
+/// ```
+#[derive(Debug)]
+pub struct IncrementalParser<P> {
+    parser: P,
+    text: String,
+    len: usize,
+    /// Loc of every item of the last parse
+    items: Vec<Loc>,
+    memo: MemoTable,
+}
+impl<P> IncrementalParser<P> {
+    #[inline]
+    pub fn new(parser: P) -> Self {
+        Self {
+            parser,
+            text: String::new(),
+            len: 0,
+            items: vec![],
+            memo: MemoTable::new(),
+        }
+    }
+    /// The current text
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// The cached results, `hits` counts the reuse in the last parse
+    #[inline]
+    pub fn memo(&self) -> &MemoTable {
+        &self.memo
+    }
+    /// Parse a new document from scratch
+    pub fn parse<O>(&mut self, text: impl Into<String>) -> Option<O>
+    where
+        P: for<'a> Parser<CharSpan<'a>, Output = O>,
+    {
+        self.text = text.into();
+        self.memo.clear();
+        self.reparse()
+    }
+    /// Apply an edit and parse again
+    /// ## panics
+    /// If the range is out of the text
+    pub fn edit<O>(&mut self, edit: TextEdit) -> Option<O>
+    where
+        P: for<'a> Parser<CharSpan<'a>, Output = O>,
+    {
+        let TextEdit { range, new_text } = edit;
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "edit {:?} is out of the text of {} chars",
+            range,
+            self.len
+        );
+        let index = LineIndex::new(&self.text);
+        let bytes = index.byte_offset(range.start).unwrap()..index.byte_offset(range.end).unwrap();
+        let end_line = index.loc(range.end).unwrap().line;
+
+        let old_items = std::mem::take(&mut self.items);
+        let old_len = self.len;
+        let old_lines = index.line_count();
+        self.text.replace_range(bytes, &new_text);
+        let new_items = CharChars::new(self.text.chars()).count();
+        let new_len = self.text.chars().count();
+        let new_lines = LineIndex::new(&self.text).line_count();
+        let shift = Shift::new(
+            new_items as isize - old_items.len() as isize,
+            new_len as isize - old_len as isize,
+            new_lines as isize - old_lines as isize,
+        );
+
+        let char_end = |i: usize| old_items.get(i + 1).map(|l| l.offset).unwrap_or(old_len);
+        self.memo.rebase(|start, peak| {
+            // reading nothing still depends on the item at start
+            let last = peak.max(start + 1) - 1;
+            if last < old_items.len() && char_end(last) < range.start {
+                Rebase::Keep
+            } else if start < old_items.len() && old_items[start].line > end_line {
+                Rebase::Shift(shift)
+            } else {
+                Rebase::Drop
+            }
+        });
+        self.reparse()
+    }
+    fn reparse<O>(&mut self) -> Option<O>
+    where
+        P: for<'a> Parser<CharSpan<'a>, Output = O>,
+    {
+        let Self {
+            parser, text, memo, ..
+        } = self;
+        memo.reset_hits();
+        let r = memo.run(|| parser.parse(text.span()));
+        self.items = CharChars::new(self.text.chars()).map(|c| c.loc()).collect();
+        self.len = self.text.chars().count();
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn word(input: CharSpan) -> Option<(String, LocRange)> {
+        satisfy(|c: Char| c.char(|c, _| c.is_alphanumeric()).unwrap_or(false))
+            .many1()
+            .map(|r| {
+                let r = range_of_many1(r);
+                (
+                    input.com_string(r.clone()).unwrap(),
+                    input.loc_range(r).unwrap(),
+                )
+            })
+            .memo("word")
+            .parse(input.ref_clone())
+    }
+    fn space(input: CharSpan) -> Option<Vec<std::ops::Range<usize>>> {
+        satisfy(|c: Char| c == ' ' || c.is_wrap())
+            .many()
+            .parse(input)
+    }
+    fn doc(input: CharSpan) -> Option<Vec<(String, LocRange)>> {
+        space
+            .and(word.and(space).map(|(w, _)| w).many())
+            .map(|(_, w)| w)
+            .parse(input)
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut parser = IncrementalParser::new(doc);
+        let r = parser.parse("ab cd\r\nef\ngh ij").unwrap();
+        println!("{:?}", r);
+        assert_eq!(r.len(), 5);
+        assert_eq!(parser.memo().hits(), 0);
+
+        let edits = vec![
+            TextEdit::new(3..5, "xyz"),
+            TextEdit::insert(0, "\n\n"),
+            TextEdit::delete(9..10),
+            TextEdit::insert(17, " kl"),
+            TextEdit::new(2..9, "q\r"),
+        ];
+        for edit in edits {
+            let r = parser.edit(edit);
+            let text = parser.text().to_string();
+            println!("{:?} {:?} {:?}", text, r, parser.memo());
+            assert_eq!(r, IncrementalParser::new(doc).parse(text));
+        }
+        assert_eq!(parser.text(), "\n\nq\ref\ngh ij kl");
+    }
+
+    #[test]
+    fn test_reuse() {
+        let mut parser = IncrementalParser::new(doc);
+        parser.parse("a b\nc d\ne f");
+
+        let r = parser.edit(TextEdit::new(4..5, "xx")).unwrap();
+        assert_eq!(parser.memo().hits(), 3);
+        assert_eq!(r[2], ("xx".to_string(), (4, 1, 0, 5, 1, 1).into()));
+        assert_eq!(r[5], ("f".to_string(), (11, 2, 2).into()));
+    }
+}
@@ -0,0 +1,363 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Display;
+use std::ops::Range;
+
+/// Line and column, both 0-based  
+/// The unit of `col` depends on where it comes from, see [LineIndex](struct.LineIndex.html)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub struct LineCol {
+    /// nth of line
+    pub line: usize,
+    /// column in current line
+    pub col: usize,
+}
+impl LineCol {
+    /// New at
+    #[inline]
+    pub const fn new(line: usize, col: usize) -> Self {
+        Self { line, col }
+    }
+}
+impl Display for LineCol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+impl From<(usize, usize)> for LineCol {
+    #[inline]
+    fn from((line, col): (usize, usize)) -> Self {
+        Self::new(line, col)
+    }
+}
+
+/// A char that is not a single byte in UTF-8
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct WideChar {
+    /// char offset
+    char: usize,
+    /// byte offset
+    byte: usize,
+    /// UTF-16 code unit offset
+    utf16: usize,
+    len_utf8: usize,
+    len_utf16: usize,
+}
+impl WideChar {
+    #[inline]
+    fn extra_bytes(&self) -> usize {
+        self.byte + self.len_utf8 - self.char - 1
+    }
+    #[inline]
+    fn extra_utf16(&self) -> usize {
+        self.utf16 + self.len_utf16 - self.char - 1
+    }
+}
+
+/// Converts between char offsets, byte offsets, `(line, utf8 col)` and `(line, utf16 col)`  
+/// Built once from the source, every conversion is `O(log n)`  
+/// Lines are split the same way as [CharChars](struct.CharChars.html) with default options, so it works with the `Loc`s parsers produce
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct LineIndex {
+    /// char offset of each line start
+    lines: Vec<usize>,
+    /// every non-ASCII char, sorted
+    wide: Vec<WideChar>,
+    /// char offset of the `'\r'` in every `"\r\n"`
+    crlf: Vec<usize>,
+    /// number of chars
+    len: usize,
+}
+impl LineIndex {
+    /// Index the source
+    pub fn new(src: &str) -> Self {
+        let mut lines = vec![0];
+        let mut wide = vec![];
+        let mut crlf = vec![];
+        let mut utf16 = 0;
+        let mut len = 0;
+        let mut iter = src.char_indices().peekable();
+        while let Some((byte, c)) = iter.next() {
+            let char = len;
+            if !c.is_ascii() {
+                wide.push(WideChar {
+                    char,
+                    byte,
+                    utf16,
+                    len_utf8: c.len_utf8(),
+                    len_utf16: c.len_utf16(),
+                });
+            }
+            utf16 += c.len_utf16();
+            len += 1;
+            if c == '\r' {
+                if let Some((_, '\n')) = iter.peek() {
+                    iter.next();
+                    crlf.push(char);
+                    utf16 += 1;
+                    len += 1;
+                }
+                lines.push(len);
+            } else if c == '\n' {
+                lines.push(len);
+            }
+        }
+        Self {
+            lines,
+            wide,
+            crlf,
+            len,
+        }
+    }
+    /// Number of chars in the source
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Is the source empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Number of lines, an empty source has 1 line
+    #[inline]
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+    /// Char offset range of a line, including its line terminator
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let start = *self.lines.get(line)?;
+        let end = self.lines.get(line + 1).copied().unwrap_or(self.len);
+        Some(start..end)
+    }
+
+    /// Char offset to byte offset
+    pub fn byte_offset(&self, char: usize) -> Option<usize> {
+        if char > self.len {
+            return None;
+        }
+        let k = self.wide.partition_point(|w| w.char < char);
+        let extra = if k == 0 {
+            0
+        } else {
+            self.wide[k - 1].extra_bytes()
+        };
+        Some(char + extra)
+    }
+    /// Byte offset to char offset  
+    /// None if it is not on a char boundary
+    pub fn char_offset(&self, byte: usize) -> Option<usize> {
+        let k = self.wide.partition_point(|w| w.byte + w.len_utf8 <= byte);
+        if let Some(w) = self.wide.get(k) {
+            if w.byte < byte {
+                return None;
+            }
+        }
+        let extra = if k == 0 {
+            0
+        } else {
+            self.wide[k - 1].extra_bytes()
+        };
+        let char = byte - extra;
+        if char > self.len {
+            None
+        } else {
+            Some(char)
+        }
+    }
+    /// Char offset to UTF-16 code unit offset
+    pub fn utf16_offset(&self, char: usize) -> Option<usize> {
+        if char > self.len {
+            return None;
+        }
+        let k = self.wide.partition_point(|w| w.char < char);
+        let extra = if k == 0 {
+            0
+        } else {
+            self.wide[k - 1].extra_utf16()
+        };
+        Some(char + extra)
+    }
+    /// UTF-16 code unit offset to char offset  
+    /// None if it splits a surrogate pair
+    pub fn char_offset_utf16(&self, utf16: usize) -> Option<usize> {
+        let k = self.wide.partition_point(|w| w.utf16 + w.len_utf16 <= utf16);
+        if let Some(w) = self.wide.get(k) {
+            if w.utf16 < utf16 {
+                return None;
+            }
+        }
+        let extra = if k == 0 {
+            0
+        } else {
+            self.wide[k - 1].extra_utf16()
+        };
+        let char = utf16 - extra;
+        if char > self.len {
+            None
+        } else {
+            Some(char)
+        }
+    }
+
+    /// Loc of a char offset
+    pub fn loc(&self, char: usize) -> Option<Loc> {
+        if char > self.len {
+            return None;
+        }
+        let line = self.lines.partition_point(|s| *s <= char) - 1;
+        Some(Loc::new_at(char, line, char - self.lines[line]))
+    }
+    /// Loc of a byte offset
+    #[inline]
+    pub fn loc_of_byte(&self, byte: usize) -> Option<Loc> {
+        self.loc(self.char_offset(byte)?)
+    }
+    /// `(line, utf8 col)` of a Loc, the column counts bytes
+    pub fn to_utf8(&self, loc: Loc) -> Option<LineCol> {
+        let start = *self.lines.get(loc.line)?;
+        let col = self.byte_offset(loc.offset)? - self.byte_offset(start)?;
+        Some(LineCol::new(loc.line, col))
+    }
+    /// `(line, utf16 col)` of a Loc, the column counts UTF-16 code units like LSP does
+    pub fn to_utf16(&self, loc: Loc) -> Option<LineCol> {
+        let start = *self.lines.get(loc.line)?;
+        let col = self.utf16_offset(loc.offset)? - self.utf16_offset(start)?;
+        Some(LineCol::new(loc.line, col))
+    }
+    /// Loc of `(line, utf8 col)`  
+    /// None if it is past the end of the line or not on a char boundary
+    pub fn from_utf8(&self, pos: LineCol) -> Option<Loc> {
+        let range = self.line_range(pos.line)?;
+        let byte = self.byte_offset(range.start)? + pos.col;
+        let char = self.char_offset(byte)?;
+        if char > range.end {
+            return None;
+        }
+        Some(Loc::new_at(char, pos.line, char - range.start))
+    }
+    /// Loc of `(line, utf16 col)`  
+    /// None if it is past the end of the line or splits a surrogate pair
+    pub fn from_utf16(&self, pos: LineCol) -> Option<Loc> {
+        let range = self.line_range(pos.line)?;
+        let utf16 = self.utf16_offset(range.start)? + pos.col;
+        let char = self.char_offset_utf16(utf16)?;
+        if char > range.end {
+            return None;
+        }
+        Some(Loc::new_at(char, pos.line, char - range.start))
+    }
+
+    /// Char offset just after the item at `loc`  
+    /// A `"\r\n"` item is 2 chars long
+    pub fn end_of(&self, loc: Loc) -> usize {
+        let len = if self.crlf.binary_search(&loc.offset).is_ok() {
+            2
+        } else {
+            1
+        };
+        (loc.offset + len).min(self.len)
+    }
+    /// Byte range of a LocRange, `to` is inclusive like the ranges parsers produce
+    pub fn byte_range(&self, range: LocRange) -> Option<Range<usize>> {
+        let start = self.byte_offset(range.from.offset)?;
+        let end = self.byte_offset(self.end_of(range.to))?;
+        Some(start..end)
+    }
+    /// `(line, utf8 col)` range of a LocRange, the end is exclusive
+    pub fn utf8_range(&self, range: LocRange) -> Option<Range<LineCol>> {
+        let start = self.to_utf8(range.from)?;
+        let end = self.to_utf8(self.loc(self.end_of(range.to))?)?;
+        Some(start..end)
+    }
+    /// `(line, utf16 col)` range of a LocRange, the end is exclusive like LSP ranges
+    pub fn utf16_range(&self, range: LocRange) -> Option<Range<LineCol>> {
+        let start = self.to_utf16(range.from)?;
+        let end = self.to_utf16(self.loc(self.end_of(range.to))?)?;
+        Some(start..end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_ascii() {
+        let code = "asd\n123";
+        let index = LineIndex::new(code);
+
+        assert_eq!(index.len(), 7);
+        assert_eq!(index.line_count(), 2);
+        assert_eq!(index.byte_offset(5), Some(5));
+        assert_eq!(index.char_offset(5), Some(5));
+        assert_eq!(index.loc(5), Some(Loc::new_at(5, 1, 1)));
+        assert_eq!(index.loc(8), None);
+    }
+
+    #[test]
+    fn test_wide() {
+        let code = "a你😀b\nc";
+        let index = LineIndex::new(code);
+
+        assert_eq!(index.byte_offset(1), Some(1));
+        assert_eq!(index.byte_offset(2), Some(4));
+        assert_eq!(index.byte_offset(3), Some(8));
+        assert_eq!(index.byte_offset(6), Some(code.len()));
+        assert_eq!(index.char_offset(8), Some(3));
+        assert_eq!(index.char_offset(2), None);
+
+        assert_eq!(index.utf16_offset(3), Some(4));
+        assert_eq!(index.utf16_offset(4), Some(5));
+        assert_eq!(index.char_offset_utf16(4), Some(3));
+        assert_eq!(index.char_offset_utf16(3), None);
+
+        let b = Loc::new_at(3, 0, 3);
+        assert_eq!(index.to_utf8(b), Some(LineCol::new(0, 8)));
+        assert_eq!(index.to_utf16(b), Some(LineCol::new(0, 4)));
+        assert_eq!(index.from_utf8(LineCol::new(0, 8)), Some(b));
+        assert_eq!(index.from_utf16(LineCol::new(0, 4)), Some(b));
+        assert_eq!(index.from_utf16(LineCol::new(0, 9)), None);
+    }
+
+    #[test]
+    fn test_wraps() {
+        let code = "a\nb\rc\r\nd";
+        let index = LineIndex::new(code);
+        let chars: Vec<Char> = CharChars::new(code.chars()).collect();
+
+        for c in chars {
+            assert_eq!(index.loc(c.loc().offset), Some(c.loc()));
+        }
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_range(2), Some(4..7));
+    }
+
+    #[test]
+    fn test_loc_range() {
+        let code = "x = \"你好\"\r\ny";
+        let span = code.span();
+        let index = LineIndex::new(code);
+
+        let r = one('x')
+            .and(substr(" = "))
+            .and(one('"'))
+            .and(satisfy(|c: Char| c != '"').many())
+            .and(one('"'))
+            .and(satisfy(|c: Char| c.is_wrap()))
+            .map(|(_, r)| r)
+            .parse(span.ref_clone())
+            .unwrap();
+        let loc = span.loc_range(0..r.end).unwrap();
+
+        assert_eq!(index.byte_range(loc), Some(0..code.len() - 1));
+        let r = index.utf16_range(loc).unwrap();
+        assert_eq!(r, LineCol::new(0, 0)..LineCol::new(1, 0));
+
+        let loc = span.loc_range(5..7).unwrap();
+        assert_eq!(index.byte_range(loc), Some(5..11));
+        let r = index.utf16_range(loc).unwrap();
+        assert_eq!(r, LineCol::new(0, 5)..LineCol::new(0, 7));
+    }
+}
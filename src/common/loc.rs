@@ -12,6 +12,10 @@ pub struct Loc {
     pub line: usize,
     /// nth of characters in current line
     pub char: usize,
+    /// visual column in current line, tabs expanded  
+    /// same as `char` when there are no tabs or the tab width is 1
+    #[serde(default)]
+    pub col: usize,
 }
 impl Loc {
     /// New empty
@@ -21,17 +25,33 @@ impl Loc {
             offset: 0,
             line: 0,
             char: 0,
+            col: 0,
         }
     }
-    /// New at
+    /// New at, the visual column is the same as `char`
     #[inline]
     pub const fn new_at(offset: usize, line: usize, char: usize) -> Self {
-        Self { offset, line, char }
+        Self {
+            offset,
+            line,
+            char,
+            col: char,
+        }
+    }
+    /// New at with a visual column
+    #[inline]
+    pub const fn new_at_col(offset: usize, line: usize, char: usize, col: usize) -> Self {
+        Self {
+            offset,
+            line,
+            char,
+            col,
+        }
     }
 }
 impl Display for Loc {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Loc {{ {}:{}({}) }}", self.line, self.char, self.offset)
+        write!(f, "Loc {{ {}:{}({}) }}", self.line, self.col, self.offset)
     }
 }
 impl Default for Loc {
@@ -0,0 +1,284 @@
+use crate::common::cursor;
+use crate::*;
+use std::ops::Range;
+
+/// A type that can be parsed from text, only with the `derive` feature, usually through `#[derive(Parse)]`  
+/// Whitespace before every token and item is skipped  
+/// `T::parse` is a parser itself, so derived types can be used with all the combinators
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let r = <Vec<Ident>>::parse("a b  c".span());
+/// assert_eq!(r.map(|v| v.len()), Some(3));
+/// ```
+pub trait Parse: Sized {
+    fn parse(input: CharSpan) -> Option<Self>;
+}
+
+/// An identifier, a letter or `_` followed by letters, digits and `_`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Ident(pub String);
+impl Parse for Ident {
+    fn parse(input: CharSpan) -> Option<Self> {
+        __derive::skip_ws(&input);
+        satisfy(|c: Char| {
+            c.char(|c, _| c.is_alphabetic() || c == '_')
+                .unwrap_or(false)
+        })
+        .and(satisfy(is_word).many())
+        .map(|(r, w)| {
+            let end = w.last().map(|w| w.end).unwrap_or(r.end);
+            Ident(input.com_string(r.start..end).unwrap())
+        })
+        .parse(input.ref_clone())
+    }
+}
+
+fn is_word(c: Char) -> bool {
+    c.char(|c, _| c.is_alphanumeric() || c == '_')
+        .unwrap_or(false)
+}
+
+/// Range of `-? digits (. digits)?`, no fraction if not float
+fn number(input: &CharSpan, float: bool) -> Option<Range<usize>> {
+    __derive::skip_ws(input);
+    let digit = || satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false));
+    let int = one('-').may().and(digit().many1()).map(|(s, d)| {
+        let d = range_of_many1(d);
+        s.map(|s| s.start).unwrap_or(d.start)..d.end
+    });
+    if float {
+        int.and(one('.').and(digit().many1()).may())
+            .map(|(r, f)| r.start..f.map(|(_, f)| range_of_many1(f).end).unwrap_or(r.end))
+            .parse(input.ref_clone())
+    } else {
+        int.parse(input.ref_clone())
+    }
+}
+
+macro_rules! impl_parse_number {
+    { $float:expr; $($t:ty),* } => { $(
+        impl Parse for $t {
+            fn parse(input: CharSpan) -> Option<Self> {
+                let r = number(&input, $float)?;
+                input.com_string(r)?.parse().ok()
+            }
+        }
+    )* };
+}
+impl_parse_number! { false; u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize }
+impl_parse_number! { true; f32, f64 }
+
+impl Parse for bool {
+    fn parse(input: CharSpan) -> Option<Self> {
+        let from = __derive::start(&input);
+        if __derive::keyword(&input, "true").is_some() {
+            return Some(true);
+        }
+        __derive::back(&input, from);
+        __derive::keyword(&input, "false").map(|_| false)
+    }
+}
+impl<T: Parse> Parse for Box<T> {
+    #[inline]
+    fn parse(input: CharSpan) -> Option<Self> {
+        T::parse(input).map(Box::new)
+    }
+}
+impl<T: Parse> Parse for Option<T> {
+    /// Never fails, None without consuming if T fails
+    fn parse(input: CharSpan) -> Option<Self> {
+        let from = __derive::start(&input);
+        let r = __derive::item(&input);
+        if r.is_none() {
+            __derive::back(&input, from);
+        }
+        Some(r)
+    }
+}
+impl<T: Parse> Parse for Vec<T> {
+    /// Zero or more, use `#[parse(sep_by = ",")]` for separated lists  
+    /// Stops at an item that consumes nothing, it is not kept
+    fn parse(input: CharSpan) -> Option<Self> {
+        let mut items = vec![];
+        loop {
+            let from = __derive::start(&input);
+            match __derive::item(&input) {
+                Some(v) if cursor::now(&input) > from => items.push(v),
+                _ => {
+                    __derive::back(&input, from);
+                    return Some(items);
+                }
+            }
+        }
+    }
+}
+
+/// Any parsed node with its location, from the first item after whitespace to the last consumed item
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let r = <Located<Ident>>::parse("  ab c".span()).unwrap();
+/// assert_eq!(r.value, Ident("ab".to_string()));
+/// assert_eq!((r.loc.from.offset, r.loc.to.offset), (2, 3));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Located<T> {
+    pub value: T,
+    pub loc: LocRange,
+}
+impl<T: Parse> Parse for Located<T> {
+    fn parse(input: CharSpan) -> Option<Self> {
+        let from = __derive::start(&input);
+        let value = T::parse(input.ref_clone())?;
+        let loc = __derive::loc(&input, from);
+        Some(Self { value, loc })
+    }
+}
+impl<T> std::ops::Deref for Located<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Used by the code `#[derive(Parse)]` generates
+#[doc(hidden)]
+pub mod __derive {
+    use crate::common::cursor;
+    use crate::*;
+
+    pub fn skip_ws(input: &CharSpan) {
+        let mut input = input.ref_clone();
+        input.re_ready();
+        satisfy(|c: Char| c.is_wrap() || c.char(|c, _| c.is_whitespace()).unwrap_or(false))
+            .skip_many()
+            .parse(input);
+    }
+    /// Skip whitespace and return the position
+    pub fn start(input: &CharSpan) -> usize {
+        skip_ws(input);
+        cursor::now(input)
+    }
+    #[inline]
+    pub fn back(input: &CharSpan, index: usize) {
+        cursor::back(input, index)
+    }
+    pub fn token(input: &CharSpan, s: &str) -> Option<()> {
+        start(input);
+        substr(s).parse(input.ref_clone()).map(|_| ())
+    }
+    /// A token not followed by a letter, digit or `_`
+    pub fn keyword(input: &CharSpan, s: &str) -> Option<()> {
+        token(input, s)?;
+        let end = cursor::now(input);
+        let word = satisfy(super::is_word).parse(input.ref_clone());
+        back(input, end);
+        match word {
+            Some(_) => None,
+            None => Some(()),
+        }
+    }
+    pub fn item<T: Parse>(input: &CharSpan) -> Option<T> {
+        start(input);
+        T::parse(input.ref_clone())
+    }
+    pub fn with<T>(
+        input: &CharSpan,
+        parser: impl for<'a> Parser<CharSpan<'a>, Output = T>,
+    ) -> Option<T> {
+        start(input);
+        parser.parse(input.ref_clone())
+    }
+    /// Zero or more items separated by sep, a trailing sep is not consumed  
+    /// Stops if a sep and item consume nothing
+    pub fn sep_by<T>(
+        input: &CharSpan,
+        sep: &str,
+        f: impl Fn(&CharSpan) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        let mut items = vec![];
+        let mut from = start(input);
+        loop {
+            if !items.is_empty() && token(input, sep).is_none() {
+                back(input, from);
+                return Some(items);
+            }
+            match f(input) {
+                Some(v) if cursor::now(input) > from => items.push(v),
+                _ => {
+                    back(input, from);
+                    return Some(items);
+                }
+            }
+            from = cursor::now(input);
+        }
+    }
+    /// Location of start to the last consumed item
+    #[inline]
+    pub fn loc(input: &CharSpan, start: usize) -> LocRange {
+        cursor::loc(input, start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_builtin() {
+        let r = <Vec<i32>>::parse(" 1 -2 3x".span());
+        println!("{:?}", r);
+        assert_eq!(r, Some(vec![1, -2, 3]));
+
+        assert_eq!(f64::parse("-1.5".span()), Some(-1.5));
+        assert_eq!(u8::parse("300".span()), None);
+        assert_eq!(bool::parse(" false".span()), Some(false));
+        assert_eq!(bool::parse("truest".span()), None);
+        assert_eq!(Ident::parse("_a1 b".span()), Some(Ident("_a1".to_string())));
+        assert_eq!(<Option<Ident>>::parse("1".span()), Some(None));
+    }
+
+    #[test]
+    fn test_no_progress() {
+        let span = "1".span();
+        assert_eq!(<Vec<Option<Ident>>>::parse(span.ref_clone()), Some(vec![]));
+        assert_eq!(__derive::start(&span), 0);
+
+        let span = "a b".span();
+        let r = __derive::sep_by(&span, "", __derive::item::<Option<Ident>>);
+        assert_eq!(r.map(|v| v.len()), Some(2));
+        assert_eq!(__derive::start(&span), 3);
+        let r = __derive::sep_by(&span, ",", __derive::item::<Option<Ident>>);
+        assert_eq!(r, Some(vec![]));
+    }
+
+    #[test]
+    fn test_located() {
+        let r = <Vec<Located<i32>>>::parse("1\n -23".span()).unwrap();
+        println!("{:?}", r);
+        assert_eq!(*r[1], -23);
+        assert_eq!(r[1].loc, (3, 1, 1, 5, 1, 3).into());
+
+        let r = <Located<Option<Ident>>>::parse(" 1".span()).unwrap();
+        assert_eq!(r.value, None);
+        assert_eq!(r.loc, (1, 0, 1, 1, 0, 1).into());
+    }
+
+    #[test]
+    fn test_sep_by() {
+        let span = "a, b ,c,".span();
+        let r = __derive::sep_by(&span, ",", __derive::item::<Ident>);
+        println!("{:?}", r);
+        assert_eq!(r.map(|v| v.len()), Some(3));
+        assert_eq!(__derive::start(&span), 7);
+
+        let span = "".span();
+        assert_eq!(
+            __derive::sep_by(&span, ",", __derive::item::<Ident>),
+            Some(vec![])
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Display;
+
+/// Id of a file registered in a [SourceMap](struct.SourceMap.html)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub struct FileId(pub u32);
+impl Display for FileId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileId({})", self.0)
+    }
+}
+
+/// Location in a file  
+/// [Loc](struct.Loc.html) has no file, it is kept per item and compares the same in every file  
+/// Only the span knows its file, so this pairs them, get it from [file_loc](trait.ComFileLoc.html#tymethod.file_loc) on a span from [SourceMap::span](struct.SourceMap.html#method.span)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub struct FileLoc {
+    pub file: FileId,
+    pub loc: Loc,
+}
+impl FileLoc {
+    /// New at
+    #[inline]
+    pub const fn new(file: FileId, loc: Loc) -> Self {
+        Self { file, loc }
+    }
+}
+impl Display for FileLoc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileLoc {{ {} {} }}", self.file, self.loc)
+    }
+}
+
+/// Range of location in a file, see [FileLoc](struct.FileLoc.html)  
+/// Get it from [file_loc_range](trait.ComFileLocRange.html#tymethod.file_loc_range) on the span a parser ran on
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub struct FileLocRange {
+    pub file: FileId,
+    pub range: LocRange,
+}
+impl FileLocRange {
+    /// New at
+    #[inline]
+    pub const fn new(file: FileId, range: LocRange) -> Self {
+        Self { file, range }
+    }
+    /// Start of the range
+    #[inline]
+    pub const fn from(&self) -> FileLoc {
+        FileLoc::new(self.file, self.range.from)
+    }
+    /// End of the range, inclusive
+    #[inline]
+    pub const fn to(&self) -> FileLoc {
+        FileLoc::new(self.file, self.range.to)
+    }
+}
+impl Display for FileLocRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileLocRange {{ {} {} }}", self.file, self.range)
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Get the file an iterator or span reads from  
+/// None if it is not from a [SourceMap](struct.SourceMap.html)
+pub trait GetFileId {
+    /// Get the FileId
+    fn file_id(&self) -> Option<FileId>;
+}
+
+/// Calculate FileLoc
+pub trait ComFileLoc {
+    type ComFileLocData;
+
+    /// Calculate FileLoc
+    fn file_loc(&self, data: Self::ComFileLocData) -> Option<FileLoc>;
+}
+impl<T: ComLoc + GetFileId> ComFileLoc for T {
+    type ComFileLocData = T::ComLocData;
+
+    #[inline]
+    fn file_loc(&self, data: Self::ComFileLocData) -> Option<FileLoc> {
+        Some(FileLoc::new(self.file_id()?, self.loc(data)?))
+    }
+}
+/// Calculate FileLocRange
+pub trait ComFileLocRange {
+    type ComFileLocRangeData;
+
+    /// Calculate FileLocRange
+    fn file_loc_range(&self, data: Self::ComFileLocRangeData) -> Option<FileLocRange>;
+}
+impl<T: ComLocRange + GetFileId> ComFileLocRange for T {
+    type ComFileLocRangeData = T::ComLocRangeData;
+
+    #[inline]
+    fn file_loc_range(&self, data: Self::ComFileLocRangeData) -> Option<FileLocRange> {
+        Some(FileLocRange::new(self.file_id()?, self.loc_range(data)?))
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A source registered in a [SourceMap](struct.SourceMap.html)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourceFile {
+    id: FileId,
+    name: String,
+    src: String,
+    index: LineIndex,
+}
+impl SourceFile {
+    #[inline]
+    pub fn id(&self) -> FileId {
+        self.id
+    }
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    #[inline]
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+    #[inline]
+    pub fn line_index(&self) -> &LineIndex {
+        &self.index
+    }
+    /// Text of a line without its line terminator
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let range = self.index.line_range(line)?;
+        let start = self.index.byte_offset(range.start)?;
+        let end = self.index.byte_offset(range.end)?;
+        Some(self.src[start..end].trim_end_matches(&['\r', '\n'][..]))
+    }
+    /// Parse this file
+    #[inline]
+    pub fn span(&self) -> CharSpan<'_> {
+        CharChars::new(self.src.chars()).with_file(self.id).span()
+    }
+}
+
+/// Registers sources and hands out [FileId](struct.FileId.html)s  
+/// Spans from [span](#method.span) know their file, so ranges can be resolved back to names and snippets
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// let mut map = SourceMap::new();
+/// let id = map.add("a.txt", "let x = ;");
+/// let span = map.span(id).unwrap();
+///
+/// let r = substr("let x = ").parse(span.ref_clone()).unwrap();
+/// let range = span.file_loc_range(r.end..r.end + 1).unwrap();
+/// let snippet = map.snippet(range).unwrap();
+/// assert_eq!(snippet.to_string(), "a.txt:1:9\n1 | let x = ;\n  |         ^\n");
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+impl SourceMap {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Register a source
+    pub fn add(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+        let src = src.into();
+        self.files.push(SourceFile {
+            id,
+            name: name.into(),
+            index: LineIndex::new(&src),
+            src,
+        });
+        id
+    }
+    /// Number of files
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+    #[inline]
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0 as usize)
+    }
+    /// Find a file by name
+    pub fn find(&self, name: &str) -> Option<FileId> {
+        self.files.iter().find(|f| f.name == name).map(|f| f.id)
+    }
+    #[inline]
+    pub fn name(&self, id: FileId) -> Option<&str> {
+        self.get(id).map(|f| f.name())
+    }
+    #[inline]
+    pub fn source(&self, id: FileId) -> Option<&str> {
+        self.get(id).map(|f| f.src())
+    }
+    #[inline]
+    pub fn files(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter()
+    }
+    /// Parse a file, the span knows its FileId  
+    /// Its `Loc`s do not, ask the span for [file_loc](trait.ComFileLoc.html#tymethod.file_loc) to get them with the file
+    #[inline]
+    pub fn span(&self, id: FileId) -> Option<CharSpan<'_>> {
+        self.get(id).map(|f| f.span())
+    }
+    /// Resolve the file name and source line of a range  
+    /// Only the first line is kept when the range spans several lines
+    pub fn snippet(&self, range: FileLocRange) -> Option<Snippet<'_>> {
+        let file = self.get(range.file)?;
+        let LocRange { from, to } = range.range;
+        let text = file.line(from.line)?;
+        let line_len = text.chars().count();
+        let end = if to.line == from.line {
+            file.index.end_of(to) - from.offset + from.char
+        } else {
+            line_len
+        };
+        Some(Snippet {
+            name: file.name(),
+            line: from.line,
+            col: from.col,
+            text,
+            cols: from.char..end.min(line_len).max(from.char + 1),
+        })
+    }
+}
+
+/// A source line with a highlighted range  
+/// Displayed with 1-based line and column like editors do
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Snippet<'a> {
+    /// file name
+    pub name: &'a str,
+    /// 0-based line
+    pub line: usize,
+    /// 0-based visual column of the start, tabs expanded
+    pub col: usize,
+    /// line text without terminator
+    pub text: &'a str,
+    /// highlighted char columns in the line
+    pub cols: std::ops::Range<usize>,
+}
+impl Display for Snippet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = (self.line + 1).to_string();
+        let pad = " ".repeat(line.len());
+        writeln!(f, "{}:{}:{}", self.name, line, self.col + 1)?;
+        writeln!(f, "{} | {}", line, self.text)?;
+        let lead: String = self
+            .text
+            .chars()
+            .take(self.cols.start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "{} | {}{}", pad, lead, "^".repeat(self.cols.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_source_map() {
+        let mut map = SourceMap::new();
+        let a = map.add("a.txt", "asd\n123");
+        let b = map.add("b.txt", "qwe");
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.find("b.txt"), Some(b));
+        assert_eq!(map.name(a), Some("a.txt"));
+
+        let mut span = map.span(a).unwrap();
+        substr("asd").parse(span.ref_clone());
+        span.re_ready();
+        let r = one('\n').and(substr("12")).parse(span.ref_clone()).unwrap();
+        let range = span.file_loc_range(r.1).unwrap();
+        println!("{}", range);
+        assert_eq!(range, FileLocRange::new(a, (4, 1, 0, 5, 1, 1).into()));
+        assert_eq!(span.file_loc(0), Some(FileLoc::new(a, Loc::new())));
+
+        let span = map.span(b).unwrap();
+        assert_eq!(span.file_id(), Some(b));
+        assert_eq!("qwe".span().file_id(), None);
+        assert_eq!("qwe".span().file_loc(0), None);
+    }
+
+    #[test]
+    fn test_snippet() {
+        let mut map = SourceMap::new();
+        let a = map.add("a.txt", "x\r\n\tfoo bar\r\n");
+        let span = map.span(a).unwrap();
+        span.ref_clone().for_each(drop);
+
+        let range = span.file_loc_range(7..10).unwrap();
+        let snippet = map.snippet(range).unwrap();
+        assert_eq!(snippet.text, "\tfoo bar");
+        assert_eq!(snippet.cols, 5..8);
+        assert_eq!(snippet.to_string(), "a.txt:2:6\n2 | \tfoo bar\n  | \t    ^^^\n");
+
+        let range = span.file_loc_range(3..11).unwrap();
+        assert_eq!(map.snippet(range).unwrap().cols, 1..8);
+    }
+}
@@ -1,5 +1,5 @@
-use super::cell::*;
 use super::*;
+use std::cell::RefCell;
 use std::ops::Range;
 use std::rc::Rc;
 
@@ -21,7 +21,7 @@ impl<I: Iterator> SpanData<I> {
     #[inline]
     pub fn new_restart(&self) -> Self {
         Self {
-            index: 0,
+            index: self.timeline.committed(),
             ready: true,
             timeline: self.timeline.clone(),
         }
@@ -46,15 +46,19 @@ impl<I: Iterator> Clone for SpanData<I> {
 /// See [TimeTravel](trait.TimeTravel.html)
 #[derive(Debug, PartialEq, Eq)]
 pub struct Span<I: Iterator> {
-    inner: Rc<ExtRefCell<SpanData<I>>>,
+    inner: Rc<RefCell<SpanData<I>>>,
 }
 impl<I: Iterator> Span<I> {
     #[inline]
     pub fn new(iter: I) -> Self {
         Self {
-            inner: Rc::new(ExtRefCell::new(SpanData::new(iter))),
+            inner: Rc::new(RefCell::new(SpanData::new(iter))),
         }
     }
+    #[inline]
+    pub(crate) fn timeline(&self) -> Timeline<I> {
+        self.inner.borrow().timeline.clone()
+    }
 }
 impl<I: Iterator> From<I> for Span<I> {
     #[inline]
@@ -65,7 +69,7 @@ impl<I: Iterator> From<I> for Span<I> {
 impl<I: Iterator> Clone for Span<I> {
     fn clone(&self) -> Self {
         Self {
-            inner: Rc::new(ExtRefCell::new(self.inner.new_restart())),
+            inner: Rc::new(RefCell::new(self.inner.borrow().new_restart())),
         }
     }
 }
@@ -83,11 +87,11 @@ where
     type Item = I::Item;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let this: &mut SpanData<I> = self.inner.get_mut();
-        let r = this.timeline.get(this.index)?;
+        let mut this = self.inner.borrow_mut();
+        let r = this.timeline.get(this.index)?.clone();
         this.index += 1;
         this.ready = false;
-        Some(r.clone())
+        Some(r)
     }
 }
 impl<I: Iterator> TimeTravel for Span<I>
@@ -95,37 +99,73 @@ where
     I::Item: Clone,
 {
     fn get(&mut self, index: usize) -> Option<Self::Item> {
-        let this: &mut SpanData<I> = self.inner.get_mut();
-        this.timeline.get(index).cloned()
+        let this = self.inner.borrow();
+        let r = this.timeline.get(index)?.clone();
+        Some(r)
     }
     fn re_ready(&mut self) {
-        let this: &mut SpanData<I> = self.inner.get_mut();
-        if !this.ready && !(this.timeline.is_end() && this.index >= this.timeline.now_len()) {
+        let mut this = self.inner.borrow_mut();
+        if !this.ready && (!this.timeline.is_end() || this.index < this.timeline.now_len()) {
             this.index -= 1;
             this.ready = true;
         }
     }
     fn do_ready(&mut self) {
-        let this: &mut SpanData<I> = self.inner.get_mut();
-        this.ready = true;
+        self.inner.borrow_mut().ready = true;
     }
     fn is_complete(&self) -> bool {
-        self.inner.timeline.is_end()
+        self.inner.borrow().timeline.is_end()
     }
     fn save(&self) -> usize {
-        self.inner.index
+        self.inner.borrow().index
     }
     fn back(&mut self, index: usize) {
-        let this = self.inner.get_mut();
+        let mut this = self.inner.borrow_mut();
         this.index = index;
         this.ready = true;
     }
+    fn commit(&mut self) {
+        let mut this = self.inner.borrow_mut();
+        let index = if this.ready {
+            this.index
+        } else {
+            this.index - 1
+        };
+        this.timeline.commit(index);
+    }
+}
+impl<I: Iterator> Memoizable for Span<I>
+where
+    I::Item: Clone,
+{
+    #[inline]
+    fn peak(&self) -> usize {
+        self.inner.borrow().timeline.peak()
+    }
+    #[inline]
+    fn swap_peak(&mut self, peak: usize) -> usize {
+        self.inner.borrow().timeline.swap_peak(peak)
+    }
+    #[inline]
+    fn is_ready(&self) -> bool {
+        self.inner.borrow().ready
+    }
+    #[inline]
+    fn input_id(&self) -> u128 {
+        self.inner.borrow().timeline.id()
+    }
 }
 impl<I: Iterator> SyncTo for Span<I> {
     fn sync_to(&self, other: &mut Self) {
         other.inner = self.inner.clone();
     }
 }
+impl<I: Iterator + GetFileId> GetFileId for Span<I> {
+    #[inline]
+    fn file_id(&self) -> Option<FileId> {
+        self.inner.borrow().timeline.with_iter(|i| i.file_id())
+    }
+}
 impl<I: Iterator> ComString for Span<I>
 where
     I::Item: GetString,
@@ -133,16 +173,10 @@ where
     type ComStringData = Range<usize>;
 
     fn com_string(&self, range: Range<usize>) -> Option<String> {
-        let this = self.inner.get();
-        let Range { start: _, end } = range;
-        if end > this.timeline.now_len() {
-            None
-        } else {
-            let s = this.timeline[range].iter();
-            let s = s.map(|c| c.get_string());
-            let s: String = s.collect();
-            Some(s)
-        }
+        let this = self.inner.borrow();
+        let s = this.timeline.slice(range)?;
+        let s: String = s.iter().map(|c| c.get_string()).collect();
+        Some(s)
     }
 }
 impl<I: Iterator> ComLoc for Span<I>
@@ -152,7 +186,7 @@ where
     type ComLocData = usize;
 
     fn loc(&self, index: usize) -> Option<Loc> {
-        let this = unsafe { (*self.inner).get_mut() };
+        let this = self.inner.borrow();
         let c = this.timeline.get(index)?;
         let loc = c.loc();
         Some(loc)
@@ -160,12 +194,12 @@ where
 }
 impl<I: Iterator> ComLocRange for Span<I>
 where
-    I::Item: GetLoc + Clone,
+    I::Item: GetLoc,
 {
     type ComLocRangeData = Range<usize>;
 
     fn loc_range(&self, range: Range<usize>) -> Option<LocRange> {
-        let this = unsafe { (*self.inner).get_mut() };
+        let this = self.inner.borrow();
 
         let Range { start, end } = range;
         debug_assert!(start <= end);
@@ -176,9 +210,9 @@ where
         if end > now_len {
             None
         } else {
-            let s = this.timeline.get(start)?.clone();
-            let e = this.timeline.get(end - 1)?.clone();
-            let loc_range = LocRange::new(s.loc(), e.loc());
+            let s = this.timeline.get(start)?.loc();
+            let e = this.timeline.get(end - 1)?.loc();
+            let loc_range = LocRange::new(s, e);
             Some(loc_range)
         }
     }
@@ -189,3 +223,49 @@ pub trait SpanOf {
     type SpanOfTarget;
     fn span(self) -> Self::SpanOfTarget;
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_commit() {
+        let code = "asd123";
+        let mut span = code.span();
+
+        let r = substr("asd").parse(span.ref_clone());
+        assert_eq!(r, Some(0..3));
+        span.re_ready();
+        span.commit();
+
+        assert_eq!(span.com_string(0..3), None);
+        assert_eq!(span.loc(2), None);
+        let r = substr("123").parse(span.ref_clone());
+        assert_eq!(r, Some(3..6));
+        assert_eq!(span.com_string(3..6), Some("123".to_string()));
+        assert_eq!(span.loc_range(3..6), Some((3, 0, 3, 5, 0, 5).into()));
+    }
+
+    #[test]
+    fn test_commit_restart() {
+        let code = "asd123";
+        let mut span = code.span();
+
+        one('a').parse(span.ref_clone());
+        span.commit();
+
+        let r = one('s').parse(span.clone());
+        assert_eq!(r, Some(1..2));
+    }
+
+    #[test]
+    fn test_commit_endless() {
+        let span = Span::new(CharChars::new("ab".chars().cycle()));
+        let ab = one('a').and(one('b'));
+        let iter = ab.iter().parse(span.ref_clone()).unwrap().auto_commit();
+
+        let n = iter.take(10000).count();
+        assert_eq!(n, 10000);
+        assert!(span.inner.borrow().timeline.buffered_len() <= 4);
+    }
+}
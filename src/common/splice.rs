@@ -0,0 +1,339 @@
+use super::*;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+/// Where a spliced source came from  
+/// Forms a chain up to the root source through the include sites
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Origin {
+    /// file or macro name
+    pub name: String,
+    /// file in a [SourceMap](struct.SourceMap.html)
+    pub file: Option<FileId>,
+    /// the source it was spliced into and the Loc of the item before the splice point
+    pub parent: Option<(Arc<Origin>, Loc)>,
+}
+impl Origin {
+    #[inline]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            file: None,
+            parent: None,
+        }
+    }
+    /// Origin of a file in a SourceMap
+    #[inline]
+    pub fn of_file(map: &SourceMap, file: FileId) -> Option<Self> {
+        Some(Self {
+            name: map.name(file)?.to_string(),
+            file: Some(file),
+            parent: None,
+        })
+    }
+    /// Number of splices between it and the root
+    pub fn depth(&self) -> usize {
+        self.includes().count()
+    }
+    /// Iterate the include sites, innermost first
+    pub fn includes(&self) -> impl Iterator<Item = (&Origin, Loc)> {
+        let mut now = self.parent.as_ref();
+        std::iter::from_fn(move || {
+            let (origin, loc) = now?;
+            now = origin.parent.as_ref();
+            Some((&**origin, *loc))
+        })
+    }
+    /// Lines like `included from a.conf:3:1`, innermost first, 1-based like editors
+    pub fn include_stack(&self) -> Vec<String> {
+        self.includes()
+            .map(|(o, l)| format!("included from {}:{}:{}", o.name, l.line + 1, l.col + 1))
+            .collect()
+    }
+}
+impl Display for Origin {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A [Char](enum.Char.html) with the source it came from  
+/// The Loc is the original one in that source
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct SpliceChar {
+    pub c: Char,
+    pub origin: Arc<Origin>,
+}
+impl SpliceChar {
+    #[inline]
+    pub fn new(c: Char, origin: Arc<Origin>) -> Self {
+        Self { c, origin }
+    }
+    /// The loc with its file  
+    /// None if the origin is not a file
+    #[inline]
+    pub fn file_loc(&self) -> Option<FileLoc> {
+        Some(FileLoc::new(self.origin.file?, self.c.loc()))
+    }
+}
+impl GetLoc for SpliceChar {
+    #[inline]
+    fn loc(&self) -> Loc {
+        self.c.loc()
+    }
+}
+impl GetString for SpliceChar {
+    #[inline]
+    fn get_string(&self) -> String {
+        self.c.get_string()
+    }
+}
+impl GetChar for SpliceChar {
+    #[inline]
+    fn get_char(&self) -> char {
+        self.c.c()
+    }
+}
+impl PartialEq<char> for SpliceChar {
+    #[inline]
+    fn eq(&self, other: &char) -> bool {
+        self.c == *other
+    }
+}
+impl PartialEq<Char> for SpliceChar {
+    /// Checks if the Char is equal to it, ignore the origin
+    #[inline]
+    fn eq(&self, other: &Char) -> bool {
+        self.c == *other
+    }
+}
+impl From<SpliceChar> for char {
+    #[inline]
+    fn from(c: SpliceChar) -> char {
+        c.c.c()
+    }
+}
+impl Display for SpliceChar {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} in {}", self.c, self.origin)
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+enum Frame<'a> {
+    Source(Box<dyn Iterator<Item = Char> + 'a>, Arc<Origin>),
+    Queue(VecDeque<SpliceChar>),
+}
+
+/// An iterator that reads a stack of sources as one stream of [SpliceChar](struct.SpliceChar.html)  
+/// Use [Span::splice](struct.Span.html#method.splice) to insert a source while parsing
+pub struct SpliceChars<'a> {
+    stack: Vec<Frame<'a>>,
+}
+impl<'a> SpliceChars<'a> {
+    #[inline]
+    pub fn new(chars: impl Iterator<Item = Char> + 'a, origin: Origin) -> Self {
+        Self {
+            stack: vec![Frame::Source(Box::new(chars), Arc::new(origin))],
+        }
+    }
+    /// Read a file in a SourceMap
+    #[inline]
+    pub fn of_file(map: &'a SourceMap, file: FileId) -> Option<Self> {
+        let src = map.source(file)?;
+        Some(Self::new(
+            CharChars::new(src.chars()),
+            Origin::of_file(map, file)?,
+        ))
+    }
+    /// Number of sources not yet finished, including re-queued items
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+    /// Read `chars` before everything else
+    fn push(
+        &mut self,
+        chars: impl Iterator<Item = Char> + 'a,
+        origin: Arc<Origin>,
+        tail: Vec<SpliceChar>,
+    ) {
+        if !tail.is_empty() {
+            self.stack.push(Frame::Queue(tail.into()));
+        }
+        self.stack.push(Frame::Source(Box::new(chars), origin));
+    }
+}
+impl Debug for SpliceChars<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpliceChars")
+            .field("depth", &self.stack.len())
+            .finish()
+    }
+}
+impl<'a> Iterator for SpliceChars<'a> {
+    type Item = SpliceChar;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let r = match self.stack.last_mut()? {
+                Frame::Source(iter, origin) => {
+                    iter.next().map(|c| SpliceChar::new(c, origin.clone()))
+                }
+                Frame::Queue(queue) => queue.pop_front(),
+            };
+            if r.is_some() {
+                return r;
+            }
+            self.stack.pop();
+        }
+    }
+}
+
+/// A of kind Span, wrap [SpliceChar](struct.SpliceChar.html)
+pub type SpliceSpan<'a> = Span<SpliceChars<'a>>;
+
+impl<'a> SpanOf for SpliceChars<'a> {
+    type SpanOfTarget = SpliceSpan<'a>;
+
+    #[inline]
+    fn span(self) -> Self::SpanOfTarget {
+        SpliceSpan::new(self)
+    }
+}
+
+impl<'a> SpliceSpan<'a> {
+    /// Insert a source at `at`, the span moves back to `at`  
+    /// Items already read after `at` come again after the new source  
+    /// The origin is chained to the item before `at`  
+    /// None and nothing changes if `at` is before the [commit point](trait.TimeTravel.html#method.commit) or past the end of the input
+    /// ## example
+    /// ```
+    /// # use parser_fuck::*;
+    /// let mut span = SpliceChars::new(CharChars::new("a;c".chars()), Origin::new("main")).span();
+    /// one('a').parse(span.ref_clone());
+    /// span.splice(1, CharChars::new("b".chars()), Origin::new("inc")).unwrap();
+    ///
+    /// let r = one('b').and(one(';')).and(one('c')).parse(span.ref_clone());
+    /// assert!(r.is_some());
+    /// assert_eq!(span.get(1).unwrap().origin.include_stack(), vec!["included from main:1:1"]);
+    /// ```
+    pub fn splice(
+        &mut self,
+        at: usize,
+        chars: impl Iterator<Item = Char> + 'a,
+        mut origin: Origin,
+    ) -> Option<()> {
+        let mut timeline = self.timeline();
+        if at < timeline.committed() {
+            return None;
+        }
+        if at > timeline.now_len() {
+            // read up to `at`, the input may end before
+            timeline.get(at - 1)?;
+        }
+        if origin.parent.is_none() && at > 0 {
+            if let Some(c) = timeline.get(at - 1) {
+                origin.parent = Some((c.origin.clone(), c.loc()));
+            }
+        }
+        timeline.rewind_with(at, |iter, tail| iter.push(chars, Arc::new(origin), tail));
+        self.back(at);
+        Some(())
+    }
+    /// Insert a file of a SourceMap at `at`, see [splice](#method.splice)
+    pub fn splice_file(&mut self, at: usize, map: &'a SourceMap, file: FileId) -> Option<()> {
+        let src = map.source(file)?;
+        self.splice(at, CharChars::new(src.chars()), Origin::of_file(map, file)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_splice() {
+        let main = "include\nend";
+        let mut span = SpliceChars::new(CharChars::new(main.chars()), Origin::new("main")).span();
+
+        let r = substr("include").and(one('\n')).parse(span.ref_clone());
+        println!("{:?}", r);
+        assert_eq!(r, Some((0..7, 7..8)));
+        span.splice(8, CharChars::new("x\ny".chars()), Origin::new("inc"));
+
+        let r = substr("x\ny").and(substr("end")).parse(span.ref_clone());
+        println!("{:?}", r);
+        assert_eq!(r, Some((8..11, 11..14)));
+        assert_eq!(span.com_string(0..14), Some("include\nx\nyend".to_string()));
+
+        let y = span.get(10).unwrap();
+        assert_eq!(y.loc(), Loc::new_at(2, 1, 0));
+        assert_eq!(y.origin.name, "inc");
+        assert_eq!(y.origin.include_stack(), vec!["included from main:1:8"]);
+        let e = span.get(11).unwrap();
+        assert_eq!(e.loc(), Loc::new_at(8, 1, 0));
+        assert_eq!(e.origin.depth(), 0);
+    }
+
+    #[test]
+    fn test_nested() {
+        let mut map = SourceMap::new();
+        let a = map.add("a.conf", "1\n2");
+        let b = map.add("b.conf", "3");
+        let mut span = SpliceChars::of_file(&map, a).unwrap().span();
+
+        span.ref_clone().for_each(drop);
+        span.splice_file(2, &map, b).unwrap();
+        span.splice(3, CharChars::new("4".chars()), Origin::new("MACRO"));
+
+        let s: String = span.ref_clone().map(char::from).collect();
+        assert_eq!(s, "42");
+        assert_eq!(span.com_string(0..5), Some("1\n342".to_string()));
+
+        let four = span.get(3).unwrap();
+        assert_eq!(
+            four.origin.include_stack(),
+            vec!["included from b.conf:1:1", "included from a.conf:1:2"]
+        );
+        assert_eq!(four.file_loc(), None);
+        assert_eq!(
+            span.get(2).unwrap().file_loc(),
+            Some(FileLoc::new(b, Loc::new()))
+        );
+    }
+
+    #[test]
+    fn test_splice_bounds() {
+        let text = |span: &SpliceSpan| -> String { span.clone().map(char::from).collect() };
+        let main = "abc";
+        let mut span = SpliceChars::new(CharChars::new(main.chars()), Origin::new("main")).span();
+
+        // not read yet, the input is read up to it
+        span.splice(2, CharChars::new("x".chars()), Origin::new("inc"))
+            .unwrap();
+        assert_eq!(span.save(), 2);
+        assert_eq!(text(&span), "abxc");
+
+        let r = span.splice(5, CharChars::new("y".chars()), Origin::new("inc"));
+        assert_eq!(r, None);
+        assert_eq!(span.save(), 2);
+        assert_eq!(text(&span), "abxc");
+        span.splice(4, CharChars::new("y".chars()), Origin::new("inc"))
+            .unwrap();
+        assert_eq!(text(&span), "abxcy");
+
+        span.back(1);
+        span.commit();
+        let r = span.splice(0, CharChars::new("z".chars()), Origin::new("inc"));
+        assert_eq!(r, None);
+        span.splice(1, CharChars::new("z".chars()), Origin::new("inc"))
+            .unwrap();
+        assert_eq!(text(&span), "zbxcy");
+    }
+}
@@ -0,0 +1,369 @@
+use crate::*;
+use std::cell::{Cell, Ref, RefCell};
+use std::ops::Range;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StateData<S> {
+    state: S,
+    /// `(pos, old)`, the state before the first change at pos since the last save, pos is non-decreasing
+    journal: Vec<(usize, S)>,
+    /// Number of changes dropped by commit, `dropped + journal.len()` only grows by changes
+    dropped: usize,
+    /// `dropped + journal.len()` at the last save, changes after it get their own entry
+    sealed: usize,
+}
+impl<S> StateData<S> {
+    #[inline]
+    fn top(&self) -> usize {
+        self.dropped + self.journal.len()
+    }
+    /// Undo the changes while f says so
+    fn undo(&mut self, f: impl Fn(&Self, usize) -> bool) {
+        while let Some(&(pos, _)) = self.journal.last() {
+            if !f(self, pos) {
+                break;
+            }
+            let (_, old) = self.journal.pop().unwrap();
+            self.state = old;
+        }
+    }
+}
+
+/// A timeline with a user state, like symbol tables or nesting depth  
+/// Changes made by [update](#method.update) belong to the position they are made at,
+/// [back](trait.TimeTravel.html#tymethod.back) undoes every change made after the position, so `or` and `many` roll back the failed branches  
+/// Changes made without consuming anything are undone too when going back to the last [save](trait.TimeTravel.html#tymethod.save) of the same span
+/// [memo](trait.Parser.html#method.memo) does not replay state changes, don't memo parsers that change the state
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// type In<'a> = StatefulSpan<CharSpan<'a>, usize>;
+/// fn nest(input: In) -> Option<usize> {
+///     one('(')
+///         .map(|_| input.update(|d| { *d += 1; *d }))
+///         .and(nest.may())
+///         .and(one(')'))
+///         .map(|((d, n), _)| n.unwrap_or(d))
+///         .parse(input.ref_clone())
+/// }
+/// let code = StatefulSpan::new("((()".span(), 0);
+/// let r = nest.or(one('(').map(|_| 0)).parse(code.ref_clone());
+/// assert_eq!(r, Some(0));
+/// assert_eq!(*code.state(), 0);
+///
+/// let code = StatefulSpan::new("(())".span(), 0);
+/// assert_eq!(nest.parse(code.ref_clone()), Some(2));
+/// ```
+#[derive(Debug)]
+pub struct StatefulSpan<I, S> {
+    inner: I,
+    data: Rc<RefCell<StateData<S>>>,
+    /// `(index, top)` of the last save of this span
+    saved: Cell<Option<(usize, usize)>>,
+}
+impl<I, S> StatefulSpan<I, S> {
+    #[inline]
+    pub fn new(inner: I, state: S) -> Self {
+        Self::with_data(
+            inner,
+            Rc::new(RefCell::new(StateData {
+                state,
+                journal: vec![],
+                dropped: 0,
+                sealed: 0,
+            })),
+        )
+    }
+    #[inline]
+    fn with_data(inner: I, data: Rc<RefCell<StateData<S>>>) -> Self {
+        Self {
+            inner,
+            data,
+            saved: Cell::new(None),
+        }
+    }
+    /// The wrapped timeline
+    #[inline]
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+    /// The current state  
+    /// Don't hold it across an update
+    #[inline]
+    pub fn state(&self) -> Ref<'_, S> {
+        Ref::map(self.data.borrow(), |d| &d.state)
+    }
+    /// Call f with the current state
+    #[inline]
+    pub fn with_state<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        f(&self.data.borrow().state)
+    }
+    /// Number of changes that can still be undone
+    #[inline]
+    pub fn journal_len(&self) -> usize {
+        self.data.borrow().journal.len()
+    }
+}
+impl<I: Memoizable, S: Clone> StatefulSpan<I, S> {
+    /// Position of the current change, after the last consumed item
+    #[inline]
+    fn pos(&self) -> usize {
+        self.inner.consumed_end()
+    }
+    /// Change the state at the current position
+    pub fn update<R>(&self, f: impl FnOnce(&mut S) -> R) -> R {
+        let pos = self.pos();
+        let mut data = self.data.borrow_mut();
+        let last = data.journal.last().map(|(p, _)| *p);
+        if last != Some(pos) || data.top() <= data.sealed {
+            let old = data.state.clone();
+            data.journal.push((pos, old));
+        }
+        f(&mut data.state)
+    }
+    /// Replace the state at the current position
+    #[inline]
+    pub fn set_state(&self, state: S) -> S {
+        self.update(|s| std::mem::replace(s, state))
+    }
+}
+impl<I: PartialEq, S: PartialEq> PartialEq for StatefulSpan<I, S> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner && self.data == other.data
+    }
+}
+impl<I: Eq, S: Eq> Eq for StatefulSpan<I, S> {}
+impl<I: Clone, S: Clone> Clone for StatefulSpan<I, S> {
+    /// Clone the timeline and a copy of the state
+    fn clone(&self) -> Self {
+        Self::with_data(
+            self.inner.clone(),
+            Rc::new(RefCell::new(self.data.borrow().clone())),
+        )
+    }
+}
+impl<I: RefClone, S: Clone> RefClone for StatefulSpan<I, S> {
+    #[inline]
+    fn ref_clone(&self) -> Self {
+        Self::with_data(self.inner.ref_clone(), self.data.clone())
+    }
+}
+impl<I: Iterator, S> Iterator for StatefulSpan<I, S> {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+impl<I: SyncTo, S> SyncTo for StatefulSpan<I, S> {
+    fn sync_to(&self, other: &mut Self) {
+        self.inner.sync_to(&mut other.inner);
+        other.data = self.data.clone();
+    }
+}
+impl<I: TimeTravel, S: Clone> TimeTravel for StatefulSpan<I, S> {
+    #[inline]
+    fn get(&mut self, index: usize) -> Option<Self::Item> {
+        self.inner.get(index)
+    }
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.inner.is_complete()
+    }
+    #[inline]
+    fn re_ready(&mut self) {
+        self.inner.re_ready()
+    }
+    #[inline]
+    fn do_ready(&mut self) {
+        self.inner.do_ready()
+    }
+    fn save(&self) -> usize {
+        let index = self.inner.save();
+        let mut data = self.data.borrow_mut();
+        data.sealed = data.top();
+        self.saved.set(Some((index, data.sealed)));
+        index
+    }
+    fn back(&mut self, index: usize) {
+        let mut data = self.data.borrow_mut();
+        match self.saved.get() {
+            // everything after the save, even at the same position
+            Some((saved, top)) if saved == index => data.undo(|d, _| d.top() > top),
+            _ => data.undo(|_, pos| pos > index),
+        }
+        drop(data);
+        self.inner.back(index)
+    }
+    fn commit(&mut self) {
+        self.inner.commit();
+        // the commit point is save() or save() - 1, keep what going back to either would undo
+        let index = self.inner.save().saturating_sub(1);
+        let mut data = self.data.borrow_mut();
+        let keep = data.journal.iter().position(|(p, _)| *p > index);
+        let dead = keep.unwrap_or(data.journal.len());
+        data.journal.drain(..dead);
+        data.dropped += dead;
+    }
+}
+impl<I: Memoizable, S: Clone> Memoizable for StatefulSpan<I, S> {
+    #[inline]
+    fn peak(&self) -> usize {
+        self.inner.peak()
+    }
+    #[inline]
+    fn swap_peak(&mut self, peak: usize) -> usize {
+        self.inner.swap_peak(peak)
+    }
+    #[inline]
+    fn is_ready(&self) -> bool {
+        self.inner.is_ready()
+    }
+    #[inline]
+    fn input_id(&self) -> u128 {
+        self.inner.input_id()
+    }
+}
+impl<I: GetFileId, S> GetFileId for StatefulSpan<I, S> {
+    #[inline]
+    fn file_id(&self) -> Option<FileId> {
+        self.inner.file_id()
+    }
+}
+impl<I: ComString<ComStringData = Range<usize>>, S> ComString for StatefulSpan<I, S> {
+    type ComStringData = Range<usize>;
+
+    #[inline]
+    fn com_string(&self, range: Range<usize>) -> Option<String> {
+        self.inner.com_string(range)
+    }
+}
+impl<I: ComLoc<ComLocData = usize>, S> ComLoc for StatefulSpan<I, S> {
+    type ComLocData = usize;
+
+    #[inline]
+    fn loc(&self, index: usize) -> Option<Loc> {
+        self.inner.loc(index)
+    }
+}
+impl<I: ComLocRange<ComLocRangeData = Range<usize>>, S> ComLocRange for StatefulSpan<I, S> {
+    type ComLocRangeData = Range<usize>;
+
+    #[inline]
+    fn loc_range(&self, range: Range<usize>) -> Option<LocRange> {
+        self.inner.loc_range(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::collections::HashSet;
+
+    type In<'a> = StatefulSpan<CharSpan<'a>, HashSet<String>>;
+
+    fn ident(input: In) -> Option<String> {
+        satisfy(|c: Char| c.char(|c, _| c.is_alphabetic()).unwrap_or(false))
+            .many1()
+            .map(|r| input.com_string(range_of_many1(r)).unwrap())
+            .parse(input.ref_clone())
+    }
+    /// `typedef T;` declares T as a type name
+    fn typedef(input: In) -> Option<()> {
+        substr("typedef ")
+            .and(ident)
+            .and(one(';'))
+            .map(|((_, name), _)| {
+                input.update(|s| s.insert(name));
+            })
+            .parse(input.ref_clone())
+    }
+    /// An ident declared by typedef
+    fn type_name(input: In) -> Option<String> {
+        ident
+            .parse(input.ref_clone())
+            .filter(|t| input.with_state(|s| s.contains(t)))
+    }
+    /// `T x;` is a declaration only if T is a type name
+    fn decl(input: In) -> Option<(String, String)> {
+        type_name
+            .and(one(' '))
+            .and(ident)
+            .and(one(';'))
+            .map(|(((t, _), x), _)| (t, x))
+            .parse(input)
+    }
+
+    #[test]
+    fn test_rollback() {
+        let code = StatefulSpan::new("typedef A;!typedef B;?".span(), HashSet::new());
+        let x = typedef.and(one('!')).many();
+        let r = x.parse(code.ref_clone());
+        println!("{:?} {:?}", r, code.state());
+        assert_eq!(r, Some(vec![((), 10..11)]));
+        assert_eq!(*code.state(), vec!["A".to_string()].into_iter().collect());
+
+        let code = StatefulSpan::new("typedef A;".span(), HashSet::new());
+        let x = typedef
+            .and(one('!'))
+            .map(|_| 1)
+            .or(substr("typedef ").map(|_| 2));
+        let r = x.parse(code.ref_clone());
+        assert_eq!(r, Some(2));
+        assert!(code.state().is_empty());
+        assert_eq!(code.journal_len(), 0);
+    }
+
+    #[test]
+    fn test_zero_width() {
+        type In<'a> = StatefulSpan<CharSpan<'a>, usize>;
+        fn add(n: usize) -> impl Fn(In) -> Option<()> {
+            move |input: In| {
+                input.update(|s| *s += n);
+                Some(())
+            }
+        }
+        fn read(input: In) -> Option<usize> {
+            let s = *input.state();
+            one('a').map(move |_| s).parse(input.ref_clone())
+        }
+        // the failed branch changes the state without consuming anything
+        let x = add(10).and(
+            add(1)
+                .and(one('b'))
+                .map(|_| 0)
+                .or(add(100).and(read).map(|(_, s)| s)),
+        );
+
+        let code = StatefulSpan::new("a".span(), 0);
+        let r = x.parse(code.ref_clone());
+        assert_eq!(r, Some(((), 110)));
+        assert_eq!(*code.state(), 110);
+
+        let code = StatefulSpan::new("b".span(), 0);
+        let r = x.and(one('!')).map(|_| 0).or(read).parse(code.ref_clone());
+        assert_eq!(r, None);
+        assert_eq!(*code.state(), 0);
+        assert_eq!(code.journal_len(), 0);
+    }
+
+    #[test]
+    fn test_typedef() {
+        let code = StatefulSpan::new("typedef T;T x;U y;".span(), HashSet::new());
+        let x = typedef.and(decl).and(decl);
+        let r = x.parse(code.ref_clone());
+        assert_eq!(r, None);
+
+        let mut code = StatefulSpan::new("typedef T;T x;".span(), HashSet::new());
+        let x = typedef.and(decl);
+        let r = x.parse(code.ref_clone());
+        assert_eq!(r, Some(((), ("T".to_string(), "x".to_string()))));
+        assert_eq!(code.journal_len(), 1);
+        code.commit();
+        assert_eq!(code.journal_len(), 0);
+        assert!(code.with_state(|s| s.contains("T")));
+    }
+}
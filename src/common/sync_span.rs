@@ -0,0 +1,427 @@
+use super::timeline::TimelineBox;
+use super::*;
+use std::fmt;
+use std::fmt::Debug;
+use std::ops::Range;
+use std::str::Chars;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[inline]
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    // the lock is never held while user code runs, so a poisoned lock still holds consistent data
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Thread-safe version of [Timeline](struct.Timeline.html)  
+/// Items are cloned out instead of borrowed
+pub struct SyncTimeline<I: Iterator> {
+    inner: Arc<Mutex<TimelineBox<I>>>,
+}
+impl<I: Iterator> SyncTimeline<I> {
+    #[inline]
+    pub fn new(iter: I) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(TimelineBox::new(iter))),
+        }
+    }
+    /// Number of history records currently stored  
+    /// Counts from the start of the timeline, including records that were dropped by [commit](#method.commit)
+    #[inline]
+    pub fn now_len(&self) -> usize {
+        lock(&self.inner).now_len()
+    }
+    /// Iterate internal iterator to completion
+    pub fn to_end(&self) {
+        let mut inner = lock(&self.inner);
+        loop {
+            let index = inner.now_len();
+            if !inner.fill(index) {
+                break;
+            }
+        }
+    }
+    /// Check if the internal iterator has been completed
+    #[inline]
+    pub fn is_end(&self) -> bool {
+        lock(&self.inner).end
+    }
+    /// Get the value of the specified position  
+    /// - None if the internal iterator is completed but not found  
+    /// - None if index is before the commit point
+    pub fn get(&self, index: usize) -> Option<I::Item>
+    where
+        I::Item: Clone,
+    {
+        let mut inner = lock(&self.inner);
+        inner.peak = inner.peak.max(index + 1);
+        if index < inner.committed || !inner.fill(index) {
+            return None;
+        }
+        inner.get(index).cloned()
+    }
+    /// Call f with the records in range  
+    /// None if the range is not stored
+    pub fn with_range<R>(&self, range: Range<usize>, f: impl FnOnce(&[I::Item]) -> R) -> Option<R> {
+        let inner = lock(&self.inner);
+        if range.end > inner.now_len() || range.start < inner.committed {
+            return None;
+        }
+        Some(f(inner.slice(range.start, range.end)))
+    }
+    /// Promise that nothing before `index` will be read again  
+    /// Earlier records may be freed, indices stay absolute
+    #[inline]
+    pub fn commit(&self, index: usize) {
+        lock(&self.inner).commit(index)
+    }
+    /// The current commit point, records before it are gone
+    #[inline]
+    pub fn committed(&self) -> usize {
+        lock(&self.inner).committed
+    }
+    /// Number of history records actually kept in memory
+    #[inline]
+    pub fn buffered_len(&self) -> usize {
+        lock(&self.inner).buf.len()
+    }
+    /// Call f with the internal iterator
+    #[inline]
+    pub fn with_iter<R>(&self, f: impl FnOnce(&I) -> R) -> R {
+        f(&lock(&self.inner).iter)
+    }
+    /// One past the highest index asked for since the last [swap_peak](#method.swap_peak)
+    #[inline]
+    pub fn peak(&self) -> usize {
+        lock(&self.inner).peak
+    }
+    /// Identity of the timeline, the same for all its clones
+    #[inline]
+    pub fn id(&self) -> u128 {
+        lock(&self.inner).uuid.as_u128()
+    }
+    /// Set the peak and return the old one
+    #[inline]
+    pub fn swap_peak(&self, peak: usize) -> usize {
+        std::mem::replace(&mut lock(&self.inner).peak, peak)
+    }
+}
+impl<I: Iterator> Clone for SyncTimeline<I> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+impl<I: Iterator> RefClone for SyncTimeline<I> {
+    #[inline]
+    fn ref_clone(&self) -> Self {
+        self.clone()
+    }
+}
+impl<I: Iterator> PartialEq for SyncTimeline<I> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+impl<I: Iterator> Eq for SyncTimeline<I> {}
+impl<I: Iterator> Debug for SyncTimeline<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncTimeline")
+            .field("inner", &*lock(&self.inner))
+            .finish()
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+struct SyncSpanData<I: Iterator> {
+    index: usize,
+    ready: bool,
+    timeline: SyncTimeline<I>,
+}
+impl<I: Iterator> Debug for SyncSpanData<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncSpanData")
+            .field("index", &self.index)
+            .field("ready", &self.ready)
+            .field("timeline", &self.timeline)
+            .finish()
+    }
+}
+
+/// Thread-safe version of [Span](struct.Span.html)  
+/// Built on `Arc<Mutex<..>>`, so it is `Send + Sync` when the iterator and its items are `Send`
+pub struct SyncSpan<I: Iterator> {
+    inner: Arc<Mutex<SyncSpanData<I>>>,
+}
+impl<I: Iterator> SyncSpan<I> {
+    #[inline]
+    pub fn new(iter: I) -> Self {
+        Self::from_timeline(SyncTimeline::new(iter), 0)
+    }
+    #[inline]
+    fn from_timeline(timeline: SyncTimeline<I>, index: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(SyncSpanData {
+                index,
+                ready: true,
+                timeline,
+            })),
+        }
+    }
+    #[inline]
+    fn timeline(&self) -> SyncTimeline<I> {
+        lock(&self.inner).timeline.clone()
+    }
+}
+impl<I: Iterator> From<I> for SyncSpan<I> {
+    #[inline]
+    fn from(iter: I) -> Self {
+        Self::new(iter)
+    }
+}
+impl<I: Iterator> Clone for SyncSpan<I> {
+    fn clone(&self) -> Self {
+        let timeline = self.timeline();
+        let index = timeline.committed();
+        Self::from_timeline(timeline, index)
+    }
+}
+impl<I: Iterator> RefClone for SyncSpan<I> {
+    fn ref_clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+impl<I: Iterator> Debug for SyncSpan<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncSpan")
+            .field("inner", &*lock(&self.inner))
+            .finish()
+    }
+}
+impl<I: Iterator> Iterator for SyncSpan<I>
+where
+    I::Item: Clone,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut this = lock(&self.inner);
+        let r = this.timeline.get(this.index)?;
+        this.index += 1;
+        this.ready = false;
+        Some(r)
+    }
+}
+impl<I: Iterator> TimeTravel for SyncSpan<I>
+where
+    I::Item: Clone,
+{
+    fn get(&mut self, index: usize) -> Option<Self::Item> {
+        self.timeline().get(index)
+    }
+    fn re_ready(&mut self) {
+        let mut this = lock(&self.inner);
+        if !this.ready && (!this.timeline.is_end() || this.index < this.timeline.now_len()) {
+            this.index -= 1;
+            this.ready = true;
+        }
+    }
+    fn do_ready(&mut self) {
+        lock(&self.inner).ready = true;
+    }
+    fn is_complete(&self) -> bool {
+        self.timeline().is_end()
+    }
+    fn save(&self) -> usize {
+        lock(&self.inner).index
+    }
+    fn back(&mut self, index: usize) {
+        let mut this = lock(&self.inner);
+        this.index = index;
+        this.ready = true;
+    }
+    fn commit(&mut self) {
+        let this = lock(&self.inner);
+        let index = if this.ready {
+            this.index
+        } else {
+            this.index - 1
+        };
+        this.timeline.commit(index);
+    }
+}
+impl<I: Iterator> Memoizable for SyncSpan<I>
+where
+    I::Item: Clone,
+{
+    #[inline]
+    fn peak(&self) -> usize {
+        self.timeline().peak()
+    }
+    #[inline]
+    fn swap_peak(&mut self, peak: usize) -> usize {
+        self.timeline().swap_peak(peak)
+    }
+    #[inline]
+    fn is_ready(&self) -> bool {
+        lock(&self.inner).ready
+    }
+    #[inline]
+    fn input_id(&self) -> u128 {
+        self.timeline().id()
+    }
+}
+impl<I: Iterator> SyncTo for SyncSpan<I> {
+    fn sync_to(&self, other: &mut Self) {
+        other.inner = self.inner.clone();
+    }
+}
+impl<I: Iterator + GetFileId> GetFileId for SyncSpan<I> {
+    #[inline]
+    fn file_id(&self) -> Option<FileId> {
+        self.timeline().with_iter(|i| i.file_id())
+    }
+}
+impl<I: Iterator> ComString for SyncSpan<I>
+where
+    I::Item: GetString,
+{
+    type ComStringData = Range<usize>;
+
+    fn com_string(&self, range: Range<usize>) -> Option<String> {
+        self.timeline()
+            .with_range(range, |s| s.iter().map(|c| c.get_string()).collect())
+    }
+}
+impl<I: Iterator> ComLoc for SyncSpan<I>
+where
+    I::Item: GetLoc + Clone,
+{
+    type ComLocData = usize;
+
+    fn loc(&self, index: usize) -> Option<Loc> {
+        let c = self.timeline().get(index)?;
+        Some(c.loc())
+    }
+}
+impl<I: Iterator> ComLocRange for SyncSpan<I>
+where
+    I::Item: GetLoc + Clone,
+{
+    type ComLocRangeData = Range<usize>;
+
+    fn loc_range(&self, range: Range<usize>) -> Option<LocRange> {
+        let Range { start, end } = range;
+        debug_assert!(start <= end);
+        if start == 0 && end == 0 {
+            return Some(LocRange::new_empty());
+        }
+        let timeline = self.timeline();
+        if end > timeline.now_len() {
+            None
+        } else {
+            let s = timeline.get(start)?;
+            let e = timeline.get(end - 1)?;
+            Some(LocRange::new(s.loc(), e.loc()))
+        }
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A of kind SyncSpan, wrap [Char](enum.Char.html)
+pub type SyncCharSpan<'a> = SyncSpan<CharChars<Chars<'a>>>;
+
+/// Into SyncSpan
+pub trait SyncSpanOf {
+    type SyncSpanOfTarget;
+    fn sync_span(self) -> Self::SyncSpanOfTarget;
+}
+
+impl<'a> SyncSpanOf for CharChars<Chars<'a>> {
+    type SyncSpanOfTarget = SyncCharSpan<'a>;
+
+    #[inline]
+    fn sync_span(self) -> Self::SyncSpanOfTarget {
+        SyncCharSpan::new(self)
+    }
+}
+
+impl<'a> SyncSpanOf for Chars<'a> {
+    type SyncSpanOfTarget = SyncCharSpan<'a>;
+
+    #[inline]
+    fn sync_span(self) -> Self::SyncSpanOfTarget {
+        SyncCharSpan::new(CharChars::new(self))
+    }
+}
+
+impl<'a> SyncSpanOf for &'a str {
+    type SyncSpanOfTarget = SyncCharSpan<'a>;
+
+    #[inline]
+    fn sync_span(self) -> Self::SyncSpanOfTarget {
+        SyncCharSpan::new(CharChars::new(self.chars()))
+    }
+}
+
+impl<'a> SyncSpanOf for &'a String {
+    type SyncSpanOfTarget = SyncCharSpan<'a>;
+
+    #[inline]
+    fn sync_span(self) -> Self::SyncSpanOfTarget {
+        SyncCharSpan::new(CharChars::new(self.chars()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    #[test]
+    fn test() {
+        let mut span = "asd123".sync_span();
+        assert_send_sync(&span);
+
+        let r = substr("asd").parse(span.ref_clone());
+        assert_eq!(r, Some(0..3));
+        span.re_ready();
+        let r = substr("123").parse(span.ref_clone());
+        assert_eq!(r, Some(3..6));
+        assert_eq!(span.com_string(0..6), Some("asd123".to_string()));
+        assert_eq!(span.loc_range(3..6), Some((3, 0, 3, 5, 0, 5).into()));
+    }
+
+    #[test]
+    fn test_across_threads() {
+        let mut span = "asd123".sync_span();
+        substr("asd").parse(span.ref_clone());
+        span.re_ready();
+
+        let other = span.ref_clone();
+        let r = thread::spawn(move || substr("123").parse(other))
+            .join()
+            .unwrap();
+        assert_eq!(r, Some(3..6));
+    }
+
+    #[test]
+    fn test_commit() {
+        let mut span = "asd123".sync_span();
+
+        one('a').parse(span.ref_clone());
+        span.commit();
+        assert_eq!(span.com_string(0..1), None);
+
+        let r = one('s').parse(span.clone());
+        assert_eq!(r, Some(1..2));
+    }
+}
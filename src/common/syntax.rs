@@ -0,0 +1,558 @@
+use crate::*;
+use std::fmt;
+use std::fmt::{Debug, Display};
+use std::ops::Range;
+use std::rc::Rc;
+
+/// A leaf of the green tree, owns its text
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct GreenToken<K> {
+    kind: K,
+    text: String,
+    len: usize,
+    trivia: bool,
+}
+impl<K: Copy> GreenToken<K> {
+    #[inline]
+    pub fn new(kind: K, text: impl Into<String>, trivia: bool) -> Self {
+        let text = text.into();
+        Self {
+            kind,
+            len: text.chars().count(),
+            text,
+            trivia,
+        }
+    }
+    #[inline]
+    pub fn kind(&self) -> K {
+        self.kind
+    }
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// Length in chars
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Is whitespace or comment, see [trivia](trait.Parser.html#method.trivia)
+    #[inline]
+    pub fn is_trivia(&self) -> bool {
+        self.trivia
+    }
+}
+
+/// A child of a green node
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum GreenElement<K> {
+    Node(Rc<GreenNode<K>>),
+    Token(Rc<GreenToken<K>>),
+}
+impl<K: Copy> GreenElement<K> {
+    #[inline]
+    pub fn kind(&self) -> K {
+        match self {
+            GreenElement::Node(n) => n.kind(),
+            GreenElement::Token(t) => t.kind(),
+        }
+    }
+    /// Length in chars
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(n) => n.len(),
+            GreenElement::Token(t) => t.len(),
+        }
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// An immutable node without position, can be shared between trees
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct GreenNode<K> {
+    kind: K,
+    children: Vec<GreenElement<K>>,
+    len: usize,
+}
+impl<K: Copy> GreenNode<K> {
+    #[inline]
+    pub fn new(kind: K, children: Vec<GreenElement<K>>) -> Self {
+        let len = children.iter().map(|c| c.len()).sum();
+        Self {
+            kind,
+            children,
+            len,
+        }
+    }
+    #[inline]
+    pub fn kind(&self) -> K {
+        self.kind
+    }
+    #[inline]
+    pub fn children(&self) -> &[GreenElement<K>] {
+        &self.children
+    }
+    /// Length in chars
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    fn write_text(&self, s: &mut String) {
+        for c in &self.children {
+            match c {
+                GreenElement::Node(n) => n.write_text(s),
+                GreenElement::Token(t) => s.push_str(&t.text),
+            }
+        }
+    }
+}
+impl<K: Copy> Display for GreenNode<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = String::new();
+        self.write_text(&mut s);
+        write!(f, "{}", s)
+    }
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+struct SyntaxData<K> {
+    green: Rc<GreenNode<K>>,
+    offset: usize,
+    parent: Option<SyntaxNode<K>>,
+    lines: Rc<LineIndex>,
+}
+
+/// A node of the red tree, a green node with its position and parent  
+/// Built by [parse_cst](fn.parse_cst.html), its text is exactly the source
+pub struct SyntaxNode<K> {
+    data: Rc<SyntaxData<K>>,
+}
+impl<K> Clone for SyntaxNode<K> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+        }
+    }
+}
+impl<K: Copy> SyntaxNode<K> {
+    #[inline]
+    fn new(
+        green: Rc<GreenNode<K>>,
+        offset: usize,
+        parent: Option<SyntaxNode<K>>,
+        lines: Rc<LineIndex>,
+    ) -> Self {
+        Self {
+            data: Rc::new(SyntaxData {
+                green,
+                offset,
+                parent,
+                lines,
+            }),
+        }
+    }
+    #[inline]
+    pub fn kind(&self) -> K {
+        self.data.green.kind
+    }
+    #[inline]
+    pub fn green(&self) -> &Rc<GreenNode<K>> {
+        &self.data.green
+    }
+    #[inline]
+    pub fn parent(&self) -> Option<&SyntaxNode<K>> {
+        self.data.parent.as_ref()
+    }
+    /// Char offset range in the source
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.data.offset..self.data.offset + self.data.green.len
+    }
+    /// LocRange in the source, `to` is the last item like the ranges parsers produce
+    #[inline]
+    pub fn loc_range(&self) -> LocRange {
+        loc_range_in(&self.data.lines, self.range(), &self.text())
+    }
+    /// Source text of the node
+    #[inline]
+    pub fn text(&self) -> String {
+        self.data.green.to_string()
+    }
+    /// Child nodes and tokens
+    pub fn children(&self) -> Vec<SyntaxElement<K>> {
+        let mut offset = self.data.offset;
+        self.data
+            .green
+            .children
+            .iter()
+            .map(|c| {
+                let start = offset;
+                offset += c.len();
+                match c {
+                    GreenElement::Node(n) => SyntaxElement::Node(SyntaxNode::new(
+                        n.clone(),
+                        start,
+                        Some(self.clone()),
+                        self.data.lines.clone(),
+                    )),
+                    GreenElement::Token(t) => SyntaxElement::Token(SyntaxToken {
+                        green: t.clone(),
+                        offset: start,
+                        parent: self.clone(),
+                    }),
+                }
+            })
+            .collect()
+    }
+    /// Child nodes
+    pub fn child_nodes(&self) -> Vec<SyntaxNode<K>> {
+        self.children()
+            .into_iter()
+            .filter_map(|c| match c {
+                SyntaxElement::Node(n) => Some(n),
+                _ => None,
+            })
+            .collect()
+    }
+    /// All tokens in order, including trivia
+    pub fn tokens(&self) -> Vec<SyntaxToken<K>> {
+        let mut r = vec![];
+        for c in self.children() {
+            match c {
+                SyntaxElement::Node(n) => r.extend(n.tokens()),
+                SyntaxElement::Token(t) => r.push(t),
+            }
+        }
+        r
+    }
+}
+impl<K: Copy> Display for SyntaxNode<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.data.green)
+    }
+}
+impl<K: Copy + Debug> Debug for SyntaxNode<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{:?}", self.kind(), self.range())
+    }
+}
+impl<K> PartialEq for SyntaxNode<K> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.data.green, &other.data.green) && self.data.offset == other.data.offset
+    }
+}
+impl<K> Eq for SyntaxNode<K> {}
+
+/// A token of the red tree
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxToken<K> {
+    green: Rc<GreenToken<K>>,
+    offset: usize,
+    parent: SyntaxNode<K>,
+}
+impl<K: Copy> SyntaxToken<K> {
+    #[inline]
+    pub fn kind(&self) -> K {
+        self.green.kind
+    }
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+    #[inline]
+    pub fn is_trivia(&self) -> bool {
+        self.green.trivia
+    }
+    #[inline]
+    pub fn green(&self) -> &Rc<GreenToken<K>> {
+        &self.green
+    }
+    #[inline]
+    pub fn parent(&self) -> &SyntaxNode<K> {
+        &self.parent
+    }
+    /// Char offset range in the source
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.len
+    }
+    /// LocRange in the source, `to` is the last item like the ranges parsers produce
+    #[inline]
+    pub fn loc_range(&self) -> LocRange {
+        loc_range_in(&self.parent.data.lines, self.range(), self.text())
+    }
+}
+impl<K: Copy + Debug> Debug for SyntaxToken<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{:?} {:?}", self.kind(), self.range(), self.text())
+    }
+}
+
+/// A child in the red tree
+#[derive(Clone, PartialEq, Eq)]
+pub enum SyntaxElement<K> {
+    Node(SyntaxNode<K>),
+    Token(SyntaxToken<K>),
+}
+impl<K: Copy> SyntaxElement<K> {
+    #[inline]
+    pub fn kind(&self) -> K {
+        match self {
+            SyntaxElement::Node(n) => n.kind(),
+            SyntaxElement::Token(t) => t.kind(),
+        }
+    }
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            SyntaxElement::Node(n) => n.range(),
+            SyntaxElement::Token(t) => t.range(),
+        }
+    }
+    #[inline]
+    pub fn loc_range(&self) -> LocRange {
+        match self {
+            SyntaxElement::Node(n) => n.loc_range(),
+            SyntaxElement::Token(t) => t.loc_range(),
+        }
+    }
+}
+
+impl<K: Copy + Debug> Debug for SyntaxElement<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxElement::Node(n) => Debug::fmt(n, f),
+            SyntaxElement::Token(t) => Debug::fmt(t, f),
+        }
+    }
+}
+
+fn loc_range_in(lines: &LineIndex, range: Range<usize>, text: &str) -> LocRange {
+    if range.start == range.end {
+        let loc = lines.loc(range.start).unwrap_or_default();
+        return LocRange::new(loc, loc);
+    }
+    let last = if text.ends_with("\r\n") { 2 } else { 1 };
+    let from = lines.loc(range.start).unwrap_or_default();
+    let to = lines.loc(range.end - last).unwrap_or_default();
+    LocRange::new(from, to)
+}
+
+//\/////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Parse `src` into a lossless concrete syntax tree under a `root` node  
+/// The tree is made of the [token](trait.Parser.html#method.token)s and [node](trait.Parser.html#method.node)s the parser produced,
+/// text no token covers becomes `K::default()` tokens, so the tree's text is always exactly `src`
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// enum Kind { #[default] Error, Root, Pair, Key, Eq, Value, Space }
+///
+/// let space = satisfy(|c: Char| c == ' ').many1().trivia(Kind::Space);
+/// let word = || satisfy(|c: Char| c.char(|c, _| c.is_alphanumeric()).unwrap_or(false)).many1();
+/// let pair = word().token(Kind::Key)
+///     .and(space.clone().may())
+///     .and(one('=').token(Kind::Eq))
+///     .and(space.may())
+///     .and(word().token(Kind::Value))
+///     .node(Kind::Pair);
+///
+/// let src = "a = 1";
+/// let (r, tree) = parse_cst(src, Kind::Root, pair);
+/// assert!(r.is_some());
+/// assert_eq!(tree.to_string(), src);
+/// let pair = &tree.child_nodes()[0];
+/// let kinds: Vec<Kind> = pair.tokens().iter().map(|t| t.kind()).collect();
+/// assert_eq!(kinds, vec![Kind::Key, Kind::Space, Kind::Eq, Kind::Space, Kind::Value]);
+/// assert_eq!(pair.tokens()[4].loc_range(), (4, 0, 4).into());
+/// ```
+pub fn parse_cst<'a, K, P>(src: &'a str, root: K, parser: P) -> (Option<P::Output>, SyntaxNode<K>)
+where
+    K: Copy + Default + 'static,
+    P: Parser<CharSpan<'a>>,
+{
+    let (r, forest) = cst_session::<K, _>(|| parser.parse(src.span()));
+    let mut offsets: Vec<usize> = CharChars::new(src.chars())
+        .map(|c| c.loc().offset)
+        .collect();
+    let items = offsets.len();
+    let lines = Rc::new(LineIndex::new(src));
+    offsets.push(lines.len());
+
+    let builder = GreenBuilder {
+        src,
+        offsets,
+        lines: &lines,
+    };
+    let green = builder.node(root, 0, items, forest);
+    (r, SyntaxNode::new(Rc::new(green), 0, None, lines))
+}
+
+struct GreenBuilder<'a> {
+    src: &'a str,
+    /// char offset of every item, and the length at the end
+    offsets: Vec<usize>,
+    lines: &'a LineIndex,
+}
+impl GreenBuilder<'_> {
+    fn text(&self, start: usize, end: usize) -> &str {
+        let from = self.lines.byte_offset(self.offsets[start]).unwrap();
+        let to = self.lines.byte_offset(self.offsets[end]).unwrap();
+        &self.src[from..to]
+    }
+    fn gap<K: Copy + Default>(
+        &self,
+        children: &mut Vec<GreenElement<K>>,
+        start: usize,
+        end: usize,
+    ) {
+        if start < end {
+            let token = GreenToken::new(K::default(), self.text(start, end), false);
+            children.push(GreenElement::Token(Rc::new(token)));
+        }
+    }
+    fn node<K: Copy + Default>(
+        &self,
+        kind: K,
+        start: usize,
+        end: usize,
+        events: Vec<CstEvent<K>>,
+    ) -> GreenNode<K> {
+        let mut children = vec![];
+        let mut now = start;
+        for e in events {
+            self.gap(&mut children, now, e.start());
+            now = now.max(e.end());
+            children.push(match e {
+                CstEvent::Token {
+                    kind,
+                    trivia,
+                    start,
+                    end,
+                } => GreenElement::Token(Rc::new(GreenToken::new(
+                    kind,
+                    self.text(start, end),
+                    trivia,
+                ))),
+                CstEvent::Node {
+                    kind,
+                    start,
+                    end,
+                    children,
+                } => GreenElement::Node(Rc::new(self.node(kind, start, end, children))),
+            });
+        }
+        self.gap(&mut children, now, end);
+        GreenNode::new(kind, children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    enum Kind {
+        #[default]
+        Error,
+        Root,
+        List,
+        Item,
+        Comma,
+        Space,
+        Comment,
+    }
+
+    fn trivia(input: CharSpan) -> Option<()> {
+        satisfy(|c: Char| c == ' ' || c.is_wrap())
+            .many1()
+            .trivia(Kind::Space)
+            .map(|_| ())
+            .or(one('#')
+                .and(satisfy(|c: Char| c.is_char()).many())
+                .trivia(Kind::Comment)
+                .map(|_| ()))
+            .many()
+            .map(|_| ())
+            .parse(input)
+    }
+    fn item(input: CharSpan) -> Option<()> {
+        satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false))
+            .many1()
+            .token(Kind::Item)
+            .map(|_| ())
+            .parse(input)
+    }
+    fn list(input: CharSpan) -> Option<()> {
+        trivia
+            .and(item)
+            .and(
+                trivia
+                    .and(one(',').token(Kind::Comma))
+                    .and(trivia)
+                    .and(item)
+                    .many(),
+            )
+            .and(trivia)
+            .node(Kind::List)
+            .map(|_| ())
+            .parse(input)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let src = "# nums\r\n1, 22 ,\n  333 # end\n";
+        let (r, tree) = parse_cst(src, Kind::Root, list);
+        println!("{:#?}", tree.tokens());
+        assert_eq!(r, Some(()));
+        assert_eq!(tree.to_string(), src);
+        assert_eq!(tree.kind(), Kind::Root);
+
+        let list = &tree.child_nodes()[0];
+        assert_eq!(list.kind(), Kind::List);
+        assert_eq!(list.parent(), Some(&tree));
+        let tokens = list.tokens();
+        let items: Vec<&str> = tokens
+            .iter()
+            .filter(|t| !t.is_trivia())
+            .map(|t| t.text())
+            .collect();
+        assert_eq!(items, vec!["1", ",", "22", ",", "333"]);
+        assert_eq!(tokens[0].kind(), Kind::Comment);
+        assert_eq!(tokens[1].text(), "\r\n");
+        assert_eq!(tokens[1].loc_range(), (6, 0, 6).into());
+        let last = tokens.iter().find(|t| t.text() == "333").unwrap();
+        assert_eq!(last.range(), 18..21);
+        assert_eq!(last.loc_range(), (18, 2, 2, 20, 2, 4).into());
+        assert_eq!(list.loc_range(), (0, 0, 0, 27, 2, 11).into());
+    }
+
+    #[test]
+    fn test_error_gap() {
+        let src = "1,2;x";
+        let (r, tree) = parse_cst(src, Kind::Root, list);
+        assert_eq!(r, Some(()));
+        assert_eq!(tree.to_string(), src);
+
+        let children = tree.children();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[1].kind(), Kind::Error);
+        assert_eq!(children[1].range(), 3..5);
+    }
+}
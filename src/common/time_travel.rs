@@ -46,12 +46,37 @@ pub trait TimeTravel: Iterator + RefClone + SyncTo {
     fn save(&self) -> usize;
     /// Time travel to a point in history
     fn back(&mut self, index: usize);
+    /// Promise that will never travel back before the current time point  
+    /// Implementations may free the history before it, does nothing by default
+    fn commit(&mut self) {}
     /// Calculate the range from save point to current
     fn make_range(&self, from: usize) -> Range<usize> {
         from..self.save()
     }
 }
 
+/// A timeline that knows how far it has been read, needed by [Memo](struct.Memo.html)
+pub trait Memoizable: TimeTravel {
+    /// One past the highest index read since the last [swap_peak](#tymethod.swap_peak)  
+    /// Reading past the end counts, so results that depend on the end are tracked too
+    fn peak(&self) -> usize;
+    /// Set the peak and return the old one
+    fn swap_peak(&mut self, peak: usize) -> usize;
+    /// Check if it is ready to next, see [re_ready](trait.TimeTravel.html#tymethod.re_ready)
+    fn is_ready(&self) -> bool;
+    /// Identity of the input, the same for every clone of it
+    fn input_id(&self) -> u128;
+    /// Index after the last consumed item, what `save()` would be after `re_ready()`
+    fn consumed_end(&self) -> usize {
+        let index = self.save();
+        if self.is_ready() || (self.is_complete() && self.ref_clone().get(index).is_none()) {
+            index
+        } else {
+            index - 1
+        }
+    }
+}
+
 /// Make another instance equal to yourself  
 pub trait SyncTo {
     /// let other = self
@@ -1,5 +1,5 @@
-use crate::common::cell::*;
 use crate::common::*;
+use std::cell::{Ref, RefCell};
 use std::cmp::{Eq, PartialEq};
 use std::convert::From;
 use std::default::Default;
@@ -7,17 +7,23 @@ use std::fmt;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 use std::iter::Iterator;
-use std::marker::PhantomData;
-use std::ops::{Deref, Index, Range, RangeFrom, RangeFull, RangeTo};
+use std::ops::Range;
 use std::rc::Rc;
 use uuid::Uuid;
 
 #[derive(Default)]
-struct TimelineBox<I: Iterator> {
-    uuid: Uuid,
+pub(crate) struct TimelineBox<I: Iterator> {
+    pub uuid: Uuid,
     pub iter: I,
+    /// Retained history, `buf[0]` is the item at `base`
     pub buf: Vec<I::Item>,
+    /// Absolute index of `buf[0]`
+    pub base: usize,
+    /// Items before this index are no longer reachable
+    pub committed: usize,
     pub end: bool,
+    /// One past the highest index asked for by `get`
+    pub peak: usize,
 }
 impl<I: Iterator> TimelineBox<I> {
     #[inline]
@@ -26,9 +32,76 @@ impl<I: Iterator> TimelineBox<I> {
             uuid: Uuid::new_v4(),
             iter,
             buf: vec![],
+            base: 0,
+            committed: 0,
             end: false,
+            peak: 0,
         }
     }
+    #[inline]
+    pub fn now_len(&self) -> usize {
+        self.base + self.buf.len()
+    }
+    /// Pull from the internal iterator until `index` is buffered
+    pub fn fill(&mut self, index: usize) -> bool {
+        while index >= self.now_len() {
+            if self.end {
+                return false;
+            }
+            if let Some(v) = self.iter.next() {
+                self.buf.push(v);
+            } else {
+                self.end = true;
+                return false;
+            }
+        }
+        true
+    }
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&I::Item> {
+        if index < self.committed {
+            return None;
+        }
+        self.buf.get(index - self.base)
+    }
+    /// Drop everything before `index`  
+    /// The buffer is only compacted once the dead part outgrows the live part, so repeated commits stay amortized O(1)
+    pub fn commit(&mut self, index: usize) {
+        let index = index.min(self.now_len());
+        if index <= self.committed {
+            return;
+        }
+        self.committed = index;
+        let dead = self.committed - self.base;
+        if dead >= self.buf.len() - dead {
+            self.buf.drain(..dead);
+            self.base = self.committed;
+        }
+    }
+    /// Take back the records from `index` on, the internal iterator may continue after them
+    pub fn split_off(&mut self, index: usize) -> Vec<I::Item> {
+        assert!(
+            index >= self.committed,
+            "index {} is before the commit point {}",
+            index,
+            self.committed
+        );
+        self.end = false;
+        if index >= self.now_len() {
+            return vec![];
+        }
+        self.buf.split_off(index - self.base)
+    }
+    #[inline]
+    pub fn slice(&self, from: usize, to: usize) -> &[I::Item] {
+        assert!(
+            from >= self.committed,
+            "index {} is before the commit point {}",
+            from,
+            self.committed
+        );
+        &self.buf[from - self.base..to - self.base]
+    }
 }
 impl<I: Iterator> PartialEq for TimelineBox<I> {
     fn eq(&self, other: &Self) -> bool {
@@ -47,7 +120,10 @@ impl<I: Iterator> Debug for TimelineBox<I> {
             .field("uuid", &self.uuid)
             .field("iter", &"...")
             .field("buf", &"...")
+            .field("base", &self.base)
+            .field("committed", &self.committed)
             .field("end", &self.end)
+            .field("peak", &self.peak)
             .finish()
     }
 }
@@ -55,75 +131,111 @@ impl<I: Iterator> Debug for TimelineBox<I> {
 //\/////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Collection of storage history  
-/// Wrap an iterator
+/// Wrap an iterator  
+/// Records are handed out as `Ref`s, keep them short-lived: pulling more from the iterator while one is held panics
 pub struct Timeline<I: Iterator> {
-    inner: Rc<ExtRefCell<TimelineBox<I>>>,
+    inner: Rc<RefCell<TimelineBox<I>>>,
 }
 impl<I: Iterator> Timeline<I> {
     #[inline]
     pub fn new(iter: I) -> Self {
         Self {
-            inner: Rc::new(ExtRefCell::new(TimelineBox::new(iter))),
+            inner: Rc::new(RefCell::new(TimelineBox::new(iter))),
         }
     }
+    /// Iterate from the current end, cloning the records
     #[inline]
-    pub fn iter(&self) -> TimelineIter<'_, I> {
+    pub fn iter(&self) -> TimelineIter<I> {
         TimelineIter::new(self.clone())
     }
-    /// Number of history records currently stored
+    /// Number of history records currently stored  
+    /// Counts from the start of the timeline, including records that were dropped by [commit](#method.commit)
     #[inline]
     pub fn now_len(&self) -> usize {
-        self.inner.buf.len()
+        self.inner.borrow().now_len()
     }
-    pub fn next(&mut self) -> Option<&I::Item> {
-        if self.inner.end {
-            return None;
-        } else {
-            let r = self.inner.get_mut().iter.next();
-            if let Some(v) = r {
-                self.inner.get_mut().buf.push(v);
-            } else {
-                self.inner.get_mut().end = true;
-                return None;
-            }
-        }
-        self.inner.buf.get(self.inner.buf.len() - 1)
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Ref<'_, I::Item>> {
+        let index = self.now_len();
+        self.get(index)
     }
     /// Iterate internal iterator to completion
     pub fn to_end(&mut self) {
-        while let Some(_) = self.next() {}
+        while self.next().is_some() {}
     }
     /// Check if the internal iterator has been completed
     pub fn is_end(&self) -> bool {
-        self.inner.end
+        self.inner.borrow().end
     }
     /// Get the value of the specified position  
     /// - None if the internal iterator is completed but not found  
     /// - None if index is less than 0  
-    pub fn get(&mut self, index: usize) -> Option<&I::Item> {
-        unsafe { self.unsafe_get(index) }
-    }
-    #[inline]
-    unsafe fn unsafe_get(&self, index: usize) -> Option<&I::Item> {
-        if index >= self.inner.buf.len() {
-            if self.inner.end {
+    /// - None if index is before the commit point
+    pub fn get(&self, index: usize) -> Option<Ref<'_, I::Item>> {
+        {
+            let mut inner = self.inner.borrow_mut();
+            inner.peak = inner.peak.max(index + 1);
+            if index < inner.committed || !inner.fill(index) {
                 return None;
-            } else {
-                loop {
-                    let r = (*self.inner).get_mut().iter.next();
-                    if let Some(v) = r {
-                        (*self.inner).get_mut().buf.push(v);
-                    } else {
-                        (*self.inner).get_mut().end = true;
-                        return None;
-                    }
-                    if index < self.inner.buf.len() {
-                        break;
-                    }
-                }
             }
         }
-        self.inner.buf.get(index)
+        Ref::filter_map(self.inner.borrow(), |inner| inner.get(index)).ok()
+    }
+    /// Get the records in the range  
+    /// None if the range is not stored
+    pub fn slice(&self, range: Range<usize>) -> Option<Ref<'_, [I::Item]>> {
+        let inner = self.inner.borrow();
+        if range.end > inner.now_len() || range.start < inner.committed {
+            return None;
+        }
+        Some(Ref::map(inner, |inner| inner.slice(range.start, range.end)))
+    }
+    /// Promise that nothing before `index` will be read again  
+    /// Earlier records may be freed, indices stay absolute
+    #[inline]
+    pub fn commit(&mut self, index: usize) {
+        self.inner.borrow_mut().commit(index)
+    }
+    /// The current commit point, records before it are gone
+    #[inline]
+    pub fn committed(&self) -> usize {
+        self.inner.borrow().committed
+    }
+    /// Number of history records actually kept in memory
+    #[inline]
+    pub fn buffered_len(&self) -> usize {
+        self.inner.borrow().buf.len()
+    }
+    /// Call f with the internal iterator
+    #[inline]
+    pub fn with_iter<R>(&self, f: impl FnOnce(&I) -> R) -> R {
+        f(&self.inner.borrow().iter)
+    }
+    /// One past the highest index asked for since the last [swap_peak](#method.swap_peak)
+    #[inline]
+    pub fn peak(&self) -> usize {
+        self.inner.borrow().peak
+    }
+    /// Identity of the timeline, the same for all its clones
+    #[inline]
+    pub fn id(&self) -> u128 {
+        self.inner.borrow().uuid.as_u128()
+    }
+    /// Set the peak and return the old one
+    #[inline]
+    pub fn swap_peak(&self, peak: usize) -> usize {
+        std::mem::replace(&mut self.inner.borrow_mut().peak, peak)
+    }
+    /// Take back the records from `index` on and let f change the internal iterator  
+    /// Reading continues from `index` with whatever the iterator yields next
+    pub(crate) fn rewind_with<R>(
+        &mut self,
+        index: usize,
+        f: impl FnOnce(&mut I, Vec<I::Item>) -> R,
+    ) -> R {
+        let mut inner = self.inner.borrow_mut();
+        let tail = inner.split_off(index);
+        f(&mut inner.iter, tail)
     }
 }
 impl<I: Iterator> Debug for Timeline<I> {
@@ -140,13 +252,13 @@ where
 {
     fn default() -> Self {
         Self {
-            inner: Rc::new(ExtRefCell::new(TimelineBox::default())),
+            inner: Rc::new(RefCell::new(TimelineBox::default())),
         }
     }
 }
-impl<I: Iterator> From<Rc<ExtRefCell<TimelineBox<I>>>> for Timeline<I> {
+impl<I: Iterator> From<Rc<RefCell<TimelineBox<I>>>> for Timeline<I> {
     #[inline]
-    fn from(inner: Rc<ExtRefCell<TimelineBox<I>>>) -> Self {
+    fn from(inner: Rc<RefCell<TimelineBox<I>>>) -> Self {
         Self { inner }
     }
 }
@@ -165,75 +277,42 @@ impl<I: Iterator> RefClone for Timeline<I> {
 impl<I: Iterator> PartialEq for Timeline<I> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
-        self.inner.deref().eq(other.inner.deref())
+        Rc::ptr_eq(&self.inner, &other.inner) || self.inner.borrow().eq(&*other.inner.borrow())
     }
 }
 impl<I: Iterator> Eq for Timeline<I> {}
 impl<I: Iterator> Hash for Timeline<I> {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.inner.hash(state)
-    }
-}
-impl<I: Iterator> Index<Range<usize>> for Timeline<I> {
-    type Output = [I::Item];
-    fn index(&self, index: Range<usize>) -> &Self::Output {
-        &self.inner.buf[index]
-    }
-}
-impl<I: Iterator> Index<RangeFrom<usize>> for Timeline<I> {
-    type Output = [I::Item];
-    fn index(&self, index: RangeFrom<usize>) -> &Self::Output {
-        &self.inner.buf[index]
-    }
-}
-impl<I: Iterator> Index<RangeTo<usize>> for Timeline<I> {
-    type Output = [I::Item];
-    fn index(&self, index: RangeTo<usize>) -> &Self::Output {
-        &self.inner.buf[index]
-    }
-}
-impl<I: Iterator> Index<RangeFull> for Timeline<I> {
-    type Output = [I::Item];
-    fn index(&self, index: RangeFull) -> &Self::Output {
-        &self.inner.buf[index]
-    }
-}
-impl<I: Iterator> Index<usize> for Timeline<I> {
-    type Output = I::Item;
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.inner.buf[index]
+        self.inner.borrow().hash(state)
     }
 }
 
 //\/////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// TimeLine's Iter
-pub struct TimelineIter<'a, I: Iterator> {
+pub struct TimelineIter<I: Iterator> {
     timeline: Timeline<I>,
-    _a: PhantomData<&'a I::Item>,
 }
-impl<'a, I: Iterator> TimelineIter<'a, I> {
+impl<I: Iterator> TimelineIter<I> {
     #[inline]
     pub fn new(timeline: Timeline<I>) -> Self {
-        Self {
-            timeline,
-            _a: PhantomData,
-        }
+        Self { timeline }
     }
 }
-impl<'a, I: Iterator> Iterator for TimelineIter<'a, I>
+impl<I: Iterator> Iterator for TimelineIter<I>
 where
-    I::Item: 'a,
+    I::Item: Clone,
 {
-    type Item = &'a I::Item;
+    type Item = I::Item;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        self.timeline.next().map(|v| unsafe { &*(v as *const _) })
+        self.timeline.next().map(|v| v.clone())
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::redundant_pattern_matching)]
 mod tests {
     use super::Timeline;
 
@@ -260,7 +339,7 @@ mod tests {
     #[test]
     fn test_base() {
         let code = "asd123";
-        let mut iter = Timeline::new(code.chars());
+        let iter = Timeline::new(code.chars());
 
         assert_get!(iter, 0, 'a');
         assert_get!(iter, 1, 's');
@@ -276,7 +355,7 @@ mod tests {
     #[test]
     fn test_re_get() {
         let code = "asd123";
-        let mut iter = Timeline::new(code.chars());
+        let iter = Timeline::new(code.chars());
 
         assert_get!(iter, 0, 'a');
         assert_get!(iter, 0, 'a');
@@ -302,4 +381,46 @@ mod tests {
 
         assert!(matches!(iter.next(), None))
     }
+
+    #[test]
+    fn test_commit() {
+        let code = "asd123";
+        let mut iter = Timeline::new(code.chars());
+
+        assert_get!(iter, 3, '1');
+        iter.commit(2);
+        assert_eq!(iter.committed(), 2);
+        assert!(iter.get(1).is_none());
+        assert_get!(iter, 2, 'd');
+        assert_get!(iter, 5, '3');
+        assert_eq!(iter.now_len(), 6);
+        assert_eq!(&*iter.slice(2..4).unwrap(), &['d', '1']);
+
+        iter.commit(1);
+        assert_eq!(iter.committed(), 2);
+    }
+
+    #[test]
+    fn test_iter() {
+        let code = "asd";
+        let timeline = Timeline::new(code.chars());
+
+        assert_get!(timeline, 0, 'a');
+        let rest: String = timeline.iter().collect();
+        assert_eq!(rest, "sd");
+        assert!(timeline.is_end());
+        assert_eq!(&*timeline.slice(0..3).unwrap(), &['a', 's', 'd']);
+    }
+
+    #[test]
+    fn test_commit_compact() {
+        let mut iter = Timeline::new(0..);
+
+        for i in 0..1000 {
+            assert_get!(iter, i, i);
+            iter.commit(i);
+        }
+        assert!(iter.buffered_len() <= 2);
+        assert_get!(iter, 999, 999);
+    }
 }
@@ -0,0 +1,691 @@
+//! Export a [described](../trait.Parser.html#method.describe) grammar as EBNF text or railroad diagrams  
+//! Every labelled parser becomes a rule, where it is used it is a reference to that rule instead of being inlined
+//! ## EBNF
+//! The W3C notation, with a few additions for what PEG parsers can do
+//! ```text
+//! name ::= a b | c       rule, sequence, choice
+//! a* a+ a? a{2,3}        repeat
+//! !a                     pass if a fails
+//! "lit" [a-z] .          literal, char class, any item
+//! ? name ?               a parser that can not be looked into
+//! ```
+//! ## example
+//! ```
+//! # use parser_fuck::*;
+//! use parser_fuck::export::Rules;
+//!
+//! let digit = satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false)).label("digit");
+//! let number = one('-').literal().may().and(digit.many1()).label("number");
+//! let list = substr("[").and(number.many()).and(substr("]")).label("list");
+//!
+//! let rules = Rules::of::<CharSpan, _>(&list);
+//! assert_eq!(
+//!     rules.ebnf(),
+//!     "list ::= \"[\" number* \"]\"\nnumber ::= \"-\"? digit+\ndigit ::= ? satisfy ?\n"
+//! );
+//! let svg = rules.svg("number").unwrap();
+//! assert!(svg.starts_with("<svg"));
+//! std::fs::write(std::env::temp_dir().join("list.html"), rules.html("List")).unwrap();
+//! ```
+
+use crate::*;
+use std::fmt::Write;
+
+/// The rules of a grammar, in the order they are first seen
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Rules {
+    rules: Vec<(String, GrammarNode)>,
+}
+impl Rules {
+    /// Collect the rules of a description, an unlabelled root is the rule `start`
+    pub fn new(root: &GrammarNode) -> Self {
+        let mut rules = Self::default();
+        rules.add(root);
+        rules
+    }
+    /// Collect the rules of a parser
+    #[inline]
+    pub fn of<I: TimeTravel, P: Parser<I>>(parser: &P) -> Self {
+        Self::new(&parser.describe())
+    }
+    /// Add the rules of another description  
+    /// A rule defined again the same way is kept, a different rule with a used name is renamed like `name_2`, so is a second `start`
+    pub fn add(&mut self, root: &GrammarNode) {
+        let mut open = vec![];
+        if let GrammarNode::Label { .. } = root {
+            self.extract(root, &mut open);
+        } else {
+            self.define("start", root, &mut open);
+        }
+    }
+    /// Replace labels with references, defining the rules they name  
+    /// `open` are the labels being defined and their rule names, a label inside its own definition is recursion
+    fn extract(&mut self, node: &GrammarNode, open: &mut Vec<(String, String)>) -> GrammarNode {
+        match node {
+            GrammarNode::Label { name, node } => {
+                if let Some((_, rule)) = open.iter().find(|(label, _)| label == name) {
+                    return GrammarNode::Ref(rule.clone());
+                }
+                GrammarNode::Ref(self.define(name, node, open))
+            }
+            GrammarNode::Seq(v) => {
+                GrammarNode::Seq(v.iter().map(|n| self.extract(n, open)).collect())
+            }
+            GrammarNode::Choice(v) => {
+                GrammarNode::Choice(v.iter().map(|n| self.extract(n, open)).collect())
+            }
+            GrammarNode::Repeat { node, min, max } => GrammarNode::Repeat {
+                node: Box::new(self.extract(node, open)),
+                min: *min,
+                max: *max,
+            },
+            GrammarNode::Not(node) => GrammarNode::Not(Box::new(self.extract(node, open))),
+            node => node.clone(),
+        }
+    }
+    /// Define a rule and return its name, the same definition is not added twice
+    fn define(
+        &mut self,
+        name: &str,
+        node: &GrammarNode,
+        open: &mut Vec<(String, String)>,
+    ) -> String {
+        let names = || {
+            std::iter::once(name.to_string()).chain((2..).map(move |n| format!("{}_{}", name, n)))
+        };
+        // define it first, so a rule using itself stops here
+        let i = self.rules.len();
+        let fresh = names().find(|n| self.get(n).is_none()).unwrap();
+        self.rules.push((fresh.clone(), GrammarNode::Seq(vec![])));
+        open.push((name.to_string(), fresh.clone()));
+        let def = self.extract(node, open);
+        open.pop();
+        let same = names()
+            .take_while(|n| *n != fresh)
+            .find(|n| self.get(n) == Some(&rename(&def, &fresh, n)));
+        match same {
+            Some(same) => {
+                self.rules.truncate(i);
+                same
+            }
+            None => {
+                self.rules[i].1 = def;
+                fresh
+            }
+        }
+    }
+
+    /// The definition of a rule
+    pub fn get(&self, name: &str) -> Option<&GrammarNode> {
+        self.rules.iter().find(|(n, _)| n == name).map(|(_, r)| r)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &GrammarNode)> {
+        self.rules.iter().map(|(n, r)| (n.as_str(), r))
+    }
+    /// The rules, then an opaque rule for every reference with no definition, like a `def_parser!` parser
+    fn closed(&self) -> Vec<(String, GrammarNode)> {
+        let mut refs = vec![];
+        for (_, node) in &self.rules {
+            refs_of(node, &mut refs);
+        }
+        let mut rules = self.rules.clone();
+        for name in refs {
+            if rules.iter().all(|(n, _)| *n != name) {
+                rules.push((name.clone(), GrammarNode::Opaque(name)));
+            }
+        }
+        rules
+    }
+
+    /// All rules as EBNF, one per line, top level choices on their own lines  
+    /// A reference to no rule gets an opaque one, like `digit ::= ? digit ?`
+    pub fn ebnf(&self) -> String {
+        let mut s = String::new();
+        for (name, node) in &self.closed() {
+            rule(name, node, &mut s);
+            s.push('\n');
+        }
+        s
+    }
+    /// A self contained SVG railroad diagram of a rule, references link to `#name`
+    pub fn svg(&self, rule: &str) -> Option<String> {
+        let rules = self.closed();
+        let node = rules.iter().find(|(n, _)| n == rule).map(|(_, r)| r)?;
+        let item = Item::new(node);
+        let (w, u, d) = item.size();
+        let y = MARGIN + u;
+        let end = MARGIN + END + w;
+        let mut s = String::new();
+        write!(
+            s,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" class="railroad" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = end + END + MARGIN,
+            h = u + d + 2 * MARGIN
+        )
+        .unwrap();
+        write!(s, "<style>{}</style>", STYLE).unwrap();
+        path(
+            &mut s,
+            format!("M{} {} v{} m0 {} h{}", MARGIN, y - 8, 16, -8, END),
+        );
+        item.render(MARGIN + END, y, &mut s);
+        path(&mut s, format!("M{} {} h{} m0 {} v{}", end, y, END, -8, 16));
+        s.push_str("</svg>");
+        Some(s)
+    }
+    /// A self contained HTML page with a diagram and the EBNF of every rule
+    pub fn html(&self, title: &str) -> String {
+        let mut s = String::new();
+        write!(
+            s,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>body {{ font-family: sans-serif; }} pre {{ background: #f6f6f6; padding: 8px; }}</style>\n</head>\n<body>\n<h1>{0}</h1>\n",
+            escape(title)
+        )
+        .unwrap();
+        for (name, node) in &self.closed() {
+            let mut def = String::new();
+            rule(name, node, &mut def);
+            write!(
+                s,
+                "<h2 id=\"{}\">{0}</h2>\n{}\n<pre>{}</pre>\n",
+                escape(name),
+                self.svg(name).unwrap(),
+                escape(&def)
+            )
+            .unwrap();
+        }
+        s.push_str("</body>\n</html>\n");
+        s
+    }
+}
+
+/// Write a rule, top level choices on their own lines
+fn rule(name: &str, node: &GrammarNode, s: &mut String) {
+    write!(s, "{} ::= ", name).unwrap();
+    match node {
+        GrammarNode::Choice(v) if !v.is_empty() => {
+            let indent = " ".repeat(name.chars().count() + 3);
+            for (i, n) in v.iter().enumerate() {
+                if i > 0 {
+                    write!(s, "\n{}| ", indent).unwrap();
+                }
+                ebnf(n, 1, s);
+            }
+        }
+        node => ebnf(node, 0, s),
+    }
+}
+
+/// Write node, in parentheses if it binds looser than prec  
+/// Choice 0, sequence 1, not 2, repeat 3, the rest 4
+fn ebnf(node: &GrammarNode, prec: u8, s: &mut String) {
+    let own = match node {
+        GrammarNode::Choice(v) if v.len() > 1 => 0,
+        GrammarNode::Seq(v) if v.len() > 1 => 1,
+        GrammarNode::Not(_) => 2,
+        GrammarNode::Repeat { .. } => 3,
+        _ => 4,
+    };
+    if own < prec {
+        s.push('(');
+    }
+    match node {
+        GrammarNode::Seq(v) | GrammarNode::Choice(v) if v.is_empty() => s.push_str("()"),
+        GrammarNode::Seq(v) => {
+            for (i, n) in v.iter().enumerate() {
+                if i > 0 {
+                    s.push(' ');
+                }
+                ebnf(n, 2, s);
+            }
+        }
+        GrammarNode::Choice(v) => {
+            for (i, n) in v.iter().enumerate() {
+                if i > 0 {
+                    s.push_str(" | ");
+                }
+                ebnf(n, 1, s);
+            }
+        }
+        GrammarNode::Repeat { node, min, max } => {
+            ebnf(node, 4, s);
+            match (min, max) {
+                (0, None) => s.push('*'),
+                (1, None) => s.push('+'),
+                (0, Some(1)) => s.push('?'),
+                (n, None) => write!(s, "{{{},}}", n).unwrap(),
+                (n, Some(m)) if n == m => write!(s, "{{{}}}", n).unwrap(),
+                (n, Some(m)) => write!(s, "{{{},{}}}", n, m).unwrap(),
+            }
+        }
+        GrammarNode::Not(node) => {
+            s.push('!');
+            ebnf(node, 3, s);
+        }
+        GrammarNode::Literal(l) => {
+            let q = if l.contains('"') { '\'' } else { '"' };
+            write!(s, "{}{}{}", q, l, q).unwrap();
+        }
+        GrammarNode::Class(c) => s.push_str(c),
+        GrammarNode::Label { name, .. } | GrammarNode::Ref(name) => s.push_str(name),
+        GrammarNode::Opaque(name) => write!(s, "? {} ?", name).unwrap(),
+    }
+    if own < prec {
+        s.push(')');
+    }
+}
+
+const ARC: i32 = 10;
+const VS: i32 = 10;
+const GAP: i32 = 10;
+const PAD: i32 = 10;
+const CHAR: i32 = 8;
+const BOX: i32 = 24;
+const LABEL: i32 = 14;
+const MARGIN: i32 = 20;
+const END: i32 = 10;
+const STYLE: &str = "path { fill: none; stroke: #333; stroke-width: 2; } \
+rect { fill: #ffc; stroke: #333; stroke-width: 2; } \
+.ref rect { fill: #def; } \
+.opaque rect { fill: #eee; stroke-dasharray: 4 2; } \
+rect.not { fill: none; stroke-width: 1; stroke-dasharray: 4 2; } \
+text { font: 13px monospace; text-anchor: middle; dominant-baseline: central; } \
+text.note { font-size: 11px; }";
+
+/// A piece of a diagram, drawn on a line at y from x to x + width
+enum Item {
+    Box(String, &'static str),
+    Seq(Vec<Item>),
+    Choice(Vec<Item>),
+    Repeat(Box<Item>, Option<String>),
+    Not(Box<Item>),
+    Skip,
+}
+impl Item {
+    fn new(node: &GrammarNode) -> Self {
+        match node {
+            GrammarNode::Seq(v) if v.is_empty() => Item::Skip,
+            GrammarNode::Seq(v) => Item::Seq(v.iter().map(Item::new).collect()),
+            GrammarNode::Choice(v) => Item::Choice(v.iter().map(Item::new).collect()),
+            GrammarNode::Repeat { node, min, max } => {
+                let repeat = |note| Item::Repeat(Box::new(Item::new(node)), note);
+                match (min, max) {
+                    (0, Some(1)) => Item::Choice(vec![Item::new(node), Item::Skip]),
+                    (1, None) => repeat(None),
+                    (0, None) => Item::Choice(vec![repeat(None), Item::Skip]),
+                    (n, None) => repeat(Some(format!("{}+ times", n))),
+                    (0, Some(m)) => {
+                        Item::Choice(vec![repeat(Some(format!("up to {} times", m))), Item::Skip])
+                    }
+                    (n, Some(m)) if n == m => repeat(Some(format!("{} times", n))),
+                    (n, Some(m)) => repeat(Some(format!("{}..{} times", n, m))),
+                }
+            }
+            GrammarNode::Not(node) => Item::Not(Box::new(Item::new(node))),
+            GrammarNode::Literal(l) => Item::Box(l.clone(), "literal"),
+            GrammarNode::Class(c) => Item::Box(c.clone(), "class"),
+            GrammarNode::Label { name, .. } | GrammarNode::Ref(name) => {
+                Item::Box(name.clone(), "ref")
+            }
+            GrammarNode::Opaque(name) => Item::Box(name.clone(), "opaque"),
+        }
+    }
+    /// Width, height above the line and below it
+    fn size(&self) -> (i32, i32, i32) {
+        match self {
+            Item::Box(text, _) => (
+                text.chars().count() as i32 * CHAR + 2 * PAD,
+                BOX / 2,
+                BOX / 2,
+            ),
+            Item::Seq(v) => v.iter().map(Item::size).fold((-GAP, 0, 0), |a, b| {
+                (a.0 + GAP + b.0, a.1.max(b.1), a.2.max(b.2))
+            }),
+            Item::Choice(v) => {
+                let w = v.iter().map(|i| i.size().0).max().unwrap_or(0);
+                let offsets = Self::offsets(v);
+                let last = v.last().map(|i| i.size().2).unwrap_or(0);
+                (
+                    w + 4 * ARC,
+                    v.first().map(|i| i.size().1).unwrap_or(0),
+                    offsets.last().unwrap_or(&0) + last,
+                )
+            }
+            Item::Repeat(item, note) => {
+                let (w, u, d) = item.size();
+                let note = note.as_ref().map(|_| LABEL).unwrap_or(0);
+                (w + 2 * ARC, u, Self::loop_offset(d) + note)
+            }
+            Item::Not(item) => {
+                let (w, u, d) = item.size();
+                (w + 2 * PAD, u + PAD + LABEL, d + PAD)
+            }
+            Item::Skip => (0, 0, 0),
+        }
+    }
+    /// How far below the line each choice is
+    fn offsets(v: &[Item]) -> Vec<i32> {
+        let mut offsets = vec![];
+        let mut prev: Option<(i32, i32)> = None;
+        for item in v {
+            let (_, u, d) = item.size();
+            let y = match prev {
+                None => 0,
+                Some((y, pd)) => (y + pd + VS + u).max(y + 2 * ARC),
+            };
+            offsets.push(y);
+            prev = Some((y, d));
+        }
+        offsets
+    }
+    fn loop_offset(d: i32) -> i32 {
+        (d + VS).max(2 * ARC)
+    }
+    fn render(&self, x: i32, y: i32, s: &mut String) {
+        match self {
+            Item::Box(text, class) => {
+                let (w, _, _) = self.size();
+                let (open, close) = if *class == "ref" {
+                    (format!("<a href=\"#{}\">", escape(text)), "</a>")
+                } else {
+                    (String::new(), "")
+                };
+                let rx = match *class {
+                    "literal" => 10,
+                    "class" => 4,
+                    _ => 0,
+                };
+                write!(
+                    s,
+                    r#"{}<g class="{}"><rect x="{}" y="{}" width="{}" height="{}" rx="{}"/><text x="{}" y="{}">{}</text></g>{}"#,
+                    open,
+                    class,
+                    x,
+                    y - BOX / 2,
+                    w,
+                    BOX,
+                    rx,
+                    x + w / 2,
+                    y,
+                    escape(text),
+                    close
+                )
+                .unwrap();
+            }
+            Item::Seq(v) => {
+                let mut x = x;
+                for (i, item) in v.iter().enumerate() {
+                    if i > 0 {
+                        path(s, format!("M{} {} h{}", x, y, GAP));
+                        x += GAP;
+                    }
+                    item.render(x, y, s);
+                    x += item.size().0;
+                }
+            }
+            Item::Choice(v) => {
+                let (w, _, _) = self.size();
+                let inner = w - 4 * ARC;
+                for (item, off) in v.iter().zip(Self::offsets(v)) {
+                    let iw = item.size().0;
+                    let by = y + off;
+                    if off == 0 {
+                        path(s, format!("M{} {} h{}", x, y, 2 * ARC));
+                    } else {
+                        path(
+                            s,
+                            format!(
+                                "M{} {} a{a} {a} 0 0 1 {a} {a} v{} a{a} {a} 0 0 0 {a} {a}",
+                                x,
+                                y,
+                                off - 2 * ARC,
+                                a = ARC
+                            ),
+                        );
+                    }
+                    item.render(x + 2 * ARC, by, s);
+                    let ix = x + 2 * ARC + iw;
+                    if off == 0 {
+                        path(s, format!("M{} {} h{}", ix, by, inner - iw + 2 * ARC));
+                    } else {
+                        path(
+                            s,
+                            format!(
+                                "M{} {} h{} a{a} {a} 0 0 0 {a} {} v{} a{a} {a} 0 0 1 {a} {}",
+                                ix,
+                                by,
+                                inner - iw,
+                                -ARC,
+                                -(off - 2 * ARC),
+                                -ARC,
+                                a = ARC
+                            ),
+                        );
+                    }
+                }
+            }
+            Item::Repeat(item, note) => {
+                let (iw, _, d) = item.size();
+                let off = Self::loop_offset(d);
+                path(s, format!("M{} {} h{}", x, y, ARC));
+                item.render(x + ARC, y, s);
+                let right = x + ARC + iw;
+                path(s, format!("M{} {} h{}", right, y, ARC));
+                path(
+                    s,
+                    format!(
+                        "M{} {} a{a} {a} 0 0 1 {a} {a} v{} a{a} {a} 0 0 1 {} {a} h{} a{a} {a} 0 0 1 {} {} v{} a{a} {a} 0 0 1 {a} {}",
+                        right,
+                        y,
+                        off - 2 * ARC,
+                        -ARC,
+                        -iw,
+                        -ARC,
+                        -ARC,
+                        -(off - 2 * ARC),
+                        -ARC,
+                        a = ARC
+                    ),
+                );
+                if let Some(note) = note {
+                    write!(
+                        s,
+                        r#"<text class="note" x="{}" y="{}">{}</text>"#,
+                        x + ARC + iw / 2,
+                        y + off + LABEL / 2 + 2,
+                        escape(note)
+                    )
+                    .unwrap();
+                }
+            }
+            Item::Not(item) => {
+                let (w, _, _) = self.size();
+                let (iw, u, d) = item.size();
+                write!(
+                    s,
+                    r#"<rect class="not" x="{}" y="{}" width="{}" height="{}"/><text class="note" x="{}" y="{}">not</text>"#,
+                    x,
+                    y - u - PAD,
+                    w,
+                    u + d + 2 * PAD,
+                    x + w / 2,
+                    y - u - PAD - LABEL / 2
+                )
+                .unwrap();
+                path(s, format!("M{} {} h{}", x, y, PAD));
+                item.render(x + PAD, y, s);
+                path(s, format!("M{} {} h{}", x + PAD + iw, y, PAD));
+            }
+            Item::Skip => {}
+        }
+    }
+}
+
+/// Replace references to a rule
+fn rename(node: &GrammarNode, from: &str, to: &str) -> GrammarNode {
+    match node {
+        GrammarNode::Ref(name) if name == from => GrammarNode::Ref(to.to_string()),
+        GrammarNode::Seq(v) => GrammarNode::Seq(v.iter().map(|n| rename(n, from, to)).collect()),
+        GrammarNode::Choice(v) => {
+            GrammarNode::Choice(v.iter().map(|n| rename(n, from, to)).collect())
+        }
+        GrammarNode::Repeat { node, min, max } => GrammarNode::Repeat {
+            node: Box::new(rename(node, from, to)),
+            min: *min,
+            max: *max,
+        },
+        GrammarNode::Not(node) => GrammarNode::Not(Box::new(rename(node, from, to))),
+        node => node.clone(),
+    }
+}
+
+/// Collect the names of the references in node, once each
+fn refs_of(node: &GrammarNode, refs: &mut Vec<String>) {
+    match node {
+        GrammarNode::Ref(name) if !refs.contains(name) => refs.push(name.clone()),
+        GrammarNode::Seq(v) | GrammarNode::Choice(v) => v.iter().for_each(|n| refs_of(n, refs)),
+        GrammarNode::Repeat { node, .. } | GrammarNode::Not(node) => refs_of(node, refs),
+        _ => (),
+    }
+}
+
+fn path(s: &mut String, d: String) {
+    write!(s, r#"<path d="{}"/>"#, d).unwrap();
+}
+
+fn escape(text: &str) -> String {
+    let mut s = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => s.push_str("&amp;"),
+            '<' => s.push_str("&lt;"),
+            '>' => s.push_str("&gt;"),
+            '"' => s.push_str("&quot;"),
+            c => s.push(c),
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peg::Grammar;
+
+    #[test]
+    fn test_ebnf() {
+        let grammar = Grammar::new(
+            r#"
+            sum     <- product (("+" / "-") product)*
+            product <- value (("*" / "/") value)*
+            value   <- num / "(" sum ")" / '"' [^"]* '"'
+            num     <- [0-9]+ !"."
+            "#,
+        )
+        .unwrap();
+        let mut rules = Rules::default();
+        for name in grammar.rules().collect::<Vec<_>>().into_iter().rev() {
+            rules.add(&grammar.compile(name).unwrap().describe());
+        }
+        let r = rules.ebnf();
+        println!("{}", r);
+        assert_eq!(
+            r,
+            r#"num ::= [0-9]+ !"."
+value ::= num
+        | "(" sum ")"
+        | '"' [^"]* '"'
+product ::= value (("*" | "/") value)*
+sum ::= product (("+" | "-") product)*
+"#
+        );
+
+        let x = one('a')
+            .literal()
+            .some(3)
+            .and(substr("b").many_min_max(2, 4).not());
+        let r = Rules::of::<CharSpan, _>(&x).ebnf();
+        assert_eq!(r, "start ::= \"a\"{3} !\"b\"{2,4}\n");
+    }
+
+    #[test]
+    fn test_svg() {
+        let digit = satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false));
+        let number = one('-')
+            .literal()
+            .may()
+            .and(digit.label("digit").many_max(3))
+            .label("number");
+        let list = substr("<")
+            .and(number.and(one(',').literal().not()).many())
+            .and(substr(">"))
+            .label("list");
+        let rules = Rules::of::<CharSpan, _>(&list);
+        assert_eq!(
+            rules.iter().map(|(n, _)| n).collect::<Vec<_>>(),
+            vec!["list", "number", "digit"]
+        );
+
+        let svg = rules.svg("list").unwrap();
+        println!("{}", svg);
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg""#));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains(r##"<a href="#number">"##));
+        assert!(svg.contains("&lt;"));
+        assert!(svg.contains(">not</text>"));
+        assert_eq!(svg.matches("<rect").count(), 5);
+        assert!(rules.svg("digit").unwrap().contains(r#"class="opaque""#));
+        assert!(rules.svg("number").unwrap().contains("up to 3 times"));
+        assert_eq!(rules.svg("nope"), None);
+
+        let html = rules.html("List");
+        assert!(html.contains(r#"<h2 id="digit">digit</h2>"#));
+        assert!(html.contains("<pre>number ::= &quot;-&quot;? digit{0,3}</pre>"));
+        assert_eq!(html.matches("<svg").count(), 3);
+    }
+
+    def_parser! {
+        digit(input: CharSpan<'_>) -> () {
+            satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false))
+                .parse(input)
+                .map(|_| ())
+        }
+    }
+
+    #[test]
+    fn test_add() {
+        let x = one('x').literal();
+        let mut rules = Rules::of::<CharSpan, _>(&substr("a").and(x.clone().label("x")));
+        rules.add(&Parser::<CharSpan>::describe(
+            &substr("b").and(x.clone().label("x")),
+        ));
+        rules.add(&Parser::<CharSpan>::describe(&substr("c").label("x")));
+        let nest = recursive(|this: Dyn<CharSpan, ()>| {
+            one('(')
+                .literal()
+                .and(this.may())
+                .and(one(')').literal())
+                .map(|_| ())
+                .label("nest")
+        });
+        rules.add(&nest.describe());
+        rules.add(&nest.describe());
+        rules.add(&digit::new().many1().label("num").describe());
+        let r = rules.ebnf();
+        println!("{}", r);
+        assert_eq!(
+            r,
+            r#"start ::= "a" x
+x ::= "x"
+start_2 ::= "b" x
+x_2 ::= "c"
+nest ::= "(" nest? ")"
+num ::= digit+
+digit ::= ? digit ?
+"#
+        );
+        assert!(rules.svg("num").unwrap().contains(r##"<a href="#digit">"##));
+        assert!(rules.html("g").contains(r#"<h2 id="digit">"#));
+        assert_eq!(rules.get("digit"), None);
+    }
+}
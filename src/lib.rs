@@ -72,14 +72,15 @@
 //!         .and(
 //!             one('0').or(num_start
 //!                 .many1()
-//!                 .and(num_body.many())
-//!                 .map(range_of_many1_many)),
+//!                 .map(range_of_many1)
+//!                 .and(num_body.fold_many(None, |_, r| Some(r)))
+//!                 .map(range_of_range_optrange)),
 //!         )
 //!         .map(range_of_optrange_range)
 //!         .and(
 //!             one('.')
-//!                 .and(num_body.many1())
-//!                 .map(range_of_range_many1)
+//!                 .and(num_body.fold_many1(None, |_, r| Some(r)))
+//!                 .map(range_of_range_optrange)
 //!                 .may(),
 //!         )
 //!         .map(range_of_range_optrange)
@@ -88,15 +89,14 @@
 //!                 .or(one('E'))
 //!                 .and(one('-').or(one('+')).may())
 //!                 .map(range_of_range_optrange)
-//!                 .and(num_body.many1())
-//!                 .map(range_of_range_many1)
+//!                 .and(num_body.fold_many1(None, |_, r| Some(r)))
+//!                 .map(range_of_range_optrange)
 //!                 .may(),
 //!         )
 //!         .map(range_of_range_optrange)
-//!         .map(|v: Range<usize>| {
+//!         .map_opt(|v: Range<usize>| {
 //!             let s: String = input.com_string(v).unwrap();
-//!             let v: f64 = s.parse::<f64>().unwrap();
-//!             JsonVal::Number(v)
+//!             s.parse::<f64>().ok().map(JsonVal::Number)
 //!         })
 //!         .parse(input.ref_clone())
 //! }
@@ -114,18 +114,23 @@
 //!                     .or(one('n').map(|_| '\n'))
 //!                     .or(one('r').map(|_| '\r'))
 //!                     .or(one('t').map(|_| '\t'))
+//!                     .map(Result::<char, JsonParserError>::Ok)
 //!                     .or(one('u')
 //!                         .and(
-//!                             satisfy(|c: Char| c.char(|c: char, _| c.is_digit(16)).unwrap_or(false))
+//!                             satisfy(|c: Char| c.char(|c: char, _| c.is_ascii_hexdigit()).unwrap_or(false))
 //!                                 .some(4),
 //!                         )
-//!                         .map(|(_, u)| {
+//!                         .try_map(|(_, u)| {
 //!                             let s = input.com_string(range_of_many1(u)).unwrap();
-//!                             let hex: u32 = u32::from_str_radix(&*s, 16).unwrap();
-//!                             let c = std::char::from_u32(hex).unwrap();
-//!                             c
+//!                             let hex: u32 = u32::from_str_radix(&s, 16).unwrap();
+//!                             std::char::from_u32(hex).ok_or("Unicode escape is not a char")
+//!                         })
+//!                         .map(|r| {
+//!                             r.map_err(|e| JsonParserError {
+//!                                 loc: input.loc_range(e.range).unwrap(),
+//!                                 msg: e.error.to_string(),
+//!                             })
 //!                         }))
-//!                     .map(|v| Result::<char, JsonParserError>::Ok(v))
 //!                     .or_trans(true,|i: CharSpan, ep| {
 //!                         let loc = i.loc_range(ep).unwrap();
 //!                         Err(JsonParserError {
@@ -176,8 +181,7 @@
 //!
 //! fn whitespace(input: CharSpan) -> Option<()> {
 //!     satisfy(|c: Char| c.is_wrap() || c == ' ' || c == '\t')
-//!         .many()
-//!         .map(|_| {})
+//!         .skip_many()
 //!         .parse(input)
 //! }
 //!
@@ -313,14 +317,22 @@
 //!
 //! ```
 
+#![forbid(unsafe_code)]
+
 pub mod combinators;
 pub mod common;
+pub mod export;
 pub mod utils;
+pub mod peg;
 
 pub use combinators::*;
 pub use common::*;
 pub use utils::*;
 
+#[cfg(feature = "derive")]
+pub use parser_fuck_derive::Parse;
+
+use std::borrow::Cow;
 use std::ops::Range;
 
 /// Abstract Parser with chain call
@@ -330,16 +342,107 @@ pub trait Parser<I: TimeTravel> {
     /// do parse
     fn parse(&self, input: I) -> Option<Self::Output>;
 
+    /// Name for diagnostics, set by [label](#method.label) and [def_parser](macro.def_parser.html)
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// What the parser accepts, for tooling  
+    /// Parsers that can not be looked into, like closures, are [Opaque](enum.GrammarNode.html#variant.Opaque) with their name
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::opaque::<Self>(self.name())
+    }
+
+    /// Give the parser a name for diagnostics
+    #[inline]
+    fn label(self, name: impl Into<Cow<'static, str>>) -> Label<Self, I>
+    where
+        Self: Sized,
+    {
+        Label::new(self, name)
+    }
+
+    /// Record entering and leaving the parser while a [TraceLog](struct.TraceLog.html) runs, also names it
+    #[inline]
+    fn trace(self, name: impl Into<Cow<'static, str>>) -> Trace<Self, I>
+    where
+        Self: Sized,
+    {
+        Trace::new(self, name)
+    }
+
+    /// Count calls, results, rescans and time of the parser while a [Profiler](struct.Profiler.html) runs, also names it
+    #[inline]
+    fn profile(self, name: impl Into<Cow<'static, str>>) -> Profile<Self, I>
+    where
+        Self: Sized,
+    {
+        Profile::new(self, name)
+    }
+
     /// Map a `Parser<Output = T>` to `Parser<Output = U>` by applying a function to a contained value
     #[inline]
     fn map<U, F>(self, f: F) -> Map<Self, I, F>
     where
         Self: Sized,
-        F: FnMut(Self::Output) -> U,
+        F: Fn(Self::Output) -> U,
     {
         Map::new(self, f)
     }
 
+    /// Map with a function that can fail, the error comes with the range the subparser consumed  
+    /// The input stays consumed, use [map_opt](#method.map_opt) to backtrack instead
+    /// ## example
+    /// ```
+    /// # use parser_fuck::*;
+    /// let code = "999".span();
+    /// let x = satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false))
+    ///     .many1()
+    ///     .try_map(|r| code.com_string(range_of_many1(r)).unwrap().parse::<u8>());
+    /// let e = x.parse(code.ref_clone()).unwrap().unwrap_err();
+    /// assert_eq!(e.range, 0..3);
+    /// ```
+    #[inline]
+    fn try_map<U, E, F>(self, f: F) -> TryMap<Self, I, F>
+    where
+        Self: Sized,
+        F: Fn(Self::Output) -> Result<U, E>,
+    {
+        TryMap::new(self, f)
+    }
+
+    /// Map with a function that can reject, fail without consuming if it returns None
+    #[inline]
+    fn map_opt<U, F>(self, f: F) -> MapOpt<Self, I, F>
+    where
+        Self: Sized,
+        F: Fn(Self::Output) -> Option<U>,
+    {
+        MapOpt::new(self, f)
+    }
+
+    /// Fail without consuming if the predicate returns false for the output
+    #[inline]
+    fn filter<F>(self, f: F) -> Filter<Self, I, F>
+    where
+        Self: Sized,
+        F: Fn(&Self::Output) -> bool,
+    {
+        Filter::new(self, f)
+    }
+
+    /// Like [filter](#method.filter), but the predicate also gets the input, like to reject keywords as names
+    #[inline]
+    fn verify<F>(self, f: F) -> Verify<Self, I, F>
+    where
+        Self: Sized,
+        F: Fn(&I, &Self::Output) -> bool,
+    {
+        Verify::new(self, f)
+    }
+
     /// Only pass if both subparsers pass
     #[inline]
     fn and<B>(self, b: B) -> And<Self, B, I>
@@ -432,6 +535,125 @@ pub trait Parser<I: TimeTravel> {
         Many::new(self, count, Some(count))
     }
 
+    /// `*, >= 0` into any collection, see [ManyInto](struct.ManyInto.html)
+    #[inline]
+    fn many_into<C>(self) -> ManyInto<Self, C, I>
+    where
+        Self: Sized,
+        C: Default + Extend<Self::Output>,
+    {
+        ManyInto::new(self, 0, None)
+    }
+
+    /// `+, >= 1` into any collection
+    #[inline]
+    fn many1_into<C>(self) -> ManyInto<Self, C, I>
+    where
+        Self: Sized,
+        C: Default + Extend<Self::Output>,
+    {
+        ManyInto::new(self, 1, None)
+    }
+
+    /// `{n,}, >= n` into any collection
+    #[inline]
+    fn many_min_into<C>(self, min: usize) -> ManyInto<Self, C, I>
+    where
+        Self: Sized,
+        C: Default + Extend<Self::Output>,
+    {
+        ManyInto::new(self, min, None)
+    }
+
+    /// `{,m}, <= m` into any collection
+    #[inline]
+    fn many_max_into<C>(self, max: usize) -> ManyInto<Self, C, I>
+    where
+        Self: Sized,
+        C: Default + Extend<Self::Output>,
+    {
+        ManyInto::new(self, 0, Some(max))
+    }
+
+    /// `{1,m}, >= 1 && <= m` into any collection
+    #[inline]
+    fn many1_max_into<C>(self, max: usize) -> ManyInto<Self, C, I>
+    where
+        Self: Sized,
+        C: Default + Extend<Self::Output>,
+    {
+        ManyInto::new(self, 1, Some(max))
+    }
+
+    /// `{n,m}, >= n && <= m` into any collection
+    #[inline]
+    fn many_min_max_into<C>(self, min: usize, max: usize) -> ManyInto<Self, C, I>
+    where
+        Self: Sized,
+        C: Default + Extend<Self::Output>,
+    {
+        ManyInto::new(self, min, Some(max))
+    }
+
+    /// `{n}, == n` into any collection
+    #[inline]
+    fn some_into<C>(self, count: usize) -> ManyInto<Self, C, I>
+    where
+        Self: Sized,
+        C: Default + Extend<Self::Output>,
+    {
+        ManyInto::new(self, count, Some(count))
+    }
+
+    /// `*, >= 0` and fold the outputs, see [FoldMany](struct.FoldMany.html)
+    #[inline]
+    fn fold_many<T, F>(self, init: T, f: F) -> FoldMany<Self, T, F, I>
+    where
+        Self: Sized,
+        T: Clone,
+        F: Fn(T, Self::Output) -> T,
+    {
+        FoldMany::new(self, init, f, 0, None)
+    }
+
+    /// `+, >= 1` and fold the outputs, see [FoldMany](struct.FoldMany.html)
+    #[inline]
+    fn fold_many1<T, F>(self, init: T, f: F) -> FoldMany<Self, T, F, I>
+    where
+        Self: Sized,
+        T: Clone,
+        F: Fn(T, Self::Output) -> T,
+    {
+        FoldMany::new(self, init, f, 1, None)
+    }
+
+    /// `*, >= 0` and drop the outputs
+    #[inline]
+    fn skip_many(self) -> SkipMany<Self, I>
+    where
+        Self: Sized,
+    {
+        SkipMany::new(self, 0, None)
+    }
+
+    /// `+, >= 1` and drop the outputs
+    #[inline]
+    fn skip_many1(self) -> SkipMany<Self, I>
+    where
+        Self: Sized,
+    {
+        SkipMany::new(self, 1, None)
+    }
+
+    /// `*, >= 0` and count the outputs
+    #[inline]
+    fn count_many(self) -> CountMany<Self, I>
+    where
+        Self: Sized,
+    {
+        CountMany::new(self, 0, None)
+    }
+
     /// `?, 0 or 1`
     #[inline]
     fn may(self) -> May<Self, I>
@@ -441,6 +663,47 @@ pub trait Parser<I: TimeTravel> {
         May::new(self)
     }
 
+    /// Cache the result at each position under a rule name, see [Memo](struct.Memo.html)
+    #[inline]
+    fn memo(self, rule: impl Into<Cow<'static, str>>) -> Memo<Self, I>
+    where
+        Self: Sized,
+        I: Memoizable,
+        Self::Output: Clone + Relocate + 'static,
+    {
+        Memo::new(self, rule)
+    }
+
+    /// Wrap the tokens and nodes in a node of the CST, see [parse_cst](fn.parse_cst.html)
+    #[inline]
+    fn node<K: Copy + 'static>(self, kind: K) -> Node<Self, I, K>
+    where
+        Self: Sized,
+        I: Memoizable,
+    {
+        Node::new(self, kind)
+    }
+
+    /// Mark the consumed items as a token of the CST, see [parse_cst](fn.parse_cst.html)
+    #[inline]
+    fn token<K: Copy + 'static>(self, kind: K) -> Token<Self, I, K>
+    where
+        Self: Sized,
+        I: Memoizable,
+    {
+        Token::new(self, kind, false)
+    }
+
+    /// Like [token](#method.token) but for whitespace and comments
+    #[inline]
+    fn trivia<K: Copy + 'static>(self, kind: K) -> Token<Self, I, K>
+    where
+        Self: Sized,
+        I: Memoizable,
+    {
+        Token::new(self, kind, true)
+    }
+
     /// Continuously parse into iterators
     #[inline]
     fn iter(self) -> Iter<Self, I>
@@ -456,7 +719,7 @@ pub trait Parser<I: TimeTravel> {
     where
         Self: Sized,
         U: Parser<I>,
-        F: FnMut(Self::Output) -> U,
+        F: Fn(Self::Output) -> U,
     {
         AndThen::new(self, f)
     }
@@ -467,40 +730,49 @@ pub trait Parser<I: TimeTravel> {
     where
         Self: Sized,
         U: Parser<I, Output = Self::Output>,
-        F: FnMut() -> U,
+        F: Fn() -> U,
     {
         OrElse::new(self, f)
     }
 
-    /// Pass if subparser pass, otherwise calls f with error point
+    /// Pass if subparser pass, otherwise calls f with error point  
     /// - `no_retry` Stop immediately without trying to get the error range
     #[inline]
     fn or_trans<F>(self, no_retry: bool, f: F) -> OrTrans<Self, I, F>
     where
         Self: Sized,
-        F: FnMut(I, Range<usize>) -> Self::Output,
+        F: Fn(I, Range<usize>) -> Self::Output,
     {
         OrTrans::new(self, false, no_retry, f)
     }
-    /// Pass if subparser pass, otherwise calls f with error point, but ignore EOF
+    /// Pass if subparser pass, otherwise calls f with error point, but ignore EOF  
     /// - `no_retry` Stop immediately without trying to get the error range
     #[inline]
     fn or_trans_noend<F>(self, no_retry: bool, f: F) -> OrTrans<Self, I, F>
     where
         Self: Sized,
-        F: FnMut(I, Range<usize>) -> Self::Output,
+        F: Fn(I, Range<usize>) -> Self::Output,
     {
         OrTrans::new(self, true, no_retry, f)
     }
 
     /// Wrap to dynamic
     #[inline]
-    fn dyns(self) -> Dyn<I, Self::Output>
+    fn dyns<'a>(self) -> Dyn<'a, I, Self::Output>
     where
-        Self: Sized + 'static,
+        Self: Sized + 'a,
     {
         Dyn::new(self)
     }
+
+    /// Wrap to dynamic that can be shared between threads
+    #[inline]
+    fn sync_dyns(self) -> SyncDyn<I, Self::Output>
+    where
+        Self: Sized + Send + Sync + 'static,
+    {
+        SyncDyn::new(self)
+    }
 }
 
 impl<I, U, F> Parser<I> for F
@@ -519,48 +791,143 @@ where
 #[macro_export]
 #[doc(hidden)]
 macro_rules! _def_parser_I_select {
- { } => { I };
- { $id:ident } => { $id };
+    { [$it:ty] $($I:ident)? } => { $it };
+    { [] $I:ident } => { $I };
 }
 
-/// ***Unfinished***  
 /// Define a Parser with data like a function  
-#[macro_export(local_inner_macros)]
+/// The struct is named like the function and implements `Parser`, its [name](trait.Parser.html#method.name) is the struct name  
+/// - `name(input: Type) -> Output { .. }` parse a concrete input type, lifetimes go after the name like `name<'a>`  
+/// - `name[I](input) -> Output where .. { .. }` parse any input `I: TimeTravel`, more bounds go in the where clause  
+/// - `name { field: Type, .. } (input: Type) -> Output { .. }` with configuration fields, the body gets them as references  
+///
+/// The input needs a type or `[I]`  
+/// Doc comments and attributes are kept, `new` takes the fields in order  
+/// It [describes](trait.Parser.html#method.describe) as a reference to its name, the body can not be looked into  
+/// Only `Debug` and `Clone` are derived, so the fields need them, add `#[derive(PartialEq, Eq)]` if needed
+/// ## example
+/// ```
+/// # use parser_fuck::*;
+/// use std::ops::Range;
+///
+/// def_parser! {
+///     /// A keyword not followed by a letter
+///     pub keyword[I] { word: Vec<char> } (input) -> Range<usize>
+///     where
+///         I: Memoizable,
+///         I::Item: PartialEq<char> + Into<char>,
+///     {
+///         let word = Sub::new(word.clone());
+///         let letter = satisfy(|c: I::Item| c.into().is_alphabetic());
+///         word.and(letter.not()).map(|(r, _)| r).parse(input)
+///     }
+/// }
+/// let x = keyword::new("let".chars().collect());
+/// assert_eq!(x.name(), Some("keyword"));
+/// assert_eq!(x.parse("let x".span()), Some(0..3));
+/// assert_eq!(x.parse("letter".span()), None);
+/// ```
+#[macro_export]
 macro_rules! def_parser {
     { } => { };
-    { $lt:lifetime } => { std::marker::PhantomData };
-    { $vis:vis $name:ident [$I:path] ($input:ident) -> $output:ty $(where $($tn:path: $wt:path),+)? $b:block } => {
-        #[allow(non_camel_case_types)]
-        #[derive(Debug, PartialEq, Eq, Clone)]
-        $vis struct $name;
-        impl<$I: $crate::parser_fuck::TimeTravel> Parser<I> for $name
-        $(where $($tn : $wt),+)?
-        {
-            type Output = $output;
-
-            #[allow(unused_mut)]
-            fn parse(&self, mut $input: I) -> Option<Self::Output> $b
+    {
+        $(#[$attr:meta])*
+        $vis:vis $name:ident $(<$($lt:lifetime),+ $(,)?>)? $([$I:ident])?
+        $({ $($fvis:vis $field:ident : $fty:ty),* $(,)? })?
+        ($input:ident $(: $it:ty)?) -> $output:ty
+        $b:block
+    } => {
+        $crate::def_parser! {
+            @def [$(#[$attr])*] [$vis $name] [$($($lt),+)?] [$($I)?]
+            [$({ $($fvis $field: $fty),* })?] [$input] [$($it)?] [$output] [] $b
         }
     };
-    { $vis:vis $name:ident $(<$($lt:lifetime),*>)? ($input:ident: $it:ty ) -> $output:ty $b:block } => {
+    {
+        $(#[$attr:meta])*
+        $vis:vis $name:ident $(<$($lt:lifetime),+ $(,)?>)? $([$I:ident])?
+        $({ $($fvis:vis $field:ident : $fty:ty),* $(,)? })?
+        ($input:ident $(: $it:ty)?) -> $output:ty
+        where $($rest:tt)+
+    } => {
+        $crate::def_parser! {
+            @where [
+                [$(#[$attr])*] [$vis $name] [$($($lt),+)?] [$($I)?]
+                [$({ $($fvis $field: $fty),* })?] [$input] [$($it)?] [$output]
+            ] [] $($rest)+
+        }
+    };
+    // split the where clause from the body, the body is the last token
+    { @where [$($head:tt)*] [$($wc:tt)*] $b:block } => {
+        $crate::def_parser! { @def $($head)* [$($wc)*] $b }
+    };
+    { @where [$($head:tt)*] [$($wc:tt)*] $t:tt $($rest:tt)+ } => {
+        $crate::def_parser! { @where [$($head)*] [$($wc)* $t] $($rest)+ }
+    };
+    {
+        @def [$($attr:tt)*] [$vis:vis $name:ident] [$($lt:lifetime),*] []
+        [$($fields:tt)*] [$input:ident] [] $($rest:tt)*
+    } => {
+        ::std::compile_error!(::std::concat!(
+            "def_parser! needs the input type, write `",
+            ::std::stringify!($name),
+            "(input: Type)` or `",
+            ::std::stringify!($name),
+            "[I](input)`"
+        ));
+    };
+    {
+        @def [$(#[$attr:meta])*] [$vis:vis $name:ident] [$($lt:lifetime),*] [$($I:ident)?]
+        [$({ $($fvis:vis $field:ident : $fty:ty),* })?] [$input:ident] [$($it:ty)?] [$output:ty]
+        [$($wc:tt)*] $b:block
+    } => {
+        $(#[$attr])*
         #[allow(non_camel_case_types)]
-        #[derive(Debug, PartialEq, Eq, Clone)]
-        $vis struct $name$(<$($lt),+>)? ( $($(std::marker::PhantomData<&$lt ()>),*)? );
-        impl$(<$($lt),*>)? $name $(<$($lt),*>)? {
+        #[derive(Debug, Clone)]
+        $vis struct $name<$($lt,)* $($I)?> {
+            $($($fvis $field: $fty,)*)?
+            _p: ::std::marker::PhantomData<($(&$lt (),)* $(fn($I),)?)>,
+        }
+        impl<$($lt,)* $($I)?> $name<$($lt,)* $($I)?> {
             #[inline]
-            pub fn new() -> Self {
-                Self ( $($(def_parser!($lt)),*)? )
+            #[allow(clippy::new_without_default, clippy::too_many_arguments)]
+            pub fn new($($($field: $fty),*)?) -> Self {
+                Self {
+                    $($($field,)*)?
+                    _p: ::std::marker::PhantomData,
+                }
             }
         }
-        impl$(<$($lt),*>)? Parser<$it> for $name $(<$($lt),*>)?
+        impl<$($lt,)* $($I)?> $crate::Parser<$crate::_def_parser_I_select!([$($it)?] $($I)?)>
+            for $name<$($lt,)* $($I)?>
+        where
+            $($I: $crate::TimeTravel,)?
+            $($wc)*
         {
             type Output = $output;
 
-            #[allow(unused_mut)]
-            fn parse(&self, mut $input: $it) -> Option<Self::Output> $b
+            #[allow(unused_mut, unused_variables)]
+            fn parse(
+                &self,
+                mut $input: $crate::_def_parser_I_select!([$($it)?] $($I)?),
+            ) -> ::std::option::Option<Self::Output> {
+                $(let Self { $($field,)* .. } = self;)?
+                $b
+            }
+            #[inline]
+            fn name(&self) -> ::std::option::Option<&str> {
+                ::std::option::Option::Some(::std::stringify!($name))
+            }
+            #[inline]
+            fn describe(&self) -> $crate::GrammarNode {
+                $crate::GrammarNode::Ref(::std::string::ToString::to_string(
+                    ::std::stringify!($name),
+                ))
+            }
         }
     };
 }
 
+#[cfg(test)]
+mod test_def_parser;
 #[cfg(test)]
 mod test_json;
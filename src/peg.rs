@@ -0,0 +1,816 @@
+//! Compile PEG grammars to parsers at runtime, for small grammars in configuration files
+//! ## syntax
+//! ```text
+//! rule  <- expr          also `=` or `::=`, an optional `;` ends a rule
+//! a b                    sequence
+//! a / b                  ordered choice, also `|`
+//! a* a+ a?               repeat, at least once, optional
+//! &a !a                  pass if a passes or fails, without consuming
+//! ( a )                  group
+//! "lit" 'lit'            literal, with escapes \n \r \t \\ \" \' \[ \] \-
+//! [a-z_] [^"]            char class
+//! .                      any char
+//! # comment              to the end of the line
+//! ```
+//! ## example
+//! ```
+//! # use parser_fuck::*;
+//! use parser_fuck::peg::Grammar;
+//!
+//! let grammar = Grammar::new(r#"
+//!     list  <- "(" _ (item _)* ")"
+//!     item  <- list / atom
+//!     atom  <- [a-z0-9]+
+//!     _     <- [ \t]*
+//! "#).unwrap();
+//! let list = grammar.compile("list").unwrap();
+//!
+//! let code = "(a (b c) 1)".span();
+//! let tree = list.parse(code.ref_clone()).unwrap();
+//! assert_eq!(tree.rule.as_ref(), "list");
+//! assert_eq!(tree.range, 0..11);
+//! let items: Vec<_> = tree.children.iter().filter(|n| n.rule.as_ref() == "item").collect();
+//! assert_eq!(items.len(), 3);
+//! assert_eq!(code.com_string(items[1].range.clone()), Some("(b c)".to_string()));
+//!
+//! let e = Grammar::new("a <- b / a\nb <- c").unwrap_err();
+//! assert_eq!(e[0].to_string(), "undefined rule `c` at 2:6");
+//! ```
+
+use crate::common::cursor;
+use crate::*;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+
+/// A node of the parse tree, one for each rule that passed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Node {
+    pub rule: Rc<str>,
+    pub range: Range<usize>,
+    pub loc: LocRange,
+    /// Nodes of the rules called inside
+    pub children: Vec<Node>,
+}
+
+/// What is wrong with a grammar
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GrammarErrorKind {
+    /// The text is not a grammar
+    Syntax,
+    Undefined(String),
+    Duplicate(String),
+    /// The rule calls itself before consuming anything, the path of the calls
+    LeftRecursive(Vec<String>),
+    /// A repeat of something that can pass without consuming, it would never end
+    EmptyLoop,
+}
+
+/// An error in the grammar text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GrammarError {
+    pub kind: GrammarErrorKind,
+    pub loc: LocRange,
+}
+impl Display for GrammarError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.kind {
+            GrammarErrorKind::Syntax => write!(f, "syntax error")?,
+            GrammarErrorKind::Undefined(name) => write!(f, "undefined rule `{}`", name)?,
+            GrammarErrorKind::Duplicate(name) => write!(f, "rule `{}` is defined twice", name)?,
+            GrammarErrorKind::LeftRecursive(path) => {
+                write!(f, "left recursive rule `{}`", path.join(" -> "))?
+            }
+            GrammarErrorKind::EmptyLoop => write!(f, "repeat of an expression that can be empty")?,
+        }
+        write!(
+            f,
+            " at {}:{}",
+            self.loc.from.line + 1,
+            self.loc.from.col + 1
+        )
+    }
+}
+impl Error for GrammarError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Rule(String, LocRange),
+    Literal(String),
+    /// Inclusive char ranges, negated
+    Class(Vec<(char, char)>, bool),
+    Any,
+    Seq(Vec<Expr>),
+    Choice(Vec<Expr>),
+    /// min 0 or 1, and the loc of the operator
+    Many(Box<Expr>, usize, LocRange),
+    May(Box<Expr>),
+    And(Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RuleDef {
+    name: String,
+    loc: LocRange,
+    expr: Expr,
+}
+
+/// A checked PEG grammar, see the [module docs](index.html)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grammar {
+    rules: Vec<RuleDef>,
+    index: HashMap<String, usize>,
+}
+impl Grammar {
+    /// Parse and check a grammar, all errors are reported
+    pub fn new(text: &str) -> Result<Self, Vec<GrammarError>> {
+        let span = text.span();
+        let r = syntax::grammar.parse(span.ref_clone());
+        let end = span.ref_clone().get(span.consumed_end()).is_none();
+        let rules = match r {
+            Some(rules) if end => rules,
+            _ => {
+                // the furthest char read, or the last one if that is the end
+                let mut at = span.peak().max(1) - 1;
+                while at > 0 && span.loc(at).is_none() {
+                    at -= 1;
+                }
+                let loc = span.loc_range(at..at + 1).unwrap_or_default();
+                return Err(vec![GrammarError {
+                    kind: GrammarErrorKind::Syntax,
+                    loc,
+                }]);
+            }
+        };
+        let mut errors = vec![];
+        let mut index = HashMap::new();
+        for (i, rule) in rules.iter().enumerate() {
+            if index.contains_key(&rule.name) {
+                errors.push(GrammarError {
+                    kind: GrammarErrorKind::Duplicate(rule.name.clone()),
+                    loc: rule.loc,
+                });
+            } else {
+                index.insert(rule.name.clone(), i);
+            }
+        }
+        let grammar = Self { rules, index };
+        grammar.check(&mut errors);
+        if errors.is_empty() {
+            Ok(grammar)
+        } else {
+            Err(errors)
+        }
+    }
+    /// Names of the rules in order
+    pub fn rules(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|r| r.name.as_str())
+    }
+    /// Build a parser starting at the rule, None if there is no such rule  
+    /// Rules call each other through weak references, dropping the parser frees all of them
+    pub fn compile<'a>(&self, start: &str) -> Option<Dyn<'a, CharSpan<'a>, Node>> {
+        let start = *self.index.get(start)?;
+        Some(self.start(start).dyns())
+    }
+    fn start<'a>(&self, index: usize) -> Start<'a> {
+        let table = Rc::new(Table {
+            rules: self.rules.iter().map(|_| OnceCell::new()).collect(),
+        });
+        let weak = Rc::downgrade(&table);
+        for (cell, rule) in table.rules.iter().zip(&self.rules) {
+            let body = self.compile_expr(&rule.expr, &weak);
+            let rule = Rule {
+                name: rule.name.as_str().into(),
+                body,
+            };
+            let _ = cell.set(rule.dyns());
+        }
+        Start { table, index }
+    }
+
+    fn compile_expr<'a>(&self, expr: &Expr, table: &Weak<Table<'a>>) -> Children<'a> {
+        fn none(_: Range<usize>) -> Vec<Node> {
+            vec![]
+        }
+        match expr {
+            Expr::Rule(name, _) => RuleRef {
+                table: table.clone(),
+                index: self.index[name],
+            }
+            .map(|n| vec![n])
+            .dyns(),
+            Expr::Literal(s) => substr(s).map(none).dyns(),
+            Expr::Class(ranges, negated) => CharClass {
+                ranges: ranges.clone(),
+                negated: *negated,
+            }
+            .dyns(),
+            Expr::Any => CharClass {
+                ranges: vec![],
+                negated: true,
+            }
+            .dyns(),
+            Expr::Seq(items) => items
+                .iter()
+                .map(|e| self.compile_expr(e, table))
+                .reduce(|a, b| {
+                    a.and(b)
+                        .map(|(mut a, b)| {
+                            a.extend(b);
+                            a
+                        })
+                        .dyns()
+                })
+                .unwrap_or_else(|| (|_: CharSpan<'a>| Some(vec![])).dyns()),
+            Expr::Choice(items) => items
+                .iter()
+                .map(|e| self.compile_expr(e, table))
+                .reduce(|a, b| a.or(b).dyns())
+                .unwrap(),
+            Expr::Many(e, min, _) => self
+                .compile_expr(e, table)
+                .many_min(*min)
+                .map(|v| v.into_iter().flatten().collect())
+                .dyns(),
+            Expr::May(e) => self
+                .compile_expr(e, table)
+                .may()
+                .map(Option::unwrap_or_default)
+                .dyns(),
+            Expr::And(e) => self
+                .compile_expr(e, table)
+                .not()
+                .not()
+                .map(|_| vec![])
+                .dyns(),
+            Expr::Not(e) => self.compile_expr(e, table).not().map(|_| vec![]).dyns(),
+        }
+    }
+
+    fn check(&self, errors: &mut Vec<GrammarError>) {
+        let mut undefined = vec![];
+        for rule in &self.rules {
+            self.visit(&rule.expr, &mut |e| {
+                if let Expr::Rule(name, loc) = e {
+                    if !self.index.contains_key(name) {
+                        undefined.push(GrammarError {
+                            kind: GrammarErrorKind::Undefined(name.clone()),
+                            loc: *loc,
+                        });
+                    }
+                }
+            });
+        }
+        if !undefined.is_empty() {
+            // the other checks need all rules
+            errors.extend(undefined);
+            return;
+        }
+
+        // which rules can pass without consuming, until nothing changes
+        let mut nullable = vec![false; self.rules.len()];
+        loop {
+            let next: Vec<bool> = self
+                .rules
+                .iter()
+                .map(|r| self.nullable(&r.expr, &nullable))
+                .collect();
+            if next == nullable {
+                break;
+            }
+            nullable = next;
+        }
+        for rule in &self.rules {
+            self.visit(&rule.expr, &mut |e| {
+                if let Expr::Many(e, _, loc) = e {
+                    if self.nullable(e, &nullable) {
+                        errors.push(GrammarError {
+                            kind: GrammarErrorKind::EmptyLoop,
+                            loc: *loc,
+                        });
+                    }
+                }
+            });
+        }
+
+        // rules called before anything is consumed
+        let calls: Vec<Vec<usize>> = self
+            .rules
+            .iter()
+            .map(|r| {
+                let mut calls = vec![];
+                self.left_calls(&r.expr, &nullable, &mut calls);
+                calls
+            })
+            .collect();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if self.index[&rule.name] != i {
+                continue;
+            }
+            if let Some(path) = find_path(&calls, i) {
+                let path = path.iter().map(|&r| self.rules[r].name.clone()).collect();
+                errors.push(GrammarError {
+                    kind: GrammarErrorKind::LeftRecursive(path),
+                    loc: rule.loc,
+                });
+            }
+        }
+    }
+
+    fn visit(&self, expr: &Expr, f: &mut impl FnMut(&Expr)) {
+        f(expr);
+        match expr {
+            Expr::Seq(items) | Expr::Choice(items) => {
+                items.iter().for_each(|e| self.visit(e, f));
+            }
+            Expr::Many(e, _, _) | Expr::May(e) | Expr::And(e) | Expr::Not(e) => self.visit(e, f),
+            _ => {}
+        }
+    }
+
+    fn nullable(&self, expr: &Expr, rules: &[bool]) -> bool {
+        match expr {
+            Expr::Rule(name, _) => rules[self.index[name]],
+            Expr::Literal(s) => s.is_empty(),
+            Expr::Class(..) | Expr::Any => false,
+            Expr::Seq(items) => items.iter().all(|e| self.nullable(e, rules)),
+            Expr::Choice(items) => items.iter().any(|e| self.nullable(e, rules)),
+            Expr::Many(e, min, _) => *min == 0 || self.nullable(e, rules),
+            Expr::May(_) | Expr::And(_) | Expr::Not(_) => true,
+        }
+    }
+
+    fn left_calls(&self, expr: &Expr, nullable: &[bool], calls: &mut Vec<usize>) {
+        match expr {
+            Expr::Rule(name, _) => calls.push(self.index[name]),
+            Expr::Seq(items) => {
+                for e in items {
+                    self.left_calls(e, nullable, calls);
+                    if !self.nullable(e, nullable) {
+                        break;
+                    }
+                }
+            }
+            Expr::Choice(items) => {
+                items
+                    .iter()
+                    .for_each(|e| self.left_calls(e, nullable, calls));
+            }
+            Expr::Many(e, _, _) | Expr::May(e) | Expr::And(e) | Expr::Not(e) => {
+                self.left_calls(e, nullable, calls)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Shortest path of calls from the rule back to itself
+fn find_path(calls: &[Vec<usize>], from: usize) -> Option<Vec<usize>> {
+    let mut prev = vec![None; calls.len()];
+    let mut queue = std::collections::VecDeque::new();
+    queue.push_back(from);
+    while let Some(r) = queue.pop_front() {
+        for &next in &calls[r] {
+            if next == from {
+                let mut path = vec![from];
+                let mut at = r;
+                while at != from {
+                    path.push(at);
+                    at = prev[at].unwrap();
+                }
+                path.push(from);
+                let n = path.len();
+                path[1..n - 1].reverse();
+                return Some(path);
+            }
+            if prev[next].is_none() {
+                prev[next] = Some(r);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+type Children<'a> = Dyn<'a, CharSpan<'a>, Vec<Node>>;
+
+struct Table<'a> {
+    rules: Vec<OnceCell<Dyn<'a, CharSpan<'a>, Node>>>,
+}
+
+/// A call of a rule, only a weak reference so rules calling each other are no cycle
+struct RuleRef<'a> {
+    table: Weak<Table<'a>>,
+    index: usize,
+}
+impl<'a> Parser<CharSpan<'a>> for RuleRef<'a> {
+    type Output = Node;
+
+    fn parse(&self, input: CharSpan<'a>) -> Option<Self::Output> {
+        let table = self
+            .table
+            .upgrade()
+            .expect("peg parser used after it was dropped");
+        table.rules[self.index].get().unwrap().parse(input)
+    }
+    fn describe(&self) -> GrammarNode {
+        let table = self
+            .table
+            .upgrade()
+            .expect("peg parser used after it was dropped");
+        let name = table.rules[self.index].get().unwrap().name();
+        GrammarNode::Ref(name.unwrap_or_default().to_string())
+    }
+}
+
+/// The compiled parser, keeps all rules alive
+struct Start<'a> {
+    table: Rc<Table<'a>>,
+    index: usize,
+}
+impl<'a> Parser<CharSpan<'a>> for Start<'a> {
+    type Output = Node;
+
+    #[inline]
+    fn parse(&self, input: CharSpan<'a>) -> Option<Self::Output> {
+        self.table.rules[self.index].get().unwrap().parse(input)
+    }
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        self.table.rules[self.index].get().unwrap().name()
+    }
+    #[inline]
+    fn describe(&self) -> GrammarNode {
+        self.table.rules[self.index].get().unwrap().describe()
+    }
+}
+
+/// A rule, building a node from what its body parsed
+struct Rule<'a> {
+    name: Rc<str>,
+    body: Children<'a>,
+}
+impl<'a> Parser<CharSpan<'a>> for Rule<'a> {
+    type Output = Node;
+
+    fn parse(&self, input: CharSpan<'a>) -> Option<Self::Output> {
+        let start = cursor::now(&input);
+        let children = self.body.parse(input.ref_clone())?;
+        Some(Node {
+            rule: self.name.clone(),
+            range: start..input.consumed_end(),
+            loc: cursor::loc(&input, start),
+            children,
+        })
+    }
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+    fn describe(&self) -> GrammarNode {
+        GrammarNode::Label {
+            name: self.name.to_string(),
+            node: Box::new(self.body.describe()),
+        }
+    }
+}
+
+/// One char in or out of the ranges, no ranges and negated is any char
+struct CharClass {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+impl<'a> Parser<CharSpan<'a>> for CharClass {
+    type Output = Vec<Node>;
+
+    fn parse(&self, input: CharSpan<'a>) -> Option<Self::Output> {
+        satisfy(|c: Char| {
+            let c = c.c();
+            self.ranges.iter().any(|&(a, b)| a <= c && c <= b) != self.negated
+        })
+        .map(|_| vec![])
+        .parse(input)
+    }
+    /// As written in the grammar
+    fn describe(&self) -> GrammarNode {
+        if self.ranges.is_empty() && self.negated {
+            return GrammarNode::Class(".".to_string());
+        }
+        fn escape(c: char) -> String {
+            match c {
+                '\n' => "\\n".to_string(),
+                '\r' => "\\r".to_string(),
+                '\t' => "\\t".to_string(),
+                '\\' | ']' | '-' | '^' => format!("\\{}", c),
+                c => c.to_string(),
+            }
+        }
+        let mut s = String::from(if self.negated { "[^" } else { "[" });
+        for &(a, b) in &self.ranges {
+            s.push_str(&escape(a));
+            if a != b {
+                s.push('-');
+                s.push_str(&escape(b));
+            }
+        }
+        s.push(']');
+        GrammarNode::Class(s)
+    }
+}
+
+/// The grammar of grammars
+mod syntax {
+    use super::*;
+
+    fn any_char(mut input: CharSpan) -> Option<char> {
+        let c = input.next()?;
+        input.do_ready();
+        Some(c.c())
+    }
+    fn is<'a>(f: impl Fn(char) -> bool) -> impl Parser<CharSpan<'a>, Output = char> {
+        any_char.filter(move |c| f(*c))
+    }
+    fn loc(input: &CharSpan, range: Range<usize>) -> LocRange {
+        input.loc_range(range).unwrap_or_default()
+    }
+
+    /// Whitespace and comments
+    fn skip(input: CharSpan) -> Option<()> {
+        is(char::is_whitespace)
+            .map(|_| ())
+            .or(one('#')
+                .and(satisfy(|c: Char| c.is_char()).skip_many())
+                .map(|_| ()))
+            .skip_many()
+            .parse(input)
+    }
+    fn name(input: CharSpan) -> Option<(String, LocRange)> {
+        let word = |c: Char| c.char(|c, _| c.is_alphanumeric() || c == '_' || c == '-');
+        skip.and(satisfy(|c: Char| {
+            c.char(|c, _| c.is_alphabetic() || c == '_')
+                .unwrap_or(false)
+        }))
+        .and(satisfy(move |c: Char| word(c).unwrap_or(false)).many())
+        .map(|((_, a), w)| {
+            let r = a.start..w.last().map(|w| w.end).unwrap_or(a.end);
+            (input.com_string(r.clone()).unwrap(), loc(&input, r))
+        })
+        .parse(input.ref_clone())
+    }
+    fn arrow(input: CharSpan) -> Option<()> {
+        skip.and(substr("<-").or(substr("::=")).or(substr("=")))
+            .map(|_| ())
+            .parse(input)
+    }
+    fn escape(input: CharSpan) -> Option<char> {
+        one('\\')
+            .and(any_char)
+            .map_opt(|(_, c)| match c {
+                'n' => Some('\n'),
+                'r' => Some('\r'),
+                't' => Some('\t'),
+                '\\' | '"' | '\'' | '[' | ']' | '-' | '^' => Some(c),
+                _ => None,
+            })
+            .parse(input)
+    }
+    fn literal(input: CharSpan) -> Option<Expr> {
+        let quoted = |q: char| {
+            one(q)
+                .and(escape.or(is(move |c| c != q && c != '\\')).many())
+                .and(one(q))
+                .map(|((_, s), _)| Expr::Literal(s.into_iter().collect()))
+        };
+        quoted('"').or(quoted('\'')).parse(input)
+    }
+    fn class(input: CharSpan) -> Option<Expr> {
+        let item = || escape.or(is(|c| c != ']' && c != '\\'));
+        one('[')
+            .and(one('^').may())
+            .and(
+                item()
+                    .and(one('-').and(item()).map(|(_, c)| c).may())
+                    .map(|(a, b)| (a, b.unwrap_or(a)))
+                    .many(),
+            )
+            .and(one(']'))
+            .map(|(((_, neg), ranges), _)| Expr::Class(ranges, neg.is_some()))
+            .parse(input)
+    }
+    fn primary(input: CharSpan) -> Option<Expr> {
+        let rule = name.and(arrow.not()).map(|((n, l), _)| Expr::Rule(n, l));
+        let group = one('(')
+            .and(expr)
+            .and(skip.and(one(')')))
+            .map(|((_, e), _)| e);
+        skip.and(
+            rule.or(group)
+                .or(literal)
+                .or(class)
+                .or(one('.').map(|_| Expr::Any)),
+        )
+        .map(|(_, e)| e)
+        .parse(input)
+    }
+    fn suffix(input: CharSpan) -> Option<Expr> {
+        primary
+            .and(skip.and(one('*').or(one('+')).or(one('?'))).may())
+            .map(|(e, op)| match op {
+                None => e,
+                Some((_, r)) => match input.com_string(r.clone()).unwrap().as_str() {
+                    "*" => Expr::Many(Box::new(e), 0, loc(&input, r)),
+                    "+" => Expr::Many(Box::new(e), 1, loc(&input, r)),
+                    _ => Expr::May(Box::new(e)),
+                },
+            })
+            .parse(input.ref_clone())
+    }
+    fn prefix(input: CharSpan) -> Option<Expr> {
+        skip.and(one('&').map(|_| true).or(one('!').map(|_| false)).may())
+            .and(suffix)
+            .map(|((_, p), e)| match p {
+                None => e,
+                Some(true) => Expr::And(Box::new(e)),
+                Some(false) => Expr::Not(Box::new(e)),
+            })
+            .parse(input)
+    }
+    fn seq(input: CharSpan) -> Option<Expr> {
+        prefix
+            .many()
+            .map(|mut v| {
+                if v.len() == 1 {
+                    v.pop().unwrap()
+                } else {
+                    Expr::Seq(v)
+                }
+            })
+            .parse(input)
+    }
+    pub(super) fn expr(input: CharSpan) -> Option<Expr> {
+        seq.and(
+            skip.and(one('/').or(one('|')))
+                .and(seq)
+                .map(|(_, e)| e)
+                .many(),
+        )
+        .map(|(first, mut rest)| {
+            if rest.is_empty() {
+                first
+            } else {
+                rest.insert(0, first);
+                Expr::Choice(rest)
+            }
+        })
+        .parse(input)
+    }
+    fn rule(input: CharSpan) -> Option<RuleDef> {
+        name.and(arrow)
+            .and(expr)
+            .and(skip.and(one(';')).may())
+            .map(|((((name, loc), _), expr), _)| RuleDef { name, loc, expr })
+            .parse(input)
+    }
+    pub(super) fn grammar(input: CharSpan) -> Option<Vec<RuleDef>> {
+        rule.many1().and(skip).map(|(rules, _)| rules).parse(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tree() {
+        let grammar = Grammar::new(
+            r#"
+            # arithmetic
+            sum     <- product (("+" / "-") product)*
+            product <- value (("*" / "/") value)*
+            value   <- num / "(" sum ")"
+            num     <- [0-9]+ !"."
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.rules().collect::<Vec<_>>(),
+            vec!["sum", "product", "value", "num"]
+        );
+        let sum = grammar.compile("sum").unwrap();
+        assert_eq!(sum.name(), Some("sum"));
+
+        let code = "1+2*(3-4)".span();
+        let tree = sum.parse(code.ref_clone()).unwrap();
+        println!("{:#?}", tree);
+        fn show(n: &Node) -> String {
+            let children: Vec<_> = n.children.iter().map(show).collect();
+            format!("{}[{}]", n.rule, children.join(" "))
+        }
+        assert_eq!(
+            show(&tree),
+            "sum[product[value[num[]]] product[value[num[]] value[sum[product[value[num[]]] product[value[num[]]]]]]]"
+        );
+        assert_eq!(tree.range, 0..9);
+        assert_eq!(tree.children[1].range, 2..9);
+        assert_eq!(tree.children[1].loc.from.offset, 2);
+
+        assert_eq!(sum.parse("1.5".span()), None);
+
+        let grammar = Grammar::new(r#"word <- &"ab" !"ax" "abc""#).unwrap();
+        let word = grammar.compile("word").unwrap();
+        assert_eq!(word.parse("abc".span()).map(|n| n.range), Some(0..3));
+    }
+
+    #[test]
+    fn test_syntax() {
+        let grammar =
+            Grammar::new(r#"a = 'it''s' / "\"" [^\]\-a-c] | . ; b ::= &a !"x" a? ("," b)?"#);
+        let grammar = match grammar {
+            Ok(g) => g,
+            Err(e) => panic!("{}", e[0]),
+        };
+        let b = grammar.compile("b").unwrap();
+        assert_eq!(b.parse("\"d".span()).map(|n| n.range), Some(0..2));
+        assert_eq!(b.parse("\"-".span()).map(|n| n.range), Some(0..1));
+        assert_eq!(b.parse("x".span()), None);
+        assert!(grammar.compile("c").is_none());
+
+        let e = Grammar::new("a <- 'x\nb <- a").unwrap_err();
+        assert_eq!(e[0].kind, GrammarErrorKind::Syntax);
+        let e = Grammar::new("a <- 'x' (").unwrap_err();
+        assert_eq!(e[0].to_string(), "syntax error at 1:10");
+    }
+
+    #[test]
+    fn test_errors() {
+        let e = Grammar::new("a <- b c\nb <- 'b'\nb <- 'c'").unwrap_err();
+        let e: Vec<_> = e.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            e,
+            vec![
+                "rule `b` is defined twice at 3:1",
+                "undefined rule `c` at 1:8"
+            ]
+        );
+
+        let e = Grammar::new("a <- b? 'x' / 'y'\nb <- 'z'* a\nc <- (!'x')*").unwrap_err();
+        let e: Vec<_> = e.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            e,
+            vec![
+                "repeat of an expression that can be empty at 3:12",
+                "left recursive rule `a -> b -> a` at 1:1",
+                "left recursive rule `b -> a -> b` at 2:1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        let grammar = Grammar::new(r#"a <- "x" b* / &. [^\]a-c] ; b <- !a 'y'"#).unwrap();
+        let a = grammar.compile("a").unwrap();
+        let r = a.describe();
+        println!("{:?}", r);
+        let label = |name: &str, node| GrammarNode::Label {
+            name: name.to_string(),
+            node: Box::new(node),
+        };
+        assert_eq!(
+            r,
+            label(
+                "a",
+                GrammarNode::Choice(vec![
+                    GrammarNode::Seq(vec![
+                        GrammarNode::Literal("x".to_string()),
+                        GrammarNode::Repeat {
+                            node: Box::new(GrammarNode::Ref("b".to_string())),
+                            min: 0,
+                            max: None,
+                        },
+                    ]),
+                    GrammarNode::Seq(vec![
+                        GrammarNode::Not(Box::new(GrammarNode::Not(Box::new(GrammarNode::Class(
+                            ".".to_string()
+                        ))))),
+                        GrammarNode::Class("[^\\]a-c]".to_string()),
+                    ]),
+                ])
+            )
+        );
+    }
+
+    #[test]
+    fn test_drop() {
+        let grammar = Grammar::new("a <- 'x' a / 'y'").unwrap();
+        let a = grammar.start(0);
+        let weak = Rc::downgrade(&a.table);
+        assert_eq!(a.parse("xxy".span()).map(|n| n.children.len()), Some(1));
+        drop(a);
+        assert!(weak.upgrade().is_none());
+    }
+}
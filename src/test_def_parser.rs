@@ -0,0 +1,84 @@
+use crate::*;
+use std::ops::Range;
+
+def_parser! {
+    digit<'a>(input: CharSpan<'a>) -> Range<usize> {
+        satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false)).parse(input)
+    }
+}
+
+def_parser! {
+    /// A run of letters
+    pub word<'a>(input: CharSpan<'a>) -> String {
+        satisfy(|c: Char| c.char(|c, _| c.is_alphabetic()).unwrap_or(false))
+            .many1()
+            .map(|r| input.com_string(range_of_many1(r)).unwrap())
+            .parse(input.ref_clone())
+    }
+}
+
+def_parser! {
+    any_item[I](input) -> I::Item {
+        let r = input.next()?;
+        input.do_ready();
+        Some(r)
+    }
+}
+
+def_parser! {
+    #[derive(PartialEq, Eq)]
+    pub(crate) repeat_of<'a> { pub item: &'a str, times: usize } (input: CharSpan<'a>) -> Vec<Range<usize>> {
+        substr(item).some(*times).parse(input)
+    }
+}
+
+def_parser! {
+    between[I] { open: char, close: char, } (input) -> Range<usize>
+    where
+        I: Memoizable,
+        I::Item: PartialEq<char>,
+    {
+        one(*open)
+            .and(satisfy(|c: I::Item| c != *close).many())
+            .and(one(*close))
+            .map(|((a, _), b)| a.start..b.end)
+            .parse(input)
+    }
+}
+
+#[test]
+fn test_unit() {
+    let r = digit::new().and(digit::new()).parse("12".span());
+    println!("{:?}", r);
+    assert_eq!(r, Some((0..1, 1..2)));
+    assert_eq!(digit::new().name(), Some("digit"));
+    assert_eq!(
+        digit::new().describe(),
+        GrammarNode::Ref("digit".to_string())
+    );
+
+    let r = word::new().parse("asd1".span());
+    assert_eq!(r, Some("asd".to_string()));
+}
+
+#[test]
+fn test_generic() {
+    let span = Span::new(vec![1u8, 2].into_iter());
+    let r = any_item::new().many().parse(span);
+    assert_eq!(r, Some(vec![1, 2]));
+    assert_eq!(any_item::<CharSpan>::new().name(), Some("any_item"));
+}
+
+#[test]
+fn test_fields() {
+    let x = repeat_of::new("ab", 2);
+    assert_eq!(x.item, "ab");
+    assert_eq!(x, repeat_of::new("ab", 2));
+    assert_eq!(x.parse("abab;".span()), Some(vec![0..2, 2..4]));
+    assert_eq!(x.parse("abx".span()), None);
+
+    let x = between::new('(', ')');
+    assert_eq!(x.parse("(asd)".span()), Some(0..5));
+    assert_eq!(x.parse("(asd".span()), None);
+    assert_eq!(x.label("parens").name(), Some("parens"));
+}
@@ -1,9 +1,11 @@
+#![allow(clippy::type_complexity)]
+
 use crate::*;
 use std::collections::HashMap;
 use std::f64;
 use std::ops::Range;
 
-static CODE: &'static str =
+static CODE: &str =
     "{ \"a\": 1, \"b\": true, \"c\": [null, 1.5, false], \"d\": { \"v\": \"asd\" } }";
 
 #[test]
@@ -82,14 +84,15 @@ fn numberval(input: CharSpan) -> Option<JsonVal> {
         .and(
             one('0').or(num_start
                 .many1()
-                .and(num_body.many())
-                .map(range_of_many1_many)),
+                .map(range_of_many1)
+                .and(num_body.fold_many(None, |_, r| Some(r)))
+                .map(range_of_range_optrange)),
         )
         .map(range_of_optrange_range)
         .and(
             one('.')
-                .and(num_body.many1())
-                .map(range_of_range_many1)
+                .and(num_body.fold_many1(None, |_, r| Some(r)))
+                .map(range_of_range_optrange)
                 .may(),
         )
         .map(range_of_range_optrange)
@@ -98,15 +101,14 @@ fn numberval(input: CharSpan) -> Option<JsonVal> {
                 .or(one('E'))
                 .and(one('-').or(one('+')).may())
                 .map(range_of_range_optrange)
-                .and(num_body.many1())
-                .map(range_of_range_many1)
+                .and(num_body.fold_many1(None, |_, r| Some(r)))
+                .map(range_of_range_optrange)
                 .may(),
         )
         .map(range_of_range_optrange)
-        .map(|v: Range<usize>| {
+        .map_opt(|v: Range<usize>| {
             let s: String = input.com_string(v).unwrap();
-            let v: f64 = s.parse::<f64>().unwrap();
-            JsonVal::Number(v)
+            s.parse::<f64>().ok().map(JsonVal::Number)
         })
         .parse(input.ref_clone())
 }
@@ -124,18 +126,23 @@ fn stringval(input: CharSpan) -> Option<JsonResult> {
                     .or(one('n').map(|_| '\n'))
                     .or(one('r').map(|_| '\r'))
                     .or(one('t').map(|_| '\t'))
+                    .map(Result::<char, JsonParserError>::Ok)
                     .or(one('u')
                         .and(
-                            satisfy(|c: Char| c.char(|c: char, _| c.is_digit(16)).unwrap_or(false))
+                            satisfy(|c: Char| c.char(|c: char, _| c.is_ascii_hexdigit()).unwrap_or(false))
                                 .some(4),
                         )
-                        .map(|(_, u)| {
+                        .try_map(|(_, u)| {
                             let s = input.com_string(range_of_many1(u)).unwrap();
-                            let hex: u32 = u32::from_str_radix(&*s, 16).unwrap();
-                            let c = std::char::from_u32(hex).unwrap();
-                            c
+                            let hex: u32 = u32::from_str_radix(&s, 16).unwrap();
+                            std::char::from_u32(hex).ok_or("Unicode escape is not a char")
+                        })
+                        .map(|r| {
+                            r.map_err(|e| JsonParserError {
+                                loc: input.loc_range(e.range).unwrap(),
+                                msg: e.error.to_string(),
+                            })
                         }))
-                    .map(|v| Result::<char, JsonParserError>::Ok(v))
                     .or_trans(true, |i: CharSpan, ep| {
                         let loc = i.loc_range(ep).unwrap();
                         Err(JsonParserError {
@@ -186,8 +193,7 @@ fn stringval(input: CharSpan) -> Option<JsonResult> {
 
 fn whitespace(input: CharSpan) -> Option<()> {
     satisfy(|c: Char| c.is_wrap() || c == ' ' || c == '\t')
-        .many()
-        .map(|_| {})
+        .skip_many()
         .parse(input)
 }
 
@@ -197,7 +203,7 @@ fn value(input: CharSpan) -> Option<JsonResult> {
             stringval
                 .or(object)
                 .or(array)
-                .or(numberval.or(boolval).or(nullval).map(|v| Ok(v))),
+                .or(numberval.or(boolval).or(nullval).map(Ok)),
         )
         .and(whitespace)
         .map(|((_, v), _)| v)
@@ -249,7 +255,7 @@ fn object(input: CharSpan) -> Option<JsonResult> {
         whitespace
             .and(stringval)
             .and(whitespace)
-            .and(one(':').map(|v| Ok(v)).or_trans(true, |i: CharSpan, ep| {
+            .and(one(':').map(Ok).or_trans(true, |i: CharSpan, ep| {
                 let loc = i.loc_range(ep).unwrap();
                 Err(JsonParserError {
                     loc,
@@ -462,6 +468,18 @@ fn test_string5() {
     assert_eq!(r, Some(Err(err)))
 }
 
+#[test]
+fn test_string6() {
+    let code = "\"\\uD800\"".span();
+    let r = stringval.parse(code);
+    println!("{:?}", r);
+    let err = JsonParserError {
+        loc: (2, 0, 2, 6, 0, 6).into(),
+        msg: "Unicode escape is not a char".to_string(),
+    };
+    assert_eq!(r, Some(Err(err)))
+}
+
 #[test]
 fn test_string_none() {
     let code = "asd".span();
@@ -0,0 +1,6 @@
+#[test]
+fn test_def_parser() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/def_parser_forms.rs");
+    t.compile_fail("tests/ui/def_parser_no_input_type.rs");
+}
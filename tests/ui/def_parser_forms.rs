@@ -0,0 +1,72 @@
+use parser_fuck::*;
+use std::ops::Range;
+
+type Bytes = Span<std::vec::IntoIter<u8>>;
+
+// plain
+def_parser! {
+    zero(input: Bytes) -> u8 {
+        let r = input.next().filter(|b| *b == 0);
+        input.do_ready();
+        r
+    }
+}
+
+// lifetime
+def_parser! {
+    /// A digit
+    pub digit<'a>(input: CharSpan<'a>) -> Range<usize> {
+        satisfy(|c: Char| c.char(|c, _| c.is_ascii_digit()).unwrap_or(false)).parse(input)
+    }
+}
+
+// generic input
+def_parser! {
+    any_item[I](input) -> I::Item {
+        let r = input.next()?;
+        input.do_ready();
+        Some(r)
+    }
+}
+
+// fields
+def_parser! {
+    #[derive(PartialEq)]
+    pub(crate) word<'a> { pub text: &'a str } (input: CharSpan<'a>) -> Range<usize> {
+        substr(text).parse(input)
+    }
+}
+
+// where clause
+def_parser! {
+    open[I](input) -> Range<usize>
+    where
+        I::Item: PartialEq<char>,
+    {
+        one('(').parse(input)
+    }
+}
+
+// fields and where clause
+def_parser! {
+    times[I] { n: usize } (input) -> Vec<I::Item>
+    where
+        I::Item: Clone,
+    {
+        any_item::new().some(*n).parse(input)
+    }
+}
+
+fn main() {
+    assert_eq!(zero::new().parse(Span::new(vec![0u8].into_iter())), Some(0));
+    assert_eq!(digit::new().parse("1".span()), Some(0..1));
+    assert_eq!(any_item::new().parse("a".span()).map(char::from), Some('a'));
+    assert_eq!(word::new("ab").parse("ab".span()), Some(0..2));
+    assert!(word::new("ab") == word::new("ab"));
+    assert_eq!(open::new().parse("(".span()), Some(0..1));
+    assert_eq!(
+        times::new(2).parse(Span::new(vec![1, 2].into_iter())),
+        Some(vec![1, 2])
+    );
+    assert_eq!(times::<CharSpan>::new(1).name(), Some("times"));
+}
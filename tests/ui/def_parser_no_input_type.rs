@@ -0,0 +1,7 @@
+use parser_fuck::*;
+
+def_parser! {
+    any(input) -> () { None }
+}
+
+fn main() {}